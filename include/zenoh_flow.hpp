@@ -1,9 +1,23 @@
 #pragma once
 #include <algorithm>
+#include <chrono>
+#include <cstdint>
+#include <map>
 #include <memory>
+#include <string>
 #include <vector>
 #include "rust/cxx.h"
 #include <iostream>
+#include "zenoh_flow_codec.hpp"
+#ifdef ZENOH_FLOW_JSON
+#include "zenoh_flow_json.hpp"
+#endif
+#ifdef ZENOH_FLOW_PROTOBUF
+#include "zenoh_flow_protobuf.hpp"
+#endif
+#ifdef ZENOH_FLOW_FLATBUFFERS
+#include "zenoh_flow_flatbuffers.hpp"
+#endif
 
 namespace zenoh {
 namespace flow {
@@ -15,6 +29,284 @@ struct Output;
 struct Data;
 struct Configuration;
 struct ConfigurationMap;
+enum class Threading : std::uint8_t;
+
+//
+// Encodings of the payloads, set in `Output::encoding` and received in
+// `Input::encoding` / `Token::payload->encoding()`. An empty encoding (the default, e.g.
+// `Output { "port", data }`) leaves the payload untouched on the wire, which is
+// what Rust components expect.
+//
+namespace encoding {
+
+const std::string json = "application/json";
+const std::string cbor = "application/cbor";
+const std::string protobuf = "application/protobuf";
+const std::string flatbuffers = "application/flatbuffers";
+
+} // namespace encoding
+
+//
+// Metadata of the messages, `Input::metadata` / `Token::payload->metadata()` /
+// `Output::metadata`: key/value pairs transmitted next to the payload, like its
+// encoding, on the ports carrying envelopes only: the outputs listed in
+// `envelope_outputs` of the producer, and the inputs listed in
+// `envelope_inputs` of the consumer ("true" for every port). On the other
+// ports, the payload travels alone, as Rust components expect it.
+// Operators forward the metadata of their inputs to their outputs if
+// `forward_metadata` is set to "true" in their configuration; what is set on an
+// output takes precedence.
+//
+namespace metadata {
+
+template <typename Attributes>
+inline std::string get(const Attributes &metadata, const std::string &key,
+                       const std::string &fallback = "") {
+  for (const auto &attribute : metadata) {
+    if (std::string(attribute.key) == key) {
+      return std::string(attribute.value);
+    }
+  }
+  return fallback;
+}
+
+template <typename Attributes>
+inline void set(Attributes &metadata, const std::string &key,
+                const std::string &value) {
+  for (auto &attribute : metadata) {
+    if (std::string(attribute.key) == key) {
+      attribute.value = value;
+      return;
+    }
+  }
+  metadata.push_back({key, value});
+}
+
+} // namespace metadata
+
+//
+// Tokens: `input_rule` gets the status, timestamp and port of each token for
+// free. Its content is only read, and copied, if the rule asks for it through
+// `Token::payload`: `data()`, `encoding()` and `metadata()`. The payload is a
+// `rust::Box`, that cannot be copied: iterate over the tokens by reference.
+//
+//   for (const auto &token : tokens) {
+//     if (token.status == TokenStatus::Ready && token.payload->data().size() > 0) { ... }
+//   }
+//
+
+//
+// Inputs are not copied: the payload of an input is read in place, in the
+// message received by the wrapper, through `Input::shared->data()`, and
+// `Input::data` is empty. Only a compressed payload is in `Input::data`, where
+// the wrapper decompressed it.
+//
+// Shared memory, when the producer of a payload sets `shm_threshold` in its
+// configuration: a payload of at least that size, sent on a port carrying
+// envelopes by another runtime of the host, is mapped rather than copied. It
+// is also in `Input::shared->data()`. `payload` returns the bytes of an input
+// wherever they are:
+//
+//   auto number = codec::decode<std::uint64_t>(payload(input));
+//
+// `Input::shared` is a `rust::Box`, that cannot be copied: iterate over the
+// inputs by reference.
+//
+template <typename I>
+inline rust::Slice<const std::uint8_t> payload(const I &input) {
+  if (input.data.empty()) {
+    return input.shared->data();
+  }
+  return rust::Slice<const std::uint8_t>(input.data.data(), input.data.size());
+}
+
+//
+// Several outputs on the same port in one call to `run` are an error, unless
+// `output_sequences` is set to "true" in the configuration: they are then sent
+// together, in order, as one message that the wrapper of a C++ component
+// downstream delivers as distinct inputs. No Rust component can read it, the
+// port must carry envelopes (see `envelope_outputs` above):
+//
+//   configuration:
+//     envelope_outputs: "fizz"
+//     output_sequences: "true"
+//
+
+//
+// Opaque inputs: with `opaque_inputs` set to "true" in the configuration of an
+// operator, the Rust values it receives from components of the same runtime
+// are not serialized. `Input::data` is then empty and `Input::handle` is set;
+// an output forwards the value as is by setting `Output::handle` to it, its
+// `data`, `encoding` and `metadata` are ignored:
+//
+//   for (const auto &input : inputs) {
+//     if (input.handle != opaque::none) {
+//       Output output { "out" };
+//       output.handle = input.handle;
+//       outputs.push_back(std::move(output));
+//     }
+//   }
+//
+// `payload(input)` still gives the bytes of an opaque input, serialized on
+// first access. A handle is only valid in the call to `run` that receives it.
+// Always initialize an output with braces, `Output output{};` if nothing is
+// known yet: `handle` and `port` are integers, indeterminate otherwise.
+//
+namespace opaque {
+// Handle of an input or output that is not an opaque value.
+constexpr std::uint64_t none = 0;
+} // namespace opaque
+
+//
+// Port handles: each port declared in the configuration has a handle,
+// `Input::port` / `Token::port`, its index among the inputs (or the outputs)
+// of the component sorted by id. `zenoh-flow-cxx-graphs --headers <dir>`
+// generates them as constants, `ports::input::<id>` and
+// `ports::output::<id>`. An output may leave `port_id` empty and give its
+// `Output::port` instead. With `port_handles` set to "true" in the
+// configuration, the wrapper leaves `Input::port_id` / `Token::port_id` empty
+// as well: no string is allocated per message.
+//
+namespace ports {
+// Handle of a port that is not declared in the configuration.
+constexpr std::uint32_t none = UINT32_MAX;
+} // namespace ports
+
+//
+// Buffer pool: `allocate(size)` (declared by the bridges of the components with
+// outputs) returns a buffer of `size` bytes for `Output::data`. With
+// `buffer_pool` set to a number of buffers in the configuration, the buffers
+// return to a pool once the messages are dropped downstream and `allocate`
+// reuses them: their content is then whatever the previous message left.
+// Each instance has its own pool; `allocate` is only pooled when called from
+// `run`.
+//
+
+//
+// Instances: a library can be used by several components of a graph, each one
+// with its own configuration and `State`. The wrapper gives each instance a
+// name, the id of the component when the descriptor is generated by
+// `zenoh-flow-cxx-graphs`, as `instance` in its configuration: keep it in the
+// `State` to tell the instances apart in logs. Keep anything per instance in
+// the `State` rather than in globals: nothing else is shared between instances.
+//
+
+//
+// Threading: the runtime calls a component from any thread of its executor.
+// `threading(configuration)`, called before `initialize`, declares what the
+// `State` tolerates, and the wrapper enforces it:
+//
+//   - `Threading::Affine`: the `State` is created, used and destroyed on a
+//     thread of its own (thread-local storage, GPU contexts, ...),
+//   - `Threading::Send`: the `State` may move between threads, one call at a
+//     time,
+//   - `Threading::Concurrent`: the `State` may be used from any thread, at any
+//     time: the wrapper calls it in place, without thread nor lock.
+//
+// The runtime currently serializes the calls to a component: a `Concurrent`
+// `State` is not called from several threads at once yet, but must not rely
+// on it.
+//
+//   Threading threading(const ConfigurationMap &configuration) {
+//     return Threading::Send;
+//   }
+//
+
+//
+// Checkpoints, for the operators whose bridge declares them, when `checkpoint`
+// is set to a directory in the configuration: `snapshot(state)` serializes the
+// `State` every `checkpoint_period` calls to `run` (100 by default) and when
+// the component is cleaned, and the next start of the instance calls
+// `restore(configuration, snapshot)` instead of `initialize`. A `restore` that
+// throws falls back to `initialize`. The instance must be named (see
+// Instances) for its snapshot to be found again:
+//
+//   rust::Vec<std::uint8_t> snapshot(const std::unique_ptr<State> &state) {
+//     rust::Vec<std::uint8_t> bytes;
+//     codec::encode_into(bytes, state->counter);
+//     return bytes;
+//   }
+//
+//   std::unique_ptr<State> restore(const ConfigurationMap &configuration,
+//                                  rust::Slice<const std::uint8_t> snapshot) {
+//     return std::make_unique<State>(codec::decode<std::uint8_t>(snapshot));
+//   }
+//
+
+//
+// Batched mode, when `batch_size` is set in the configuration of the component:
+// `run` receives several inputs per port, in their order of arrival, and
+// `Token::pending` tells `input_rule` how many messages already wait on a port.
+// With `batch_timeout_ms`, the batch is also delivered once its oldest input
+// waited that long: when the next message arrives, or when a message arrives on
+// the input named by `batch_flush` (a clock, never delivered to the component
+// nor seen by `input_rule`).
+// `by_port` groups the inputs of a batch:
+//
+//   for (auto &port : by_port(inputs)) {
+//     for (const Input *input : port.second) { ... }
+//   }
+//
+template <typename Inputs>
+inline std::map<std::string, std::vector<const typename Inputs::value_type *>>
+by_port(const Inputs &inputs) {
+  std::map<std::string, std::vector<const typename Inputs::value_type *>> ports;
+  for (const auto &input : inputs) {
+    ports[std::string(input.port_id)].push_back(&input);
+  }
+  return ports;
+}
+
+// Same as `by_port`, by handle: for `port_handles` set to "true".
+template <typename Inputs>
+inline std::map<std::uint32_t, std::vector<const typename Inputs::value_type *>>
+by_handle(const Inputs &inputs) {
+  std::map<std::uint32_t, std::vector<const typename Inputs::value_type *>> ports;
+  for (const auto &input : inputs) {
+    ports[input.port].push_back(&input);
+  }
+  return ports;
+}
+
+//
+// Tracing, when `trace` is set in the configuration of the component: the
+// wrapper records a span around each call to `run`, whose W3C `traceparent` is
+// `Context::trace_parent`. The component adds spans below it with a `Scope`,
+// that records a span from its construction to its destruction:
+//
+//   {
+//     trace::Scope<Context> scope(context, "inference");
+//     ...
+//   }
+//
+namespace trace {
+
+// Nanoseconds since the UNIX epoch.
+inline std::uint64_t now() {
+  return static_cast<std::uint64_t>(
+      std::chrono::duration_cast<std::chrono::nanoseconds>(
+          std::chrono::system_clock::now().time_since_epoch())
+          .count());
+}
+
+template <typename C>
+class Scope {
+private:
+  C &context;
+  std::string name;
+  std::uint64_t start;
+
+public:
+  Scope(C &context, const std::string &name)
+      : context(context), name(name), start(now()) {}
+
+  Scope(const Scope &) = delete;
+  Scope &operator=(const Scope &) = delete;
+
+  ~Scope() { context.spans.push_back({name, start, now()}); }
+};
+
+} // namespace trace
 
 } // namespace flow
 } // namespace zenoh
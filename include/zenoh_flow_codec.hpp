@@ -0,0 +1,251 @@
+#pragma once
+#include <cstdint>
+#include <cstring>
+#include <stdexcept>
+#include <string>
+#include <tuple>
+#include <type_traits>
+#include <utility>
+#include <vector>
+#include "rust/cxx.h"
+
+//
+// Binary encoding of the primitive types of the descriptors, identical to the
+// one of `zenoh_flow_cxx_common::codec` (i.e. `bincode` with its default
+// options) on the Rust side:
+//
+// - integers: fixed width, little-endian (`u8` .. `u64`, `i8` .. `i64`),
+// - `f32` / `f64`: IEEE 754, little-endian,
+// - `bool`: one byte, 0 or 1,
+// - `string`: length as a `u64`, followed by the UTF-8 bytes,
+// - arrays (`std::vector<T>`): length as a `u64`, followed by the elements,
+// - tuples (`std::pair`, `std::tuple`) and structures: their fields, in order.
+//
+namespace zenoh {
+namespace flow {
+namespace codec {
+
+using Bytes = rust::Vec<std::uint8_t>;
+
+template <typename T>
+inline void encode_integer(Bytes &bytes, T value) {
+  static_assert(std::is_integral<T>::value, "Integers only");
+  using U = typename std::make_unsigned<T>::type;
+  U unsigned_value = static_cast<U>(value);
+  for (std::size_t i = 0; i < sizeof(T); ++i) {
+    bytes.push_back(static_cast<std::uint8_t>(unsigned_value >> (8 * i)));
+  }
+}
+
+inline void encode_into(Bytes &bytes, std::uint8_t value) { bytes.push_back(value); }
+inline void encode_into(Bytes &bytes, std::uint16_t value) { encode_integer(bytes, value); }
+inline void encode_into(Bytes &bytes, std::uint32_t value) { encode_integer(bytes, value); }
+inline void encode_into(Bytes &bytes, std::uint64_t value) { encode_integer(bytes, value); }
+inline void encode_into(Bytes &bytes, std::int8_t value) { encode_integer(bytes, value); }
+inline void encode_into(Bytes &bytes, std::int16_t value) { encode_integer(bytes, value); }
+inline void encode_into(Bytes &bytes, std::int32_t value) { encode_integer(bytes, value); }
+inline void encode_into(Bytes &bytes, std::int64_t value) { encode_integer(bytes, value); }
+
+inline void encode_into(Bytes &bytes, bool value) {
+  bytes.push_back(value ? 1 : 0);
+}
+
+inline void encode_into(Bytes &bytes, float value) {
+  std::uint32_t bits;
+  std::memcpy(&bits, &value, sizeof(bits));
+  encode_integer(bytes, bits);
+}
+
+inline void encode_into(Bytes &bytes, double value) {
+  std::uint64_t bits;
+  std::memcpy(&bits, &value, sizeof(bits));
+  encode_integer(bytes, bits);
+}
+
+inline void encode_into(Bytes &bytes, const std::string &value) {
+  encode_integer(bytes, static_cast<std::uint64_t>(value.size()));
+  for (char c : value) {
+    bytes.push_back(static_cast<std::uint8_t>(c));
+  }
+}
+
+inline void encode_into(Bytes &bytes, const char *value) {
+  encode_into(bytes, std::string(value));
+}
+
+// Declared first, so that they can be nested in any order.
+template <typename T>
+inline void encode_into(Bytes &bytes, const std::vector<T> &values);
+template <typename A, typename B>
+inline void encode_into(Bytes &bytes, const std::pair<A, B> &value);
+template <std::size_t I = 0, typename... T>
+inline typename std::enable_if<I == sizeof...(T)>::type
+encode_into(Bytes &bytes, const std::tuple<T...> &value);
+template <std::size_t I = 0, typename... T>
+inline typename std::enable_if<I < sizeof...(T)>::type
+encode_into(Bytes &bytes, const std::tuple<T...> &value);
+
+template <typename T>
+inline void encode_into(Bytes &bytes, const std::vector<T> &values) {
+  encode_integer(bytes, static_cast<std::uint64_t>(values.size()));
+  for (const T &value : values) {
+    encode_into(bytes, value);
+  }
+}
+
+template <typename A, typename B>
+inline void encode_into(Bytes &bytes, const std::pair<A, B> &value) {
+  encode_into(bytes, value.first);
+  encode_into(bytes, value.second);
+}
+
+template <std::size_t I, typename... T>
+inline typename std::enable_if<I == sizeof...(T)>::type
+encode_into(Bytes &, const std::tuple<T...> &) {}
+
+template <std::size_t I, typename... T>
+inline typename std::enable_if<I < sizeof...(T)>::type
+encode_into(Bytes &bytes, const std::tuple<T...> &value) {
+  encode_into(bytes, std::get<I>(value));
+  encode_into<I + 1>(bytes, value);
+}
+
+template <typename T>
+inline Bytes encode(const T &value) {
+  Bytes bytes;
+  encode_into(bytes, value);
+  return bytes;
+}
+
+class Decoder {
+private:
+  const std::uint8_t *data;
+  std::size_t size;
+  std::size_t offset;
+
+  const std::uint8_t *take(std::size_t count) {
+    if (size - offset < count) {
+      throw std::out_of_range("Truncated payload: cannot decode " +
+                              std::to_string(count) + " more bytes");
+    }
+    const std::uint8_t *start = data + offset;
+    offset += count;
+    return start;
+  }
+
+  template <typename T>
+  T read_integer() {
+    static_assert(std::is_integral<T>::value, "Integers only");
+    using U = typename std::make_unsigned<T>::type;
+    const std::uint8_t *bytes = take(sizeof(T));
+    U value = 0;
+    for (std::size_t i = 0; i < sizeof(T); ++i) {
+      value |= static_cast<U>(static_cast<U>(bytes[i]) << (8 * i));
+    }
+    return static_cast<T>(value);
+  }
+
+public:
+  Decoder(const std::uint8_t *data, std::size_t size)
+      : data(data), size(size), offset(0) {}
+  explicit Decoder(const Bytes &bytes)
+      : data(bytes.data()), size(bytes.size()), offset(0) {}
+
+  bool done() const { return offset == size; }
+
+  void read(std::uint8_t &value) { value = *take(1); }
+  void read(std::uint16_t &value) { value = read_integer<std::uint16_t>(); }
+  void read(std::uint32_t &value) { value = read_integer<std::uint32_t>(); }
+  void read(std::uint64_t &value) { value = read_integer<std::uint64_t>(); }
+  void read(std::int8_t &value) { value = read_integer<std::int8_t>(); }
+  void read(std::int16_t &value) { value = read_integer<std::int16_t>(); }
+  void read(std::int32_t &value) { value = read_integer<std::int32_t>(); }
+  void read(std::int64_t &value) { value = read_integer<std::int64_t>(); }
+
+  void read(bool &value) {
+    std::uint8_t byte = *take(1);
+    if (byte > 1) {
+      throw std::invalid_argument("Invalid bool: " + std::to_string(byte));
+    }
+    value = byte == 1;
+  }
+
+  void read(float &value) {
+    std::uint32_t bits = read_integer<std::uint32_t>();
+    std::memcpy(&value, &bits, sizeof(value));
+  }
+
+  void read(double &value) {
+    std::uint64_t bits = read_integer<std::uint64_t>();
+    std::memcpy(&value, &bits, sizeof(value));
+  }
+
+  void read(std::string &value) {
+    std::uint64_t length = read_integer<std::uint64_t>();
+    const std::uint8_t *bytes = take(static_cast<std::size_t>(length));
+    value.assign(reinterpret_cast<const char *>(bytes),
+                 static_cast<std::size_t>(length));
+  }
+
+  template <typename T>
+  void read(std::vector<T> &values) {
+    std::uint64_t length = read_integer<std::uint64_t>();
+    values.clear();
+    for (std::uint64_t i = 0; i < length; ++i) {
+      T value;
+      read(value);
+      values.push_back(value);
+    }
+  }
+
+  template <typename A, typename B>
+  void read(std::pair<A, B> &value) {
+    read(value.first);
+    read(value.second);
+  }
+
+  template <std::size_t I = 0, typename... T>
+  typename std::enable_if<I == sizeof...(T)>::type
+  read(std::tuple<T...> &) {}
+
+  template <std::size_t I = 0, typename... T>
+  typename std::enable_if<I < sizeof...(T)>::type
+  read(std::tuple<T...> &value) {
+    read(std::get<I>(value));
+    read<I + 1>(value);
+  }
+
+  template <typename T>
+  T decode() {
+    T value;
+    read(value);
+    return value;
+  }
+};
+
+//
+// Decodes a whole payload: trailing bytes are an error.
+//
+template <typename T>
+inline T decode(const std::uint8_t *data, std::size_t size) {
+  Decoder decoder(data, size);
+  T value = decoder.decode<T>();
+  if (!decoder.done()) {
+    throw std::invalid_argument("Trailing bytes after the decoded value");
+  }
+  return value;
+}
+
+template <typename T>
+inline T decode(const Bytes &bytes) {
+  return decode<T>(bytes.data(), bytes.size());
+}
+
+template <typename T>
+inline T decode(rust::Slice<const std::uint8_t> bytes) {
+  return decode<T>(bytes.data(), bytes.size());
+}
+
+} // namespace codec
+} // namespace flow
+} // namespace zenoh
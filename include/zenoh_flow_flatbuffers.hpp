@@ -0,0 +1,135 @@
+#pragma once
+#include <cstdint>
+#include <cstring>
+#include <stdexcept>
+#include <string>
+#include "flatbuffers/flatbuffers.h"
+#include "rust/cxx.h"
+
+//
+// FlatBuffers payloads, available when the component is built with the
+// `flatbuffers` cargo feature.
+//
+// A port carries FlatBuffers when its type, in the descriptor, is
+// `flatbuffers:<root table>`. Inputs are read in place through
+// `view<T>(payload(input))`, outputs are built with a `Builder` that writes
+// directly into the bytes of the `Output`. Rust components read the same payloads with the code generated by
+// `flatc --rust` (see `zenoh_flow_cxx_common::flatbuffers`).
+//
+namespace zenoh {
+namespace flow {
+namespace flatbuffers {
+
+const std::string type_prefix = "flatbuffers:";
+
+//
+// Verifies `bytes` and returns its root table. Nothing is copied: the view is
+// valid as long as `bytes` is.
+//
+template <typename T>
+inline const T *view(const std::uint8_t *data, std::size_t size) {
+  ::flatbuffers::Verifier verifier(data, size);
+  if (!verifier.VerifyBuffer<T>(nullptr)) {
+    throw std::invalid_argument("Invalid FlatBuffer");
+  }
+  return ::flatbuffers::GetRoot<T>(data);
+}
+
+template <typename T>
+inline const T *view(rust::Slice<const std::uint8_t> bytes) {
+  return view<T>(bytes.data(), bytes.size());
+}
+
+template <typename T>
+inline const T *view(const rust::Vec<std::uint8_t> &bytes) {
+  return view<T>(bytes.data(), bytes.size());
+}
+
+} // namespace flatbuffers
+
+// Buffers of the outputs, defined by the bridge of the component (see
+// `zenoh_flow_cxx_common::pool`).
+rust::Vec<std::uint8_t> allocate(std::size_t size) noexcept;
+
+namespace flatbuffers {
+
+//
+// Allocator of a `FlatBufferBuilder` backed by a `rust::Vec`: the builder
+// writes directly into the bytes that end up in the `Output`. Each allocation
+// is one call to `allocate`, that may reuse a pooled buffer.
+//
+class VecAllocator : public ::flatbuffers::Allocator {
+private:
+  rust::Vec<std::uint8_t> &bytes;
+
+public:
+  explicit VecAllocator(rust::Vec<std::uint8_t> &bytes) : bytes(bytes) {}
+
+  std::uint8_t *allocate(std::size_t size) override {
+    bytes = flow::allocate(size);
+    return bytes.data();
+  }
+
+  // The memory belongs to the `rust::Vec`.
+  void deallocate(std::uint8_t *, std::size_t) override {}
+
+  //
+  // A `FlatBufferBuilder` fills its buffer from the back: when growing, the
+  // bytes in use at the back are copied to the new back, the ones at the front
+  // to the new front.
+  //
+  std::uint8_t *reallocate_downward(std::uint8_t *old_p, std::size_t old_size,
+                                    std::size_t new_size, std::size_t in_use_back,
+                                    std::size_t in_use_front) override {
+    rust::Vec<std::uint8_t> grown = flow::allocate(new_size);
+    std::uint8_t *p = grown.data();
+    std::memcpy(p + new_size - in_use_back, old_p + old_size - in_use_back,
+                in_use_back);
+    std::memcpy(p, old_p, in_use_front);
+    bytes = std::move(grown);
+    return p;
+  }
+};
+
+//
+// Builds a FlatBuffer directly into the bytes of an `Output`:
+//
+//   flatbuffers::Builder builder;
+//   auto imu = CreateImu(builder.fbb(), ...);
+//   Output output { "imu", builder.finish(imu) };
+//
+class Builder {
+private:
+  rust::Vec<std::uint8_t> bytes;
+  VecAllocator allocator;
+  ::flatbuffers::FlatBufferBuilder builder;
+
+public:
+  explicit Builder(std::size_t initial_size = 1024)
+      : bytes(), allocator(bytes), builder(initial_size, &allocator, false) {}
+
+  Builder(const Builder &) = delete;
+  Builder &operator=(const Builder &) = delete;
+
+  ::flatbuffers::FlatBufferBuilder &fbb() { return builder; }
+
+  //
+  // Finishes the buffer and returns its bytes. The finished buffer lies at the
+  // back of the allocation: it is moved to the front, in place, and the rest is
+  // truncated. The builder must not be used afterwards.
+  //
+  template <typename T>
+  rust::Vec<std::uint8_t> finish(::flatbuffers::Offset<T> root) {
+    builder.Finish(root);
+    std::size_t size = builder.GetSize();
+    std::size_t offset =
+        static_cast<std::size_t>(builder.GetBufferPointer() - bytes.data());
+    std::memmove(bytes.data(), bytes.data() + offset, size);
+    bytes.truncate(size);
+    return std::move(bytes);
+  }
+};
+
+} // namespace flatbuffers
+} // namespace flow
+} // namespace zenoh
@@ -0,0 +1,184 @@
+#pragma once
+#include <cstdint>
+#include <functional>
+#include <map>
+#include <memory>
+#include <stdexcept>
+#include <string>
+#include <utility>
+#include <vector>
+#include "rust/cxx.h"
+#include "zenoh_flow.hpp"
+
+//
+// Operator fusion: a composite C++ operator runs an ordered chain of stages,
+// each one a small C++ operator, in one call to `run`. The outputs of a stage
+// are the inputs of the next one, on the ports of the same ids: they are
+// passed in memory, without serialization nor scheduling by the runtime. The
+// inputs of the composite go to the first stage, the outputs of the last stage
+// are its outputs.
+//
+// With `opaque_inputs`, an opaque input reaches the first stage with an empty
+// `data` and its `handle`: a stage forwarding the message untouched forwards
+// the Rust value, see `opaque` in `zenoh_flow.hpp`.
+//
+// The chain is set in the configuration of the component, the configuration
+// of a stage is given as `<stage>/<key>`:
+//
+//   configuration:
+//     chain: "fizzbuzz,shout"
+//     shout/suffix: "!"
+//
+// The library names its stages and forwards `initialize` and `run` to a
+// `Chain`:
+//
+//   const std::map<std::string, fusion::Factory> stages {
+//     { "fizzbuzz", [](const fusion::Configuration &configuration) {
+//         return std::unique_ptr<fusion::Stage>(new FizzBuzz());
+//       } },
+//     ...
+//   };
+//
+//   State::State(const ConfigurationMap &configuration)
+//     : chain(stages, configuration) {}
+//
+//   rust::Vec<Output> run(Context &context, std::unique_ptr<State> &state,
+//                         rust::Vec<Input> inputs) {
+//     return state->chain.run<Output>(context, std::move(inputs));
+//   }
+//
+namespace zenoh {
+namespace flow {
+namespace fusion {
+
+//
+// A message between two stages.
+//
+struct Message {
+  std::string port_id;
+  rust::Vec<std::uint8_t> data;
+  std::string encoding;
+  std::vector<std::pair<std::string, std::string>> metadata;
+  std::uint64_t timestamp;
+  // Handle of the opaque input the message forwards, `opaque::none` otherwise.
+  std::uint64_t handle;
+};
+
+using Configuration = std::map<std::string, std::string>;
+
+class Stage {
+public:
+  virtual ~Stage() = default;
+  virtual std::vector<Message> run(std::vector<Message> inputs) = 0;
+};
+
+using Factory =
+    std::function<std::unique_ptr<Stage>(const Configuration &configuration)>;
+
+class Chain {
+public:
+  //
+  // Builds the stages listed in `chain`. An invalid chain is reported by the
+  // first call to `run`: `initialize` cannot fail.
+  //
+  template <typename ConfigurationMap>
+  Chain(const std::map<std::string, Factory> &factories,
+        const ConfigurationMap &map) {
+    std::string chain;
+    std::map<std::string, Configuration> configurations;
+    for (const auto &entry : map.map) {
+      std::string key = std::string(entry.key);
+      std::size_t separator = key.find('/');
+      if (key == "chain") {
+        chain = std::string(entry.value);
+      } else if (separator != std::string::npos) {
+        configurations[key.substr(0, separator)][key.substr(separator + 1)] =
+            std::string(entry.value);
+      }
+    }
+
+    std::size_t start = 0;
+    while (start <= chain.size()) {
+      std::size_t end = chain.find(',', start);
+      if (end == std::string::npos) {
+        end = chain.size();
+      }
+      std::string name = chain.substr(start, end - start);
+      start = end + 1;
+      if (name.empty()) {
+        continue;
+      }
+
+      auto factory = factories.find(name);
+      if (factory == factories.end()) {
+        error = "Unknown stage `" + name + "` in the chain";
+        return;
+      }
+      stages.emplace_back(name, factory->second(configurations[name]));
+    }
+
+    if (stages.empty()) {
+      error = "No stage: set `chain` in the configuration";
+    }
+  }
+
+  template <typename Output, typename Context, typename Input>
+  rust::Vec<Output> run(Context &context, rust::Vec<Input> inputs) {
+    if (!error.empty()) {
+      throw std::invalid_argument(error);
+    }
+
+    std::vector<Message> messages;
+    messages.reserve(inputs.size());
+    for (auto &input : inputs) {
+      Message message;
+      message.port_id = std::string(input.port_id);
+      message.handle = input.handle;
+      if (input.data.empty() && input.handle == opaque::none) {
+        auto shared = payload(input);
+        message.data.reserve(shared.size());
+        for (std::uint8_t byte : shared) {
+          message.data.push_back(byte);
+        }
+      } else {
+        message.data = std::move(input.data);
+      }
+      message.encoding = std::string(input.encoding);
+      for (const auto &attribute : input.metadata) {
+        message.metadata.emplace_back(std::string(attribute.key),
+                                      std::string(attribute.value));
+      }
+      message.timestamp = input.timestamp;
+      messages.push_back(std::move(message));
+    }
+
+    for (auto &stage : stages) {
+      trace::Scope<Context> scope(context, stage.first);
+      messages = stage.second->run(std::move(messages));
+    }
+
+    using Attribute = typename decltype(Output::metadata)::value_type;
+    rust::Vec<Output> outputs;
+    outputs.reserve(messages.size());
+    for (auto &message : messages) {
+      Output output{};
+      output.port_id = message.port_id;
+      output.data = std::move(message.data);
+      output.encoding = message.encoding;
+      output.handle = message.handle;
+      for (const auto &attribute : message.metadata) {
+        output.metadata.push_back(Attribute{attribute.first, attribute.second});
+      }
+      outputs.push_back(std::move(output));
+    }
+    return outputs;
+  }
+
+private:
+  std::vector<std::pair<std::string, std::unique_ptr<Stage>>> stages;
+  std::string error;
+};
+
+} // namespace fusion
+} // namespace flow
+} // namespace zenoh
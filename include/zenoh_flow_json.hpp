@@ -0,0 +1,81 @@
+#pragma once
+#include <cstdint>
+#include <string>
+#include <vector>
+#include <nlohmann/json.hpp>
+#include "rust/cxx.h"
+
+//
+// Self-describing payloads, available when the component is built with the
+// `json` cargo feature (and nlohmann/json is installed): a `json::Value`, i.e.
+// a `nlohmann::json`, exchanged as JSON text (`json::encode` / `json::decode`,
+// port type `json`) or as CBOR (`cbor::encode` / `cbor::decode`, port type
+// `cbor`). The Rust wrappers pretty-print both (see
+// `zenoh_flow_cxx_common::pretty`), Rust components read them with
+// `serde_json` and `ciborium`.
+//
+//   json::Value command;
+//   command["speed"] = 1.5;
+//   command["frames"].push_back(42);
+//   Output output { "command", cbor::encode(command) };
+//
+// Decoding throws `json::Value::exception` on an invalid payload, raised in
+// Rust as the error of the call.
+//
+namespace zenoh {
+namespace flow {
+namespace json {
+
+using Value = nlohmann::json;
+
+inline rust::Vec<std::uint8_t> encode(const Value &value) {
+  std::string text = value.dump();
+  rust::Vec<std::uint8_t> bytes;
+  bytes.reserve(text.size());
+  for (char c : text) {
+    bytes.push_back(static_cast<std::uint8_t>(c));
+  }
+  return bytes;
+}
+
+inline Value decode(const std::uint8_t *data, std::size_t size) {
+  return Value::parse(data, data + size);
+}
+
+inline Value decode(rust::Slice<const std::uint8_t> bytes) {
+  return decode(bytes.data(), bytes.size());
+}
+
+inline Value decode(const rust::Vec<std::uint8_t> &bytes) {
+  return decode(bytes.data(), bytes.size());
+}
+
+} // namespace json
+
+namespace cbor {
+
+inline rust::Vec<std::uint8_t> encode(const json::Value &value) {
+  std::vector<std::uint8_t> encoded = json::Value::to_cbor(value);
+  rust::Vec<std::uint8_t> bytes;
+  bytes.reserve(encoded.size());
+  for (std::uint8_t byte : encoded) {
+    bytes.push_back(byte);
+  }
+  return bytes;
+}
+
+inline json::Value decode(const std::uint8_t *data, std::size_t size) {
+  return json::Value::from_cbor(data, data + size);
+}
+
+inline json::Value decode(rust::Slice<const std::uint8_t> bytes) {
+  return decode(bytes.data(), bytes.size());
+}
+
+inline json::Value decode(const rust::Vec<std::uint8_t> &bytes) {
+  return decode(bytes.data(), bytes.size());
+}
+
+} // namespace cbor
+} // namespace flow
+} // namespace zenoh
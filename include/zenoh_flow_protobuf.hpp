@@ -0,0 +1,68 @@
+#pragma once
+#include <cstdint>
+#include <stdexcept>
+#include <string>
+#include "rust/cxx.h"
+
+//
+// Protocol Buffers payloads, available when the component is built with the
+// `protobuf` cargo feature.
+//
+// A port carries protobuf messages when its type, in the descriptor, is
+// `protobuf:<full name>`. The payloads are the protobuf wire encoding of the
+// messages: Rust components exchange them with `prost` (see
+// `zenoh_flow_cxx_common::protobuf`).
+//
+namespace zenoh {
+namespace flow {
+namespace protobuf {
+
+const std::string type_prefix = "protobuf:";
+
+template <typename Message>
+inline Message decode(const std::uint8_t *data, std::size_t size) {
+  Message message;
+  if (!message.ParseFromArray(data, static_cast<int>(size))) {
+    throw std::invalid_argument("Could not parse a `" +
+                                message.GetTypeName() + "` message");
+  }
+  return message;
+}
+
+template <typename Message>
+inline Message decode(rust::Slice<const std::uint8_t> bytes) {
+  return decode<Message>(bytes.data(), bytes.size());
+}
+
+template <typename Message>
+inline Message decode(const rust::Vec<std::uint8_t> &bytes) {
+  return decode<Message>(bytes.data(), bytes.size());
+}
+
+template <typename Message>
+inline rust::Vec<std::uint8_t> encode(const Message &message) {
+  std::string serialized;
+  if (!message.SerializeToString(&serialized)) {
+    throw std::invalid_argument("Could not serialize a `" +
+                                message.GetTypeName() + "` message");
+  }
+
+  rust::Vec<std::uint8_t> bytes;
+  bytes.reserve(serialized.size());
+  for (char c : serialized) {
+    bytes.push_back(static_cast<std::uint8_t>(c));
+  }
+  return bytes;
+}
+
+//
+// Whether `port_type`, as declared in the descriptor, is the type of `Message`.
+//
+template <typename Message>
+inline bool is_declared_as(const std::string &port_type) {
+  return port_type == type_prefix + Message().GetTypeName();
+}
+
+} // namespace protobuf
+} // namespace flow
+} // namespace zenoh
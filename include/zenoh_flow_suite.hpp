@@ -0,0 +1,127 @@
+#pragma once
+#include <functional>
+#include <map>
+#include <memory>
+#include <stdexcept>
+#include <string>
+#include "rust/cxx.h"
+#include "zenoh_flow.hpp"
+
+//
+// Component suites: one library hosts several named C++ operators, one build
+// produces all of them. Each component of the graph picks its operator by
+// name, as `component` in its configuration or as the fragment of its URI
+// (`zenoh-flow-cxx-graphs` turns the latter into the former):
+//
+//   operators:
+//     - id: Fizz
+//       uri: file://.../libmy_suite.so#fizzbuzz
+//
+// A library exports a single kind of component (`export_operator!`): the
+// operators of a suite share it.
+//
+// The library names its operators and forwards `initialize`, `input_rule` and
+// `run` to a `Suite`:
+//
+//   const std::map<std::string, suite::Factory> operators {
+//     { "fizzbuzz", [](const ConfigurationMap &configuration) {
+//         return std::unique_ptr<suite::Operator>(new FizzBuzz());
+//       } },
+//     ...
+//   };
+//
+//   State::State(const ConfigurationMap &configuration)
+//     : suite(operators, configuration) {}
+//
+//   bool input_rule(Context &context, std::unique_ptr<State> &state,
+//                   rust::Vec<Token> &tokens) {
+//     return state->suite.input_rule(context, tokens);
+//   }
+//
+//   rust::Vec<Output> run(Context &context, std::unique_ptr<State> &state,
+//                         rust::Vec<Input> inputs) {
+//     return state->suite.run(context, std::move(inputs));
+//   }
+//
+namespace zenoh {
+namespace flow {
+namespace suite {
+
+//
+// An operator of the suite, with the `input_rule` and `run` of a C++ operator.
+// Each component using it has its own instance.
+//
+class Operator {
+public:
+  virtual ~Operator() = default;
+  virtual bool input_rule(Context &context, rust::Vec<Token> &tokens) = 0;
+  virtual rust::Vec<Output> run(Context &context, rust::Vec<Input> inputs) = 0;
+};
+
+using Factory =
+    std::function<std::unique_ptr<Operator>(const ConfigurationMap &configuration)>;
+
+//
+// Whether all the tokens are ready: the input rule of most operators.
+//
+template <typename Tokens>
+inline bool all_ready(const Tokens &tokens) {
+  for (const auto &token : tokens) {
+    if (token.status != decltype(token.status)::Ready) {
+      return false;
+    }
+  }
+  return true;
+}
+
+class Suite {
+public:
+  //
+  // Builds the operator named by `component`. An unknown operator is reported
+  // by the first call to `input_rule` or `run`: `initialize` cannot fail.
+  //
+  template <typename Map>
+  Suite(const std::map<std::string, Factory> &factories, const Map &map) {
+    std::string name;
+    for (const auto &entry : map.map) {
+      if (std::string(entry.key) == "component") {
+        name = std::string(entry.value);
+      }
+    }
+
+    if (name.empty()) {
+      error = "No operator: set `component` in the configuration, or the "
+              "fragment of the URI";
+      return;
+    }
+    auto factory = factories.find(name);
+    if (factory == factories.end()) {
+      error = "Unknown operator `" + name + "` in the suite";
+      return;
+    }
+    component = factory->second(map);
+  }
+
+  bool input_rule(Context &context, rust::Vec<Token> &tokens) {
+    return get().input_rule(context, tokens);
+  }
+
+  rust::Vec<Output> run(Context &context, rust::Vec<Input> inputs) {
+    return get().run(context, std::move(inputs));
+  }
+
+private:
+  Operator &get() {
+    if (!component) {
+      throw std::invalid_argument(error);
+    }
+    return *component;
+  }
+
+  std::unique_ptr<Operator> component;
+  std::string error;
+};
+
+} // namespace suite
+} // namespace flow
+} // namespace zenoh
@@ -0,0 +1,144 @@
+//! Build scripts of the C++ components.
+//!
+//! Each component compiles its bridge and its C++ sources the same way, its `protobuf` and
+//! `flatbuffers` features enabling the schemas of the crate and its `json` feature
+//! `zenoh_flow_json.hpp`:
+//!
+//! ```ignore
+//! fn main() {
+//!     zenoh_flow_cxx_build::component("cpp/src/operator.cpp", "cxx_operator");
+//! }
+//! ```
+
+use std::ffi::OsStr;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Compiles the bridge of `src/lib.rs` and `source` into the static library `library`.
+pub fn component(source: &str, library: &str) {
+    bridge("src/lib.rs", &[source]).compile(library);
+}
+
+/// Returns the build of the bridge `bridge` and of the C++ `sources`, with the schemas of the
+/// enabled features.
+pub fn bridge(bridge: &str, sources: &[&str]) -> cc::Build {
+    let mut build = cxx_build::bridge(bridge);
+    build.files(sources).flag_if_supported("-std=c++14");
+
+    if enabled("protobuf") {
+        protobuf(&mut build);
+    }
+    if enabled("flatbuffers") {
+        flatbuffers(&mut build);
+    }
+    if enabled("json") {
+        json(&mut build);
+    }
+    build
+}
+
+/// Whether the crate being built enables `feature`.
+fn enabled(feature: &str) -> bool {
+    let feature = feature.to_uppercase().replace('-', "_");
+    std::env::var_os(format!("CARGO_FEATURE_{}", feature)).is_some()
+}
+
+/// Returns the files of `dir` with the extension `extension`.
+fn schemas(dir: &str, extension: &str) -> Vec<PathBuf> {
+    println!("cargo:rerun-if-changed={}", dir);
+    match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension() == Some(OsStr::new(extension)))
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Runs the schema compiler `command` (or the content of the environment variable `env`).
+fn compile_schemas(command: &str, env: &str, args: &[String], schemas: &[PathBuf]) {
+    let program = std::env::var(env).unwrap_or_else(|_| command.to_string());
+    let status = Command::new(&program)
+        .args(args)
+        .args(schemas)
+        .status()
+        .unwrap_or_else(|e| panic!("Could not run {}: {}", program, e));
+    assert!(status.success(), "{} failed: {}", program, status);
+}
+
+/// The directory of the generated code of the schemas `kind`.
+fn out_dir(kind: &str) -> PathBuf {
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap()).join(kind);
+    std::fs::create_dir_all(&out_dir).unwrap();
+    out_dir
+}
+
+/// Compiles the `proto/*.proto` files of the crate with `protoc` (or `$PROTOC`) and links the
+/// component against `libprotobuf`, found with `pkg-config`.
+fn protobuf(build: &mut cc::Build) {
+    let library = pkg_config::probe_library("protobuf").expect("Could not find libprotobuf");
+    build
+        .includes(&library.include_paths)
+        // Recent versions of protobuf require C++17.
+        .flag_if_supported("-std=c++17")
+        .define("ZENOH_FLOW_PROTOBUF", None);
+
+    let out_dir = out_dir("proto");
+    build.include(&out_dir);
+
+    let protos = schemas("proto", "proto");
+    if protos.is_empty() {
+        return;
+    }
+
+    compile_schemas(
+        "protoc",
+        "PROTOC",
+        &[
+            "--proto_path=proto".to_string(),
+            format!("--cpp_out={}", out_dir.display()),
+        ],
+        &protos,
+    );
+    for proto in protos {
+        let stem = proto.file_stem().unwrap().to_string_lossy().to_string();
+        build.file(out_dir.join(format!("{}.pb.cc", stem)));
+    }
+}
+
+/// Compiles the `fbs/*.fbs` files of the crate with `flatc` (or `$FLATC`). The generated code
+/// and the FlatBuffers runtime are header-only: `pkg-config` is only used to find the headers.
+fn flatbuffers(build: &mut cc::Build) {
+    if let Ok(library) = pkg_config::probe_library("flatbuffers") {
+        build.includes(&library.include_paths);
+    }
+    build.define("ZENOH_FLOW_FLATBUFFERS", None);
+
+    let out_dir = out_dir("fbs");
+    build.include(&out_dir);
+
+    let tables = schemas("fbs", "fbs");
+    if tables.is_empty() {
+        return;
+    }
+
+    compile_schemas(
+        "flatc",
+        "FLATC",
+        &[
+            "--cpp".to_string(),
+            "-o".to_string(),
+            out_dir.display().to_string(),
+        ],
+        &tables,
+    );
+}
+
+/// Enables `zenoh_flow_json.hpp`, built on nlohmann/json. It is header-only: `pkg-config` is only
+/// used to find the headers.
+fn json(build: &mut cc::Build) {
+    if let Ok(library) = pkg_config::probe_library("nlohmann_json") {
+        build.includes(&library.include_paths);
+    }
+    build.define("ZENOH_FLOW_JSON", None);
+}
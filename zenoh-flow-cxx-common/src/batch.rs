@@ -0,0 +1,227 @@
+//! Batched delivery of the inputs of C++ components.
+//!
+//! The runtime calls `run` with one message per port. With `batch_size` set in its configuration,
+//! the wrapper of a C++ component buffers the messages instead, and calls `run` once with all of
+//! them (several inputs per port, in their order of arrival) when a port has `batch_size` of
+//! them, or when the oldest is older than `batch_timeout_ms`:
+//!
+//! ```yaml
+//! configuration:
+//!   batch_size: "32"
+//!   batch_timeout_ms: "50"
+//!   batch_flush: tick        # optional, see below
+//! ```
+//!
+//! The runtime only calls the wrapper when a message arrives: without one, a batch waits past its
+//! timeout. `batch_flush` names an input receiving a clock, e.g. from a periodic source, whose
+//! messages flush the batches that timed out: they are not delivered to the C++ component, and
+//! its `input_rule` does not see their port.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+use zenoh_flow::{ZFError, ZFResult};
+
+/// Configuration key enabling the batched mode: number of messages on a port that triggers `run`.
+pub const BATCH_SIZE: &str = "batch_size";
+/// Configuration key bounding the time a message waits in a batch, in milliseconds.
+pub const BATCH_TIMEOUT_MS: &str = "batch_timeout_ms";
+/// Configuration key of the input whose messages flush the batches that timed out.
+pub const BATCH_FLUSH: &str = "batch_flush";
+
+fn parse<T: std::str::FromStr>(key: &str, value: &str) -> ZFResult<T> {
+    value
+        .parse()
+        .map_err(|_| ZFError::ParsingError(format!("Invalid `{}`: {}", key, value)))
+}
+
+/// The messages waiting for their batch to be complete.
+#[derive(Debug)]
+pub struct Batcher<T> {
+    size: usize,
+    timeout: Option<Duration>,
+    flush: Option<String>,
+    messages: Vec<T>,
+    counts: HashMap<String, usize>,
+    oldest: Option<Instant>,
+}
+
+impl<T> Batcher<T> {
+    /// Returns a batcher if `batch_size` is set, to more than one message, in `configuration`.
+    pub fn from_configuration(
+        configuration: Option<&HashMap<String, String>>,
+    ) -> ZFResult<Option<Self>> {
+        let configuration = match configuration {
+            Some(configuration) => configuration,
+            None => return Ok(None),
+        };
+
+        let flush = configuration.get(BATCH_FLUSH).cloned();
+        let size: usize = match configuration.get(BATCH_SIZE) {
+            Some(size) => parse(BATCH_SIZE, size)?,
+            None => 0,
+        };
+        if size <= 1 {
+            return match flush {
+                Some(_) => Err(ZFError::InvalidData(format!(
+                    "`{}` is set without batches: `{}` must be more than 1",
+                    BATCH_FLUSH, BATCH_SIZE
+                ))),
+                None => Ok(None),
+            };
+        }
+
+        let timeout = match configuration.get(BATCH_TIMEOUT_MS) {
+            Some(timeout) => Some(Duration::from_millis(parse(BATCH_TIMEOUT_MS, timeout)?)),
+            None => None,
+        };
+        if flush.is_some() && timeout.is_none() {
+            return Err(ZFError::InvalidData(format!(
+                "`{}` is set without `{}`",
+                BATCH_FLUSH, BATCH_TIMEOUT_MS
+            )));
+        }
+
+        Ok(Some(Self {
+            size,
+            timeout,
+            flush,
+            messages: Vec::with_capacity(size),
+            counts: HashMap::new(),
+            oldest: None,
+        }))
+    }
+
+    /// Number of messages waiting on `port_id`.
+    pub fn pending(&self, port_id: &str) -> usize {
+        self.counts.get(port_id).copied().unwrap_or(0)
+    }
+
+    /// Whether the messages of `port_id` only flush the batches, see `BATCH_FLUSH`.
+    pub fn flushes(&self, port_id: &str) -> bool {
+        self.flush.as_deref() == Some(port_id)
+    }
+
+    /// Adds `message`, received on `port_id` at `now`.
+    pub fn push(&mut self, port_id: &str, message: T, now: Instant) {
+        if self.messages.is_empty() {
+            self.oldest = Some(now);
+        }
+        self.messages.push(message);
+        // The ports are known after the first batches: no allocation on the hot path.
+        match self.counts.get_mut(port_id) {
+            Some(count) => *count += 1,
+            None => {
+                self.counts.insert(port_id.to_string(), 1);
+            }
+        }
+    }
+
+    /// Whether the oldest message waited `batch_timeout_ms` at `now`.
+    pub fn timed_out(&self, now: Instant) -> bool {
+        match (self.timeout, self.oldest) {
+            (Some(timeout), Some(oldest)) => now.saturating_duration_since(oldest) >= timeout,
+            _ => false,
+        }
+    }
+
+    /// Whether a port has `batch_size` messages, or the oldest message waited long enough at
+    /// `now`.
+    pub fn is_ready(&self, now: Instant) -> bool {
+        self.timed_out(now) || self.counts.values().any(|count| *count >= self.size)
+    }
+
+    /// Returns the messages of the batch, in their order of arrival, and starts a new one.
+    pub fn take(&mut self) -> Vec<T> {
+        self.counts.values_mut().for_each(|count| *count = 0);
+        self.oldest = None;
+        std::mem::replace(&mut self.messages, Vec::with_capacity(self.size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn batcher(entries: &[(&str, &str)]) -> ZFResult<Option<Batcher<u32>>> {
+        let configuration: HashMap<String, String> = entries
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        Batcher::from_configuration(Some(&configuration))
+    }
+
+    #[test]
+    fn batches_need_more_than_one_message() {
+        assert!(batcher(&[]).unwrap().is_none());
+        assert!(batcher(&[(BATCH_SIZE, "1")]).unwrap().is_none());
+        assert!(batcher(&[(BATCH_SIZE, "two")]).is_err());
+        assert!(batcher(&[(BATCH_SIZE, "2"), (BATCH_TIMEOUT_MS, "soon")]).is_err());
+    }
+
+    #[test]
+    fn a_port_fills_the_batch() {
+        let now = Instant::now();
+        let mut batcher = batcher(&[(BATCH_SIZE, "2")]).unwrap().unwrap();
+        batcher.push("a", 1, now);
+        batcher.push("b", 2, now);
+        assert_eq!((batcher.pending("a"), batcher.pending("b")), (1, 1));
+        assert!(!batcher.is_ready(now));
+
+        batcher.push("a", 3, now);
+        assert!(batcher.is_ready(now));
+        assert_eq!(batcher.take(), vec![1, 2, 3]);
+        assert_eq!(batcher.pending("a"), 0);
+        assert!(!batcher.is_ready(now));
+    }
+
+    #[test]
+    fn the_oldest_message_times_out() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut batcher = batcher(&[(BATCH_SIZE, "8"), (BATCH_TIMEOUT_MS, "20")])
+            .unwrap()
+            .unwrap();
+        // An empty batch never times out.
+        assert!(!batcher.is_ready(at(30)));
+
+        batcher.push("a", 1, at(30));
+        batcher.push("a", 2, at(45));
+        assert!(!batcher.timed_out(at(30)));
+        assert!(!batcher.timed_out(at(49)));
+        // Before the batch is full, from its first message.
+        assert!(batcher.timed_out(at(50)) && batcher.is_ready(at(50)));
+        assert_eq!(batcher.take(), vec![1, 2]);
+        assert!(!batcher.timed_out(at(100)));
+
+        // The timeout counts from the first message of the new batch.
+        batcher.push("a", 3, at(60));
+        assert!(!batcher.is_ready(at(79)));
+        assert!(batcher.is_ready(at(80)));
+    }
+
+    #[test]
+    fn batches_without_timeout_wait_to_be_full() {
+        let start = Instant::now();
+        let mut batcher = batcher(&[(BATCH_SIZE, "2")]).unwrap().unwrap();
+        batcher.push("a", 1, start);
+        assert!(!batcher.is_ready(start + Duration::from_secs(3600)));
+    }
+
+    #[test]
+    fn flushes_need_a_timeout() {
+        assert!(batcher(&[(BATCH_FLUSH, "tick")]).is_err());
+        assert!(batcher(&[(BATCH_SIZE, "2"), (BATCH_FLUSH, "tick")]).is_err());
+
+        let batcher = batcher(&[
+            (BATCH_SIZE, "2"),
+            (BATCH_TIMEOUT_MS, "20"),
+            (BATCH_FLUSH, "tick"),
+        ])
+        .unwrap()
+        .unwrap();
+        assert!(batcher.flushes("tick"));
+        assert!(!batcher.flushes("number"));
+    }
+}
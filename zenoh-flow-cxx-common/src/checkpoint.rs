@@ -0,0 +1,236 @@
+//! Checkpoints of the state of C++ operators.
+//!
+//! A C++ operator whose bridge declares `snapshot(state)` and `restore(configuration, bytes)` can
+//! save its state to a local directory, set as `checkpoint` in its configuration. The snapshot is
+//! taken every `checkpoint_period` calls to `run` (100 by default: a snapshot is a synchronous
+//! write on the thread of the runtime) and when the component is cleaned. When the instance starts
+//! again (after a crash, or on another runtime sharing the directory) its state is restored from
+//! the last snapshot rather than initialized:
+//!
+//! ```yaml
+//! configuration:
+//!   checkpoint: /var/lib/zenoh-flow/checkpoints
+//!   checkpoint_period: "10"
+//! ```
+//!
+//! The snapshot of an instance is `<checkpoint>/<instance>.snapshot` (see `crate::instance`): the
+//! instance must be named, by `zenoh-flow-cxx-graphs` or `instance`, to find it again. The names
+//! given to unnamed instances depend on the order of initialization in a runtime, so the
+//! checkpoints of an unnamed instance are refused.
+
+use crate::instance::INSTANCE;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
+use zenoh_flow::{ZFError, ZFResult};
+
+/// Configuration key enabling the checkpoints: directory of the snapshots.
+pub const CHECKPOINT: &str = "checkpoint";
+/// Configuration key setting the number of calls to `run` between two snapshots.
+pub const CHECKPOINT_PERIOD: &str = "checkpoint_period";
+/// Default number of calls to `run` between two snapshots.
+pub const DEFAULT_CHECKPOINT_PERIOD: u64 = 100;
+
+fn io_error(path: &Path, e: std::io::Error) -> ZFError {
+    ZFError::IOError(format!("{}: {}", path.display(), e))
+}
+
+/// The snapshots of an instance.
+#[derive(Debug)]
+pub struct Checkpoints {
+    path: PathBuf,
+    period: u64,
+    /// Calls since the last snapshot.
+    pending: u64,
+}
+
+impl Checkpoints {
+    /// Returns the snapshots of the instance named in `configuration` if `checkpoint` is set,
+    /// creating the directory if needed.
+    pub fn from_configuration(
+        configuration: Option<&HashMap<String, String>>,
+    ) -> ZFResult<Option<Self>> {
+        let configuration = match configuration {
+            Some(configuration) => configuration,
+            None => return Ok(None),
+        };
+        let directory = match configuration.get(CHECKPOINT) {
+            Some(directory) => PathBuf::from(directory),
+            None => return Ok(None),
+        };
+        let instance = configuration.get(INSTANCE).ok_or_else(|| {
+            ZFError::ParsingError(format!(
+                "`{}` requires the instance to be named by `{}`",
+                CHECKPOINT, INSTANCE
+            ))
+        })?;
+        let period = match configuration.get(CHECKPOINT_PERIOD) {
+            Some(period) => period.parse::<u64>().map_err(|_| {
+                ZFError::ParsingError(format!("Invalid `{}`: {}", CHECKPOINT_PERIOD, period))
+            })?,
+            None => DEFAULT_CHECKPOINT_PERIOD,
+        };
+
+        std::fs::create_dir_all(&directory).map_err(|e| io_error(&directory, e))?;
+        let file: String = instance
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+
+        Ok(Some(Self {
+            path: directory.join(format!("{}.snapshot", file)),
+            period: period.max(1),
+            pending: 0,
+        }))
+    }
+
+    /// Returns the last snapshot, if any.
+    pub fn load(&self) -> ZFResult<Option<Vec<u8>>> {
+        match std::fs::read(&self.path) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(io_error(&self.path, e)),
+        }
+    }
+
+    /// Records a call to `run`, returns whether a snapshot is due.
+    pub fn is_due(&mut self) -> bool {
+        self.pending += 1;
+        if self.pending < self.period {
+            return false;
+        }
+        self.pending = 0;
+        true
+    }
+
+    /// Replaces the last snapshot: a crash while saving, even of the host, leaves the previous
+    /// one.
+    pub fn save(&self, snapshot: &[u8]) -> ZFResult<()> {
+        let temporary = self.path.with_extension("snapshot.tmp");
+        let mut file = File::create(&temporary).map_err(|e| io_error(&temporary, e))?;
+        file.write_all(snapshot)
+            .and_then(|_| file.sync_all())
+            .map_err(|e| io_error(&temporary, e))?;
+        std::fs::rename(&temporary, &self.path).map_err(|e| io_error(&self.path, e))?;
+        sync_directory(&self.path)
+    }
+}
+
+/// Makes the last rename in the directory of `path` durable.
+#[cfg(unix)]
+fn sync_directory(path: &Path) -> ZFResult<()> {
+    let directory = path.parent().unwrap_or_else(|| Path::new("."));
+    File::open(directory)
+        .and_then(|directory| directory.sync_all())
+        .map_err(|e| io_error(directory, e))
+}
+
+#[cfg(not(unix))]
+fn sync_directory(_path: &Path) -> ZFResult<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An empty directory for the snapshots of the test `name`.
+    fn directory(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("zenoh-flow-cxx-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        directory
+    }
+
+    fn configuration(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn disabled_without_directory() {
+        let configuration = configuration(&[(INSTANCE, "counter")]);
+        assert!(Checkpoints::from_configuration(Some(&configuration))
+            .unwrap()
+            .is_none());
+        assert!(Checkpoints::from_configuration(None).unwrap().is_none());
+    }
+
+    #[test]
+    fn unnamed_instance_is_refused() {
+        let directory = directory("unnamed");
+        let configuration = configuration(&[(CHECKPOINT, directory.to_str().unwrap())]);
+        assert!(Checkpoints::from_configuration(Some(&configuration)).is_err());
+    }
+
+    #[test]
+    fn invalid_period_is_refused() {
+        let directory = directory("invalid-period");
+        let configuration = configuration(&[
+            (CHECKPOINT, directory.to_str().unwrap()),
+            (INSTANCE, "counter"),
+            (CHECKPOINT_PERIOD, "often"),
+        ]);
+        assert!(Checkpoints::from_configuration(Some(&configuration)).is_err());
+    }
+
+    #[test]
+    fn snapshots_are_due_every_period() {
+        let directory = directory("period");
+        let default = configuration(&[
+            (CHECKPOINT, directory.to_str().unwrap()),
+            (INSTANCE, "counter"),
+        ]);
+        let mut checkpoints = Checkpoints::from_configuration(Some(&default))
+            .unwrap()
+            .unwrap();
+        let due = (0..2 * DEFAULT_CHECKPOINT_PERIOD)
+            .filter(|_| checkpoints.is_due())
+            .count();
+        assert_eq!(due, 2);
+
+        let mut configuration = default;
+        configuration.insert(CHECKPOINT_PERIOD.to_string(), "3".to_string());
+        let mut checkpoints = Checkpoints::from_configuration(Some(&configuration))
+            .unwrap()
+            .unwrap();
+        let due: Vec<bool> = (0..6).map(|_| checkpoints.is_due()).collect();
+        assert_eq!(due, vec![false, false, true, false, false, true]);
+    }
+
+    #[test]
+    fn saved_snapshot_is_loaded() {
+        let directory = directory("save");
+        let configuration = configuration(&[
+            (CHECKPOINT, directory.to_str().unwrap()),
+            (INSTANCE, "ns/counter 1"),
+        ]);
+        let checkpoints = Checkpoints::from_configuration(Some(&configuration))
+            .unwrap()
+            .unwrap();
+        assert_eq!(checkpoints.load().unwrap(), None);
+
+        checkpoints.save(&[1, 2, 3]).unwrap();
+        checkpoints.save(&[4, 5]).unwrap();
+        assert_eq!(checkpoints.load().unwrap(), Some(vec![4, 5]));
+        // The name of the instance is made a file name, and the temporary file is gone.
+        let mut files: Vec<String> = std::fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        files.sort();
+        assert_eq!(files, vec!["ns_counter_1.snapshot".to_string()]);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,120 @@
+//! Binary encoding of the payloads exchanged with the C++ components.
+//!
+//! The encoding is the one of `bincode` with its default options, which `include/zenoh_flow_codec.hpp`
+//! reproduces on the C++ side:
+//!
+//! - integers: fixed width, little-endian,
+//! - `f32` / `f64`: IEEE 754, little-endian,
+//! - `bool`: one byte, 0 or 1,
+//! - `String`: length as a `u64`, followed by the UTF-8 bytes,
+//! - `Vec<T>`: length as a `u64`, followed by the elements.
+//!
+//! Any `serde` type built from these (structures are the concatenation of their fields) can
+//! therefore be exchanged, byte-for-byte, between a Rust and a C++ component.
+
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt::Debug;
+use zenoh_flow::{Data, DowncastAny, ZFError, ZFResult};
+
+pub fn encode<T: Serialize>(value: &T) -> ZFResult<Vec<u8>> {
+    bincode::serialize(value).map_err(|_| ZFError::SerializationError)
+}
+
+pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> ZFResult<T> {
+    bincode::deserialize(bytes).map_err(|_| ZFError::DeseralizationError)
+}
+
+/// Decodes a `T` at the start of `bytes`, returning it with the number of bytes it took.
+pub fn decode_prefix<T: DeserializeOwned>(bytes: &[u8]) -> ZFResult<(T, usize)> {
+    let mut rest = bytes;
+    let value = bincode::deserialize_from(&mut rest).map_err(|_| ZFError::DeseralizationError)?;
+    Ok((value, bytes.len() - rest.len()))
+}
+
+/// A Rust value exchanged with C++ components through the encoding of this module.
+///
+/// Rust components can return it from their `run` so that C++ components downstream can decode
+/// it with `zenoh::flow::codec::decode`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Typed<T>(pub T);
+
+impl<T> DowncastAny for Typed<T>
+where
+    T: Serialize + Debug + Send + Sync + 'static,
+{
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_mut_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl<T> Data for Typed<T>
+where
+    T: Serialize + Debug + Send + Sync + 'static,
+{
+    fn try_serialize(&self) -> ZFResult<Vec<u8>> {
+        encode(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decodes `bytes` as `T`, checking that they are the encoding of `value`.
+    fn golden<T>(value: T, bytes: &[u8])
+    where
+        T: Serialize + DeserializeOwned + PartialEq + Debug,
+    {
+        assert_eq!(encode(&value).unwrap(), bytes, "{:?}", value);
+        assert_eq!(decode::<T>(bytes).unwrap(), value);
+    }
+
+    #[test]
+    fn integers_are_little_endian() {
+        golden(0x0102_0304_0506_0708_u64, &[8, 7, 6, 5, 4, 3, 2, 1]);
+        golden(-2_i64, &[0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+        golden(0x0102_u16, &[2, 1]);
+        golden(true, &[1]);
+    }
+
+    #[test]
+    fn floats_are_ieee_754() {
+        golden(1.5_f64, &[0, 0, 0, 0, 0, 0, 0xf8, 0x3f]);
+        golden(-0.25_f32, &[0, 0, 0x80, 0xbe]);
+    }
+
+    #[test]
+    fn strings_and_vectors_are_prefixed_by_their_length() {
+        golden(
+            "fizz".to_string(),
+            &[4, 0, 0, 0, 0, 0, 0, 0, b'f', b'i', b'z', b'z'],
+        );
+        golden(String::new(), &[0; 8]);
+        golden(
+            vec![1_u32, 256],
+            &[2, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 0],
+        );
+    }
+
+    #[test]
+    fn tuples_are_their_fields() {
+        golden(
+            (7_u8, "fizz".to_string(), vec![-1_i16, 2]),
+            &[
+                7, 4, 0, 0, 0, 0, 0, 0, 0, b'f', b'i', b'z', b'z', 2, 0, 0, 0, 0, 0, 0, 0, 0xff,
+                0xff, 2, 0,
+            ],
+        );
+    }
+
+    #[test]
+    fn truncated_payloads_are_refused() {
+        assert!(decode::<u64>(&[1, 2, 3]).is_err());
+        assert!(decode::<String>(&[4, 0, 0, 0, 0, 0, 0, 0, b'f']).is_err());
+        assert!(decode::<bool>(&[2]).is_err());
+    }
+}
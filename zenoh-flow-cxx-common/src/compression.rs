@@ -0,0 +1,234 @@
+//! Compression of the payloads of C++ components.
+//!
+//! Compression is configured per output port, in the configuration of the component producing
+//! the payloads, on ports carrying envelopes only:
+//!
+//! ```yaml
+//! configuration:
+//!   envelope_outputs: frame
+//!   compression/frame: zstd     # or `zstd:<level>`, or `lz4`
+//! ```
+//!
+//! A payload is compressed when it is serialized (i.e. when it leaves its runtime) and the
+//! compression is recorded in its envelope (see `crate::envelope`): the wrapper of the receiving
+//! C++ component decompresses it before it reaches the component.
+//!
+//! The receiving wrapper refuses the payloads decompressing to more than `max_payload` bytes
+//! (`DEFAULT_MAX_PAYLOAD` if it is not set in its configuration), before allocating them.
+
+use crate::envelope::{Framing, ENVELOPE_OUTPUTS};
+use std::{collections::HashMap, fmt, io::Read, str::FromStr};
+use zenoh_flow::{ZFError, ZFResult};
+
+/// Configuration key prefix enabling the compression of an output: `compression/<port id>`.
+pub const COMPRESSION_PREFIX: &str = "compression/";
+/// Configuration key of the size, in bytes, above which a received payload is not decompressed.
+pub const MAX_PAYLOAD: &str = "max_payload";
+/// 64 MiB.
+pub const DEFAULT_MAX_PAYLOAD: usize = 64 << 20;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    #[default]
+    None,
+    /// Compression level, `0` for the default level of zstd.
+    Zstd(i32),
+    Lz4,
+}
+
+impl Compression {
+    pub fn is_none(&self) -> bool {
+        *self == Compression::None
+    }
+
+    pub fn compress(&self, data: &[u8]) -> ZFResult<Vec<u8>> {
+        match self {
+            Compression::None => Ok(data.to_vec()),
+            Compression::Zstd(level) => {
+                zstd::bulk::compress(data, *level).map_err(|_| ZFError::SerializationError)
+            }
+            Compression::Lz4 => Ok(lz4_flex::compress_prepend_size(data)),
+        }
+    }
+
+    /// Decompresses `data`, refusing payloads of more than `max_payload` bytes before they are
+    /// allocated.
+    pub fn decompress(&self, data: &[u8], max_payload: usize) -> ZFResult<Vec<u8>> {
+        let invalid =
+            |e: &dyn fmt::Display| ZFError::InvalidData(format!("Invalid {} payload: {}", self, e));
+        let too_large = || {
+            ZFError::InvalidData(format!(
+                "{} payload of more than `{}` ({} bytes)",
+                self, MAX_PAYLOAD, max_payload
+            ))
+        };
+
+        match self {
+            Compression::None => Ok(data.to_vec()),
+            Compression::Zstd(_) => {
+                let decoder = zstd::stream::read::Decoder::new(data).map_err(|e| invalid(&e))?;
+                let mut payload = Vec::new();
+                decoder
+                    .take(max_payload as u64 + 1)
+                    .read_to_end(&mut payload)
+                    .map_err(|e| invalid(&e))?;
+                if payload.len() > max_payload {
+                    return Err(too_large());
+                }
+                Ok(payload)
+            }
+            Compression::Lz4 => {
+                // The size of the payload is prepended, `lz4_flex` allocates it.
+                let size = data
+                    .get(..4)
+                    .map(|size| u32::from_le_bytes([size[0], size[1], size[2], size[3]]))
+                    .ok_or_else(|| invalid(&"missing size"))?;
+                if size as usize > max_payload {
+                    return Err(too_large());
+                }
+                lz4_flex::decompress_size_prepended(data).map_err(|e| invalid(&e))
+            }
+        }
+    }
+}
+
+impl FromStr for Compression {
+    type Err = ZFError;
+
+    fn from_str(s: &str) -> ZFResult<Self> {
+        let (algorithm, level) = match s.find(':') {
+            Some(index) => (&s[..index], Some(&s[index + 1..])),
+            None => (s, None),
+        };
+
+        match (algorithm, level) {
+            ("" | "none", None) => Ok(Compression::None),
+            ("lz4", None) => Ok(Compression::Lz4),
+            ("zstd", None) => Ok(Compression::Zstd(0)),
+            ("zstd", Some(level)) => level.parse().map(Compression::Zstd).map_err(|_| {
+                ZFError::ParsingError(format!("Invalid zstd compression level: {}", level))
+            }),
+            _ => Err(ZFError::ParsingError(format!(
+                "Unsupported compression `{}`, expected `zstd`, `zstd:<level>` or `lz4`",
+                s
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Compression::None => Ok(()),
+            Compression::Zstd(0) => write!(f, "zstd"),
+            Compression::Zstd(level) => write!(f, "zstd:{}", level),
+            Compression::Lz4 => write!(f, "lz4"),
+        }
+    }
+}
+
+/// Returns `max_payload` from `configuration`, `DEFAULT_MAX_PAYLOAD` if it is not set.
+pub fn max_payload(configuration: Option<&HashMap<String, String>>) -> ZFResult<usize> {
+    match configuration.and_then(|configuration| configuration.get(MAX_PAYLOAD)) {
+        Some(max_payload) => max_payload.parse::<usize>().map_err(|_| {
+            ZFError::ParsingError(format!("Invalid `{}`: {}", MAX_PAYLOAD, max_payload))
+        }),
+        None => Ok(DEFAULT_MAX_PAYLOAD),
+    }
+}
+
+/// The compression of each output of a component.
+#[derive(Debug, Clone, Default)]
+pub struct Compressions {
+    outputs: HashMap<String, Compression>,
+}
+
+impl Compressions {
+    pub fn from_configuration(configuration: Option<&HashMap<String, String>>) -> ZFResult<Self> {
+        let mut compressions = Self::default();
+        let framing = Framing::from_configuration(configuration, ENVELOPE_OUTPUTS);
+        if let Some(configuration) = configuration {
+            for (key, value) in configuration {
+                if let Some(port_id) = key.strip_prefix(COMPRESSION_PREFIX) {
+                    // The compression is recorded in the envelope.
+                    if !framing.contains(port_id) {
+                        return Err(ZFError::InvalidData(format!(
+                            "<{}> is compressed but not in `{}`",
+                            port_id, ENVELOPE_OUTPUTS
+                        )));
+                    }
+                    compressions
+                        .outputs
+                        .insert(port_id.to_string(), value.parse()?);
+                }
+            }
+        }
+
+        Ok(compressions)
+    }
+
+    pub fn output(&self, port_id: &str) -> Compression {
+        self.outputs.get(port_id).copied().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compression_needs_envelopes() {
+        let mut configuration = HashMap::new();
+        configuration.insert(format!("{}count", COMPRESSION_PREFIX), "lz4".to_string());
+        assert!(Compressions::from_configuration(Some(&configuration)).is_err());
+
+        configuration.insert(ENVELOPE_OUTPUTS.to_string(), "count".to_string());
+        let compressions = Compressions::from_configuration(Some(&configuration)).unwrap();
+        assert_eq!(compressions.output("count"), Compression::Lz4);
+        assert_eq!(compressions.output("tick"), Compression::None);
+    }
+
+    #[test]
+    fn payloads_round_trip() {
+        let payload = b"fizz buzz fizz buzz fizz buzz".to_vec();
+        for compression in [Compression::None, Compression::Zstd(3), Compression::Lz4] {
+            let compressed = compression.compress(&payload).unwrap();
+            assert_eq!(
+                compression.decompress(&compressed, payload.len()).unwrap(),
+                payload
+            );
+        }
+    }
+
+    #[test]
+    fn bad_frames_are_refused() {
+        for compression in [Compression::Zstd(0), Compression::Lz4] {
+            assert!(compression.decompress(b"not a frame", 1024).is_err());
+            assert!(compression.decompress(&[], 1024).is_err());
+        }
+    }
+
+    #[test]
+    fn payloads_are_capped() {
+        // 1 GiB announced by a 4 bytes frame: refused before allocating.
+        let lz4 = [0x00, 0x00, 0x00, 0x40];
+        assert!(Compression::Lz4
+            .decompress(&lz4, DEFAULT_MAX_PAYLOAD)
+            .is_err());
+
+        let bomb = Compression::Zstd(0).compress(&vec![0; 1 << 20]).unwrap();
+        assert!(bomb.len() < 1024);
+        assert!(Compression::Zstd(0).decompress(&bomb, 1 << 16).is_err());
+        assert!(Compression::Zstd(0).decompress(&bomb, 1 << 20).is_ok());
+    }
+
+    #[test]
+    fn max_payload_is_configured() {
+        assert_eq!(max_payload(None).unwrap(), DEFAULT_MAX_PAYLOAD);
+        let mut configuration = HashMap::new();
+        configuration.insert(MAX_PAYLOAD.to_string(), "4096".to_string());
+        assert_eq!(max_payload(Some(&configuration)).unwrap(), 4096);
+        configuration.insert(MAX_PAYLOAD.to_string(), "-1".to_string());
+        assert!(max_payload(Some(&configuration)).is_err());
+    }
+}
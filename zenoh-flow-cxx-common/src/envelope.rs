@@ -0,0 +1,612 @@
+//! Envelope of the payloads produced by C++ components.
+//!
+//! The runtime only transports bytes: the information attached to a payload by a C++ component
+//! (its encoding and metadata, see `crate::metadata`) or by its wrapper (its compression, see
+//! `crate::compression`) travels with it in an envelope, opened by the wrapper of the receiving
+//! C++ component.
+//!
+//! The bytes of a payload tell nothing about their framing: whether a port carries envelopes is
+//! declared in the configuration of both ends of its links, `envelope_outputs` for the sender
+//! and `envelope_inputs` for the receiver, as `"true"` (every port) or a list of port ids:
+//!
+//! ```yaml
+//! configuration:
+//!   envelope_outputs: "fizz"           # or "true", or "fizz,buzz"
+//! ```
+//!
+//! Every message of such a port is an envelope, and every message the wrapper receives on such a
+//! port must be one. On the other ports, the payload travels as is: Rust components receive
+//! exactly the bytes the C++ component produced, whatever they are, and the encoding, the
+//! metadata or the compression of the payload are not transmitted.
+//!
+//! An envelope is `MAGIC`, `VERSION` and the encoding (see `crate::codec`) of its fields. The
+//! envelopes of the previous versions, without compression (1) or metadata (2), are still
+//! accepted.
+//!
+//! Several messages produced on the same port (carrying envelopes) by one call to `run` travel
+//! together as a `Sequence`: `SEQUENCE_MAGIC` followed by the encoding of the list of their (sealed) bytes. The
+//! wrapper of the receiving C++ component delivers them as distinct inputs, in order.
+//!
+//! A payload placed in shared memory (see `crate::shm`) is sent as `SHM_MAGIC` followed by the
+//! encoding of the name of its segment, its size, and its envelope without the payload.
+//!
+//! Opening a message does not copy its payload, unless it was compressed: the envelope holds the
+//! range of the payload in the received bytes, shared with the message (see `Bytes`).
+
+use crate::{
+    codec::{decode, decode_prefix, encode},
+    compression::{Compression, DEFAULT_MAX_PAYLOAD},
+    metadata::Metadata,
+    shm::Segment,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    ops::{Deref, Range},
+    sync::{Arc, OnceLock},
+};
+use zenoh_flow::{Data, DowncastAny, ZFError, ZFResult};
+
+pub const MAGIC: [u8; 4] = *b"\0ZFX";
+pub const VERSION: u8 = 3;
+pub const SEQUENCE_MAGIC: [u8; 4] = *b"\0ZFS";
+pub const SHM_MAGIC: [u8; 4] = *b"\0ZFM";
+
+/// Configuration key listing the inputs receiving envelopes.
+pub const ENVELOPE_INPUTS: &str = "envelope_inputs";
+/// Configuration key listing the outputs sending envelopes.
+pub const ENVELOPE_OUTPUTS: &str = "envelope_outputs";
+
+/// Encodings known to the wrappers, also defined in `zenoh_flow.hpp` for C++ components.
+pub const JSON: &str = "application/json";
+pub const CBOR: &str = "application/cbor";
+pub const PROTOBUF: &str = "application/protobuf";
+pub const FLATBUFFERS: &str = "application/flatbuffers";
+
+/// Returns the port type (see `crate::ports`) matching `encoding`, if the encoding alone is
+/// enough to decode a payload.
+pub fn port_type(encoding: &str) -> Option<&'static str> {
+    match encoding {
+        JSON => Some("json"),
+        CBOR => Some("cbor"),
+        _ => None,
+    }
+}
+
+/// The ports of a component carrying envelopes, read from `ENVELOPE_INPUTS` or
+/// `ENVELOPE_OUTPUTS`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Framing {
+    /// No port carries envelopes.
+    #[default]
+    None,
+    All,
+    Ports(HashSet<String>),
+}
+
+impl Framing {
+    pub fn from_configuration(configuration: Option<&HashMap<String, String>>, key: &str) -> Self {
+        match configuration.and_then(|configuration| configuration.get(key)) {
+            None => Framing::None,
+            Some(value) if value == "true" => Framing::All,
+            Some(value) => {
+                let ports: HashSet<String> = value
+                    .split(',')
+                    .map(|port_id| port_id.trim())
+                    .filter(|port_id| !port_id.is_empty() && *port_id != "false")
+                    .map(|port_id| port_id.to_string())
+                    .collect();
+                if ports.is_empty() {
+                    Framing::None
+                } else {
+                    Framing::Ports(ports)
+                }
+            }
+        }
+    }
+
+    /// Whether the messages of `port_id` are envelopes.
+    pub fn contains(&self, port_id: &str) -> bool {
+        match self {
+            Framing::None => false,
+            Framing::All => true,
+            Framing::Ports(ports) => ports.contains(port_id),
+        }
+    }
+}
+
+/// The bytes of a payload: a range of a received message, shared with it, or owned, e.g. once
+/// decompressed.
+#[derive(Debug, Clone)]
+pub enum Bytes {
+    Owned(Vec<u8>),
+    Shared(Arc<Vec<u8>>, Range<usize>),
+}
+
+impl Bytes {
+    pub fn as_slice(&self) -> &[u8] {
+        match self {
+            Bytes::Owned(bytes) => bytes,
+            Bytes::Shared(bytes, range) => &bytes[range.clone()],
+        }
+    }
+
+    /// Takes the bytes if they are owned, leaving them empty. Shared bytes are left in place.
+    pub fn take_owned(&mut self) -> Vec<u8> {
+        match self {
+            Bytes::Owned(bytes) => std::mem::take(bytes),
+            Bytes::Shared(..) => Vec::new(),
+        }
+    }
+}
+
+impl Default for Bytes {
+    fn default() -> Self {
+        Bytes::Owned(Vec::new())
+    }
+}
+
+impl From<Vec<u8>> for Bytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Bytes::Owned(bytes)
+    }
+}
+
+impl Deref for Bytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl PartialEq for Bytes {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Envelope {
+    /// Content type of the payload (e.g. `application/json`), empty if unspecified.
+    pub encoding: String,
+    /// Compression the payload was transmitted with, it is decompressed when opened.
+    pub compression: Compression,
+    pub metadata: Metadata,
+    pub payload: Bytes,
+    /// The segment holding the payload, if it was sent in shared memory: `payload` is then empty.
+    pub shared: Option<Arc<Segment>>,
+}
+
+impl Envelope {
+    pub fn new(
+        encoding: String,
+        compression: Compression,
+        metadata: Metadata,
+        payload: Bytes,
+    ) -> Self {
+        Self {
+            encoding,
+            compression,
+            metadata,
+            payload,
+            shared: None,
+        }
+    }
+
+    /// A payload received on a port without envelopes.
+    pub fn bare(payload: Bytes) -> Self {
+        Self::new(String::new(), Compression::None, Metadata::new(), payload)
+    }
+
+    /// The bytes of the payload, wherever they are.
+    pub fn data(&self) -> &[u8] {
+        match &self.shared {
+            Some(segment) => segment.as_slice(),
+            None => self.payload.as_slice(),
+        }
+    }
+
+    /// Whether the envelope carries nothing but the payload.
+    pub fn is_bare(&self) -> bool {
+        self.encoding.is_empty() && self.compression.is_none() && self.metadata.is_empty()
+    }
+
+    /// Returns the bytes to transmit, see `seal`.
+    pub fn seal(&self) -> ZFResult<Vec<u8>> {
+        seal(
+            &self.encoding,
+            self.compression,
+            &self.metadata,
+            &self.payload,
+        )
+    }
+
+    /// Opens received bytes, decompressing the payload up to `max_payload` bytes: they must start
+    /// with `MAGIC` (or `SHM_MAGIC`).
+    pub fn open(bytes: Vec<u8>, max_payload: usize) -> ZFResult<Self> {
+        let len = bytes.len();
+        Self::open_range(&Arc::new(bytes), 0..len, max_payload)
+    }
+
+    /// Opens the message in `range` of `bytes`, see `open`: an uncompressed payload is a range of
+    /// `bytes`.
+    fn open_range(bytes: &Arc<Vec<u8>>, range: Range<usize>, max_payload: usize) -> ZFResult<Self> {
+        let message = &bytes[range.clone()];
+        if message.len() > SHM_MAGIC.len() && message[..SHM_MAGIC.len()] == SHM_MAGIC {
+            let (name, len, header) =
+                decode::<(String, u64, Vec<u8>)>(&message[SHM_MAGIC.len()..])?;
+            let mut envelope = Self::open(header, max_payload)?;
+            envelope.shared = Some(Arc::new(Segment::open(&name, len as usize)?));
+            return Ok(envelope);
+        }
+
+        if message.len() <= MAGIC.len() || message[..MAGIC.len()] != MAGIC {
+            return Err(ZFError::InvalidData(format!(
+                "Expected an envelope, got {} bytes without: is the port in `{}` of the sender?",
+                message.len(),
+                ENVELOPE_OUTPUTS
+            )));
+        }
+
+        let fields = &message[MAGIC.len() + 1..];
+        let ((encoding, compression, metadata), used) = match message[MAGIC.len()] {
+            1 => {
+                let (encoding, used) = decode_prefix::<String>(fields)?;
+                ((encoding, String::new(), Metadata::new()), used)
+            }
+            2 => {
+                let ((encoding, compression), used) = decode_prefix::<(String, String)>(fields)?;
+                ((encoding, compression, Metadata::new()), used)
+            }
+            VERSION => decode_prefix::<(String, String, Metadata)>(fields)?,
+            version => {
+                return Err(ZFError::InvalidData(format!(
+                    "Unsupported envelope version: {}",
+                    version
+                )))
+            }
+        };
+
+        let offset = range.start + MAGIC.len() + 1 + used;
+        let payload = slice_at(&bytes[..range.end], offset)?;
+        let compression: Compression = compression.parse()?;
+        let payload = if compression.is_none() {
+            Bytes::Shared(bytes.clone(), payload)
+        } else {
+            Bytes::Owned(compression.decompress(&bytes[payload], max_payload)?)
+        };
+        Ok(Self::new(encoding, compression, metadata, payload))
+    }
+}
+
+/// Returns the range of the bytes encoded at `offset` of `bytes`: their length, then themselves.
+fn slice_at(bytes: &[u8], offset: usize) -> ZFResult<Range<usize>> {
+    let (len, used) = decode_prefix::<u64>(bytes.get(offset..).unwrap_or_default())?;
+    let start = offset + used;
+    usize::try_from(len)
+        .ok()
+        .and_then(|len| start.checked_add(len))
+        .filter(|end| *end <= bytes.len())
+        .map(|end| start..end)
+        .ok_or(ZFError::DeseralizationError)
+}
+
+/// Returns the envelope of `payload` to transmit on a port carrying envelopes, with the
+/// compressed payload.
+pub fn seal(
+    encoding: &str,
+    compression: Compression,
+    metadata: &[(String, String)],
+    payload: &[u8],
+) -> ZFResult<Vec<u8>> {
+    let payload = compression.compress(payload)?;
+    let fields = encode(&(encoding, compression.to_string(), metadata, payload))?;
+    let mut bytes = Vec::with_capacity(MAGIC.len() + 1 + fields.len());
+    bytes.extend_from_slice(&MAGIC);
+    bytes.push(VERSION);
+    bytes.extend_from_slice(&fields);
+    Ok(bytes)
+}
+
+/// Opens bytes received on a port carrying envelopes: the messages of a `Sequence`, or a single
+/// message.
+pub fn open_all(bytes: Arc<Vec<u8>>, max_payload: usize) -> ZFResult<Vec<Envelope>> {
+    if bytes.len() < SEQUENCE_MAGIC.len() || bytes[..SEQUENCE_MAGIC.len()] != SEQUENCE_MAGIC {
+        let len = bytes.len();
+        return Ok(vec![Envelope::open_range(&bytes, 0..len, max_payload)?]);
+    }
+
+    let (count, used) = decode_prefix::<u64>(&bytes[SEQUENCE_MAGIC.len()..])?;
+    let mut offset = SEQUENCE_MAGIC.len() + used;
+    let mut envelopes = Vec::new();
+    for _ in 0..count {
+        let message = slice_at(&bytes, offset)?;
+        offset = message.end;
+        envelopes.push(Envelope::open_range(&bytes, message, max_payload)?);
+    }
+    Ok(envelopes)
+}
+
+/// How the messages of an input are received, see `crate::ports::Ports::reception`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reception {
+    /// Whether the messages are envelopes.
+    pub framed: bool,
+    /// The size, in bytes, above which a compressed payload is refused, see
+    /// `crate::compression::MAX_PAYLOAD`.
+    pub max_payload: usize,
+}
+
+impl Default for Reception {
+    fn default() -> Self {
+        Self {
+            framed: false,
+            max_payload: DEFAULT_MAX_PAYLOAD,
+        }
+    }
+}
+
+/// Opens bytes received on a port: if it is framed, see `open_all`, otherwise they are the
+/// payload.
+pub fn receive(bytes: Arc<Vec<u8>>, reception: Reception) -> ZFResult<Vec<Envelope>> {
+    if reception.framed {
+        open_all(bytes, reception.max_payload)
+    } else {
+        let len = bytes.len();
+        Ok(vec![Envelope::bare(Bytes::Shared(bytes, 0..len))])
+    }
+}
+
+/// The envelope of a message, only opened when it is first accessed.
+///
+/// The first message of a sequence stands for the whole sequence.
+#[derive(Debug)]
+pub struct LazyEnvelope<M> {
+    message: Option<M>,
+    reception: Reception,
+    /// The error is formatted, to be raised in C++.
+    envelope: OnceLock<Result<Envelope, String>>,
+}
+
+impl<M> LazyEnvelope<M> {
+    /// `None` stands for a message that did not arrive yet: its envelope is empty.
+    pub fn new(message: Option<M>, reception: Reception) -> Self {
+        Self {
+            message,
+            reception,
+            envelope: OnceLock::new(),
+        }
+    }
+
+    /// Returns the envelope, opened on first access from the bytes `serialize` returns.
+    pub fn get<F>(&self, serialize: F) -> Result<&Envelope, String>
+    where
+        F: FnOnce(&M) -> ZFResult<Arc<Vec<u8>>>,
+    {
+        self.envelope
+            .get_or_init(|| {
+                let message = match &self.message {
+                    Some(message) => message,
+                    None => return Ok(Envelope::default()),
+                };
+                serialize(message)
+                    .and_then(|bytes| receive(bytes, self.reception))
+                    .map(|envelopes| envelopes.into_iter().next().unwrap_or_default())
+                    .map_err(|e| format!("{:?}", e))
+            })
+            .as_ref()
+            .map_err(|e| e.clone())
+    }
+}
+
+/// Messages produced on the same port by one call to `run`, sent as one.
+#[derive(Debug, Clone)]
+pub struct Sequence(pub Vec<Arc<dyn Data>>);
+
+impl DowncastAny for Sequence {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_mut_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Data for Sequence {
+    fn try_serialize(&self) -> ZFResult<Vec<u8>> {
+        let messages = self
+            .0
+            .iter()
+            .map(|data| data.try_serialize())
+            .collect::<ZFResult<Vec<Vec<u8>>>>()?;
+        let encoded = encode(&messages)?;
+
+        let mut bytes = Vec::with_capacity(SEQUENCE_MAGIC.len() + encoded.len());
+        bytes.extend_from_slice(&SEQUENCE_MAGIC);
+        bytes.extend_from_slice(&encoded);
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `u64` whose little-endian bytes start with `MAGIC`.
+    const COLLIDING: u64 = 0x5846_5A00;
+
+    const BARE: Reception = Reception {
+        framed: false,
+        max_payload: DEFAULT_MAX_PAYLOAD,
+    };
+    const FRAMED: Reception = Reception {
+        framed: true,
+        max_payload: DEFAULT_MAX_PAYLOAD,
+    };
+
+    fn configuration(key: &str, value: &str) -> HashMap<String, String> {
+        let mut configuration = HashMap::new();
+        configuration.insert(key.to_string(), value.to_string());
+        configuration
+    }
+
+    #[test]
+    fn bare_payloads_are_received_as_is() {
+        let colliding = [
+            COLLIDING.to_le_bytes().to_vec(),
+            SEQUENCE_MAGIC.to_vec(),
+            [&SHM_MAGIC[..], b"/zenoh-flow-cxx-0-0"].concat(),
+            [&MAGIC[..], &[VERSION]].concat(),
+        ];
+        for payload in colliding {
+            let envelopes = receive(Arc::new(payload.clone()), BARE).unwrap();
+            assert_eq!(envelopes, vec![Envelope::bare(payload.into())]);
+        }
+    }
+
+    #[test]
+    fn framed_payloads_are_unambiguous() {
+        for payload in [COLLIDING.to_le_bytes().to_vec(), MAGIC.to_vec(), Vec::new()] {
+            let bytes = seal("", Compression::None, &[], &payload).unwrap();
+            let envelopes = receive(Arc::new(bytes), FRAMED).unwrap();
+            assert_eq!(envelopes, vec![Envelope::bare(payload.into())]);
+        }
+    }
+
+    #[test]
+    fn framed_ports_refuse_bare_payloads() {
+        assert!(receive(Arc::new(COLLIDING.to_le_bytes().to_vec()), FRAMED).is_err());
+        assert!(receive(Arc::new(b"hello".to_vec()), FRAMED).is_err());
+        assert!(receive(Arc::new(Vec::new()), FRAMED).is_err());
+    }
+
+    #[test]
+    fn envelope_keeps_its_fields() {
+        let metadata = vec![("sequence".to_string(), "1".to_string())];
+        let bytes = seal(JSON, Compression::None, &metadata, b"[1]").unwrap();
+        let envelope = Envelope::open(bytes, DEFAULT_MAX_PAYLOAD).unwrap();
+        assert_eq!(envelope.encoding, JSON);
+        assert_eq!(envelope.metadata, metadata);
+        assert_eq!(envelope.data(), b"[1]");
+    }
+
+    #[test]
+    fn payloads_are_decompressed_up_to_the_cap() {
+        let payload = vec![0; 4096];
+        for compression in [Compression::Zstd(0), Compression::Lz4] {
+            let bytes = seal("", compression, &[], &payload).unwrap();
+            let envelope = Envelope::open(bytes.clone(), payload.len()).unwrap();
+            assert_eq!(envelope.data(), payload);
+            assert!(matches!(envelope.payload, Bytes::Owned(_)));
+            assert!(Envelope::open(bytes, payload.len() - 1).is_err());
+        }
+    }
+
+    #[test]
+    fn sequences_hold_colliding_payloads() {
+        let first: Arc<dyn Data> = Arc::new(Sealed(COLLIDING.to_le_bytes().to_vec()));
+        let second: Arc<dyn Data> = Arc::new(Sealed(SEQUENCE_MAGIC.to_vec()));
+        let bytes = Sequence(vec![first, second]).try_serialize().unwrap();
+
+        let payloads: Vec<Vec<u8>> = receive(Arc::new(bytes), FRAMED)
+            .unwrap()
+            .into_iter()
+            .map(|envelope| envelope.data().to_vec())
+            .collect();
+        assert_eq!(
+            payloads,
+            vec![COLLIDING.to_le_bytes().to_vec(), SEQUENCE_MAGIC.to_vec()]
+        );
+    }
+
+    #[test]
+    fn framing_lists_ports() {
+        let all = configuration(ENVELOPE_OUTPUTS, "true");
+        assert!(Framing::from_configuration(Some(&all), ENVELOPE_OUTPUTS).contains("fizz"));
+
+        let some = configuration(ENVELOPE_OUTPUTS, "fizz, buzz");
+        let framing = Framing::from_configuration(Some(&some), ENVELOPE_OUTPUTS);
+        assert!(framing.contains("fizz") && framing.contains("buzz"));
+        assert!(!framing.contains("number"));
+        assert_eq!(
+            Framing::from_configuration(Some(&some), ENVELOPE_INPUTS),
+            Framing::None
+        );
+
+        let none = configuration(ENVELOPE_OUTPUTS, "false");
+        assert_eq!(
+            Framing::from_configuration(Some(&none), ENVELOPE_OUTPUTS),
+            Framing::None
+        );
+        assert_eq!(
+            Framing::from_configuration(None, ENVELOPE_OUTPUTS),
+            Framing::None
+        );
+    }
+
+    #[test]
+    fn lazy_envelope_follows_the_framing() {
+        let bare = LazyEnvelope::new(Some(COLLIDING.to_le_bytes().to_vec()), BARE);
+        assert_eq!(
+            bare.get(|bytes| Ok(Arc::new(bytes.clone())))
+                .unwrap()
+                .data(),
+            COLLIDING.to_le_bytes()
+        );
+
+        let framed = LazyEnvelope::new(Some(COLLIDING.to_le_bytes().to_vec()), FRAMED);
+        assert!(framed.get(|bytes| Ok(Arc::new(bytes.clone()))).is_err());
+    }
+
+    #[test]
+    fn payloads_are_shared_with_the_message() {
+        let first: Arc<dyn Data> = Arc::new(Sealed(b"first".to_vec()));
+        let second: Arc<dyn Data> = Arc::new(Sealed(b"second".to_vec()));
+        let message = Arc::new(Sequence(vec![first, second]).try_serialize().unwrap());
+
+        for envelope in receive(message.clone(), FRAMED).unwrap() {
+            match envelope.payload {
+                Bytes::Shared(bytes, _) => assert!(Arc::ptr_eq(&bytes, &message)),
+                Bytes::Owned(_) => panic!("The payload was copied"),
+            }
+        }
+        let envelope = receive(message.clone(), BARE).unwrap().remove(0);
+        assert_eq!(envelope.data(), message.as_slice());
+        assert_eq!(Arc::strong_count(&message), 2);
+    }
+
+    #[test]
+    fn truncated_payloads_are_refused() {
+        let bytes = seal(JSON, Compression::None, &[], b"[1, 2]").unwrap();
+        for len in MAGIC.len() + 1..bytes.len() {
+            assert!(Envelope::open(bytes[..len].to_vec(), DEFAULT_MAX_PAYLOAD).is_err());
+        }
+
+        let first: Arc<dyn Data> = Arc::new(Sealed(b"first".to_vec()));
+        let message = Sequence(vec![first]).try_serialize().unwrap();
+        let truncated = message[..message.len() - 1].to_vec();
+        assert!(open_all(Arc::new(truncated), DEFAULT_MAX_PAYLOAD).is_err());
+    }
+
+    /// The bytes of a message, as produced by a wrapper.
+    #[derive(Debug)]
+    struct Sealed(Vec<u8>);
+
+    impl DowncastAny for Sealed {
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+
+        fn as_mut_any(&mut self) -> &mut dyn std::any::Any {
+            self
+        }
+    }
+
+    impl Data for Sealed {
+        fn try_serialize(&self) -> ZFResult<Vec<u8>> {
+            seal("", Compression::None, &[], &self.0)
+        }
+    }
+}
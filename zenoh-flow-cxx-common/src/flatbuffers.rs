@@ -0,0 +1,131 @@
+//! FlatBuffers payloads, enabled with the `flatbuffers` feature.
+//!
+//! A port carries FlatBuffers when its type, in the descriptor, is `flatbuffers:<root table>`
+//! (e.g. `flatbuffers:sensors.Imu`). C++ components read them in place with
+//! `zenoh::flow::flatbuffers::view` and build them with `zenoh::flow::flatbuffers::Builder`,
+//! Rust components with the code `flatc --rust` generates.
+
+use flatbuffers::{Follow, Verifiable};
+use zenoh_flow::{Data, DowncastAny, ZFError, ZFResult};
+
+/// Prefix of the type of the ports carrying FlatBuffers.
+pub const TYPE_PREFIX: &str = "flatbuffers:";
+
+/// Returns the name of the root table of a port type, `None` if the port does not carry
+/// FlatBuffers.
+pub fn root_type(port_type: &str) -> Option<&str> {
+    port_type.strip_prefix(TYPE_PREFIX)
+}
+
+/// Verifies `bytes` and returns a view of its root table, without copying nor deserializing.
+pub fn view<'buf, T>(bytes: &'buf [u8]) -> ZFResult<T::Inner>
+where
+    T: 'buf + Follow<'buf> + Verifiable,
+{
+    flatbuffers::root::<T>(bytes).map_err(|e| ZFError::InvalidData(format!("{}", e)))
+}
+
+/// A finished FlatBuffer exchanged with C++ components.
+///
+/// Rust components can return it from their `run`, with the bytes of
+/// `FlatBufferBuilder::finished_data`, so that C++ components downstream can view it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlatBuffer(pub Vec<u8>);
+
+impl DowncastAny for FlatBuffer {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_mut_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Data for FlatBuffer {
+    fn try_serialize(&self) -> ZFResult<Vec<u8>> {
+        Ok(self.0.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flatbuffers::{FlatBufferBuilder, InvalidFlatbuffer, Table, VOffsetT, Verifier};
+
+    /// `sensors.Imu` of `zenoh-flow-cxx-imu/fbs/sensors.fbs`, as `flatc --rust` generates it.
+    struct Imu<'a> {
+        _tab: Table<'a>,
+    }
+
+    impl<'a> Imu<'a> {
+        const VT_X: VOffsetT = 4;
+        const VT_Y: VOffsetT = 6;
+        const VT_Z: VOffsetT = 8;
+
+        fn x(&self) -> f64 {
+            unsafe { self._tab.get::<f64>(Self::VT_X, Some(0.0)).unwrap() }
+        }
+
+        fn y(&self) -> f64 {
+            unsafe { self._tab.get::<f64>(Self::VT_Y, Some(0.0)).unwrap() }
+        }
+
+        fn z(&self) -> f64 {
+            unsafe { self._tab.get::<f64>(Self::VT_Z, Some(0.0)).unwrap() }
+        }
+    }
+
+    impl<'a> Follow<'a> for Imu<'a> {
+        type Inner = Imu<'a>;
+
+        unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+            Self {
+                _tab: Table::new(buf, loc),
+            }
+        }
+    }
+
+    impl Verifiable for Imu<'_> {
+        fn run_verifier(v: &mut Verifier, pos: usize) -> Result<(), InvalidFlatbuffer> {
+            v.visit_table(pos)?
+                .visit_field::<f64>("x", Self::VT_X, false)?
+                .visit_field::<f64>("y", Self::VT_Y, false)?
+                .visit_field::<f64>("z", Self::VT_Z, false)?
+                .finish();
+            Ok(())
+        }
+    }
+
+    fn imu(x: f64, y: f64, z: f64) -> Vec<u8> {
+        let mut builder = FlatBufferBuilder::new();
+        let table = builder.start_table();
+        builder.push_slot::<f64>(Imu::VT_Z, z, 0.0);
+        builder.push_slot::<f64>(Imu::VT_Y, y, 0.0);
+        builder.push_slot::<f64>(Imu::VT_X, x, 0.0);
+        let root = builder.end_table(table);
+        builder.finish(root, None);
+        builder.finished_data().to_vec()
+    }
+
+    #[test]
+    fn root_type_is_the_suffix() {
+        assert_eq!(root_type("flatbuffers:sensors.Imu"), Some("sensors.Imu"));
+        assert_eq!(root_type("u64"), None);
+    }
+
+    #[test]
+    fn buffers_are_viewed_in_place() {
+        let buffer = FlatBuffer(imu(1.0, 2.0, 2.0));
+        let bytes = buffer.try_serialize().unwrap();
+        let view = view::<Imu>(&bytes).unwrap();
+        assert_eq!((view.x(), view.y(), view.z()), (1.0, 2.0, 2.0));
+    }
+
+    #[test]
+    fn invalid_buffers_are_refused() {
+        let bytes = imu(1.0, 2.0, 2.0);
+        assert!(view::<Imu>(&bytes[..bytes.len() / 2]).is_err());
+        assert!(view::<Imu>(&[]).is_err());
+    }
+}
@@ -0,0 +1,57 @@
+//! Instances of the C++ components.
+//!
+//! A library can be used by several components of a graph: each one is an instance, initialized
+//! with its own configuration, that owns its C++ state, buffer pool (see `crate::pool`), latency
+//! statistics and tracer. Nothing is shared between the instances of a library.
+//!
+//! An instance is named after `instance` in its configuration, that `zenoh-flow-cxx-graphs` sets
+//! to the id of the component, or else `<library>#<n>` in the order of initialization. The name
+//! prefixes the logs of the wrapper, names the instance in the latency reports and the exported
+//! spans (`service.instance.id`), and is given to the C++ component in its configuration.
+
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// Configuration key naming the instance.
+pub const INSTANCE: &str = "instance";
+
+static INSTANCES: AtomicU64 = AtomicU64::new(0);
+
+/// Returns the name of a new instance of `library` and its configuration, where `instance` is
+/// set.
+pub fn configure(
+    configuration: &Option<HashMap<String, String>>,
+    library: &str,
+) -> (String, HashMap<String, String>) {
+    let mut configuration = configuration.clone().unwrap_or_default();
+    let name = configuration
+        .entry(INSTANCE.to_string())
+        .or_insert_with(|| format!("{}#{}", library, INSTANCES.fetch_add(1, Ordering::Relaxed)))
+        .clone();
+    (name, configuration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instances_are_named_by_the_configuration() {
+        let mut configuration = HashMap::new();
+        configuration.insert(INSTANCE.to_string(), "CxxFizz".to_string());
+        let (name, configured) = configure(&Some(configuration.clone()), "fizz");
+        assert_eq!(name, "CxxFizz");
+        assert_eq!(configured, configuration);
+    }
+
+    #[test]
+    fn unnamed_instances_are_numbered() {
+        let (first, configuration) = configure(&None, "fizz");
+        let (second, _) = configure(&None, "fizz");
+        assert!(first.starts_with("fizz#") && second.starts_with("fizz#"));
+        assert_ne!(first, second);
+        assert_eq!(configuration.get(INSTANCE), Some(&first));
+    }
+}
@@ -0,0 +1,393 @@
+//! Latency measurements of the C++ components.
+//!
+//! When `latency` is set in the configuration of a component, its wrapper measures, for every
+//! call to `run`:
+//!
+//! - `transit`: from the timestamp of the (earliest) input to the call of the wrapper,
+//! - `compute`: the time spent in the C++ component,
+//! - `overhead`: the time spent in the wrapper (conversions, serialization, ...),
+//! - `end_to_end`: from the output of the sample by the first component of the graph to the end
+//!   of the call, if the inputs carry their origin (see below).
+//!
+//! Every `REPORT_PERIOD` calls, the statistics of each measure are logged and, if `latency` is a
+//! path rather than `true`, written to that file as JSON.
+//!
+//! With `latency_metadata: "true"`, the measures also travel in the metadata of the outputs (see
+//! `crate::metadata`), so that the components downstream, sinks in particular, know the latency
+//! of the whole graph:
+//!
+//! - `latency/origin`: when the first component output the sample, in nanoseconds since the UNIX
+//!   epoch,
+//! - `latency/hops`: `<component>=<transit>,<compute>` for each component the sample went
+//!   through, in nanoseconds, separated by `;`.
+
+use crate::{
+    metadata::{self, Metadata},
+    trace::now,
+};
+use serde_json::json;
+use std::{collections::HashMap, fmt, path::PathBuf};
+use zenoh_flow::{ZFError, ZFResult};
+
+/// Configuration key enabling the measurements: `true`, or the path of the report.
+pub const LATENCY: &str = "latency";
+/// Configuration key enabling the measurements in the metadata of the outputs.
+pub const LATENCY_METADATA: &str = "latency_metadata";
+/// Configuration key overriding the name of the component in the report and the metadata.
+pub const LATENCY_NAME: &str = "latency_name";
+/// Metadata key of the time at which the sample left the first component.
+pub const ORIGIN: &str = "latency/origin";
+/// Metadata key of the measures of each component the sample went through.
+pub const HOPS: &str = "latency/hops";
+/// Number of calls between two reports.
+pub const REPORT_PERIOD: u64 = 100;
+
+/// Converts a timestamp of the runtime (NTP64: seconds in the high 32 bits, fraction of second
+/// in the low 32 bits, since the UNIX epoch) to nanoseconds.
+pub fn ntp64_to_nanos(timestamp: u64) -> u64 {
+    let seconds = timestamp >> 32;
+    let fraction = timestamp & 0xFFFF_FFFF;
+    seconds * 1_000_000_000 + ((fraction * 1_000_000_000) >> 32)
+}
+
+/// The measures of one call to `run`, in nanoseconds since the UNIX epoch.
+#[derive(Debug, Clone, Default)]
+pub struct Measure {
+    received: u64,
+    sent: Option<u64>,
+    origin: Option<u64>,
+    hops: Option<String>,
+    entry: u64,
+    exit: u64,
+}
+
+impl Measure {
+    /// Starts the measures of a call received at `received`.
+    pub fn start(received: u64) -> Self {
+        Self {
+            received,
+            ..Default::default()
+        }
+    }
+
+    /// Records an input, with its timestamp as given by the runtime and its metadata.
+    pub fn input(&mut self, timestamp: u64, metadata: &[(String, String)]) {
+        let sent = ntp64_to_nanos(timestamp);
+        self.sent = Some(self.sent.map_or(sent, |earliest| earliest.min(sent)));
+
+        for (key, value) in metadata {
+            if key == ORIGIN {
+                if let Ok(origin) = value.parse::<u64>() {
+                    self.origin = Some(self.origin.map_or(origin, |earliest| earliest.min(origin)));
+                }
+            } else if key == HOPS && self.hops.is_none() {
+                self.hops = Some(value.clone());
+            }
+        }
+    }
+
+    /// Marks the call of the C++ component.
+    pub fn enter(&mut self) {
+        self.entry = now();
+    }
+
+    /// Marks the return of the C++ component.
+    pub fn exit(&mut self) {
+        self.exit = now();
+    }
+
+    fn transit(&self) -> Option<u64> {
+        self.sent.map(|sent| self.received.saturating_sub(sent))
+    }
+
+    fn compute(&self) -> u64 {
+        self.exit.saturating_sub(self.entry)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Statistics {
+    count: u64,
+    min: u64,
+    max: u64,
+    sum: u128,
+}
+
+impl Statistics {
+    fn add(&mut self, value: u64) {
+        self.min = if self.count == 0 {
+            value
+        } else {
+            self.min.min(value)
+        };
+        self.max = self.max.max(value);
+        self.sum += value as u128;
+        self.count += 1;
+    }
+
+    fn mean(&self) -> u64 {
+        if self.count == 0 {
+            0
+        } else {
+            (self.sum / self.count as u128) as u64
+        }
+    }
+}
+
+const STAGES: [&str; 4] = ["transit", "compute", "overhead", "end_to_end"];
+
+/// The latency statistics of a component.
+#[derive(Debug)]
+pub struct Latency {
+    name: String,
+    report: Option<PathBuf>,
+    metadata: bool,
+    runs: u64,
+    /// Calls since the last report.
+    pending: u64,
+    statistics: [Statistics; 4],
+}
+
+impl Latency {
+    /// Returns the statistics of the component if `latency` is set in `configuration`, `name`
+    /// being the default name of the component.
+    pub fn from_configuration(
+        configuration: Option<&HashMap<String, String>>,
+        name: &str,
+    ) -> Option<Self> {
+        let configuration = configuration?;
+        let report = match configuration.get(LATENCY)?.as_str() {
+            "false" => return None,
+            "true" => None,
+            path => Some(PathBuf::from(path)),
+        };
+
+        Some(Self {
+            name: configuration
+                .get(LATENCY_NAME)
+                .cloned()
+                .unwrap_or_else(|| name.to_string()),
+            report,
+            metadata: configuration
+                .get(LATENCY_METADATA)
+                .map(|value| value == "true")
+                .unwrap_or(false),
+            runs: 0,
+            pending: 0,
+            statistics: Default::default(),
+        })
+    }
+
+    /// Adds the measures to the metadata of an output, if `latency_metadata` is set.
+    pub fn embed(&self, measure: &Measure, metadata: &mut Metadata) {
+        if !self.metadata {
+            return;
+        }
+
+        let origin = measure.origin.unwrap_or(measure.exit);
+        let hop = format!(
+            "{}={},{}",
+            self.name,
+            measure.transit().unwrap_or(0),
+            measure.compute()
+        );
+        let hops = match &measure.hops {
+            Some(hops) => format!("{};{}", hops, hop),
+            None => hop,
+        };
+        metadata::set(metadata, ORIGIN, &origin.to_string());
+        metadata::set(metadata, HOPS, &hops);
+    }
+
+    /// Records the measures of a call, once its outputs are ready. Returns the report every
+    /// `REPORT_PERIOD` calls, after writing it if a path is configured.
+    pub fn record(&mut self, measure: &Measure) -> ZFResult<Option<String>> {
+        let end = now();
+        let compute = measure.compute();
+        if let Some(transit) = measure.transit() {
+            self.statistics[0].add(transit);
+        }
+        self.statistics[1].add(compute);
+        self.statistics[2].add(end.saturating_sub(measure.received).saturating_sub(compute));
+        if let Some(origin) = measure.origin {
+            self.statistics[3].add(end.saturating_sub(origin));
+        }
+
+        self.runs += 1;
+        self.pending += 1;
+        if self.pending < REPORT_PERIOD {
+            return Ok(None);
+        }
+        self.pending = 0;
+
+        let report = self.to_string();
+        if let Some(path) = &self.report {
+            std::fs::write(path, format!("{}\n", report))
+                .map_err(|e| ZFError::IOError(format!("{}: {}", path.display(), e)))?;
+        }
+        Ok(Some(report))
+    }
+}
+
+/// The report, as JSON: the count, minimum, mean and maximum of each measure, in nanoseconds.
+impl fmt::Display for Latency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut stages = serde_json::Map::new();
+        for (stage, statistics) in STAGES.iter().zip(self.statistics.iter()) {
+            if statistics.count > 0 {
+                stages.insert(
+                    stage.to_string(),
+                    json!({
+                        "count": statistics.count,
+                        "min_ns": statistics.min,
+                        "mean_ns": statistics.mean(),
+                        "max_ns": statistics.max,
+                    }),
+                );
+            }
+        }
+
+        let report = json!({
+            "component": self.name,
+            "runs": self.runs,
+            "stages": stages,
+        });
+        write!(f, "{}", report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn configuration(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn ntp64_timestamps_are_converted() {
+        assert_eq!(ntp64_to_nanos(0), 0);
+        assert_eq!(ntp64_to_nanos(3 << 32), 3_000_000_000);
+        assert_eq!(ntp64_to_nanos((1 << 32) | (1 << 31)), 1_500_000_000);
+    }
+
+    #[test]
+    fn latency_is_configured() {
+        assert!(Latency::from_configuration(None, "fizz").is_none());
+        let off = configuration(&[(LATENCY, "false")]);
+        assert!(Latency::from_configuration(Some(&off), "fizz").is_none());
+
+        let on = configuration(&[(LATENCY, "true")]);
+        let latency = Latency::from_configuration(Some(&on), "fizz").unwrap();
+        assert_eq!(
+            (latency.name.as_str(), latency.report.is_none()),
+            ("fizz", true)
+        );
+
+        let named = configuration(&[(LATENCY, "/tmp/latency.json"), (LATENCY_NAME, "shout")]);
+        let latency = Latency::from_configuration(Some(&named), "fizz").unwrap();
+        assert_eq!(latency.name, "shout");
+        assert_eq!(latency.report, Some(PathBuf::from("/tmp/latency.json")));
+    }
+
+    #[test]
+    fn inputs_keep_the_earliest_origin() {
+        let mut measure = Measure::start(10_000_000_000);
+        measure.input(2 << 32, &[(ORIGIN.to_string(), "500".to_string())]);
+        measure.input(
+            1 << 32,
+            &[
+                (ORIGIN.to_string(), "300".to_string()),
+                (HOPS.to_string(), "source=1,2".to_string()),
+            ],
+        );
+        assert_eq!(measure.origin, Some(300));
+        assert_eq!(measure.transit(), Some(9_000_000_000));
+        assert_eq!(measure.hops.as_deref(), Some("source=1,2"));
+    }
+
+    #[test]
+    fn hops_are_appended_to_the_metadata() {
+        let on = configuration(&[(LATENCY, "true"), (LATENCY_METADATA, "true")]);
+        let latency = Latency::from_configuration(Some(&on), "fizz").unwrap();
+        let mut measure = Measure::start(3_000_000_000);
+        measure.input(
+            2 << 32,
+            &[
+                (ORIGIN.to_string(), "42".to_string()),
+                (HOPS.to_string(), "source=1,2".to_string()),
+            ],
+        );
+        measure.entry = 10;
+        measure.exit = 25;
+
+        let mut metadata = Metadata::new();
+        latency.embed(&measure, &mut metadata);
+        assert_eq!(
+            metadata,
+            vec![
+                (ORIGIN.to_string(), "42".to_string()),
+                (
+                    HOPS.to_string(),
+                    "source=1,2;fizz=1000000000,15".to_string()
+                ),
+            ]
+        );
+
+        let silent = configuration(&[(LATENCY, "true")]);
+        let mut metadata = Metadata::new();
+        Latency::from_configuration(Some(&silent), "fizz")
+            .unwrap()
+            .embed(&measure, &mut metadata);
+        assert!(metadata.is_empty());
+    }
+
+    #[test]
+    fn reports_are_written_every_period() {
+        let path = std::env::temp_dir().join(format!("latency-{}.json", std::process::id()));
+        let on = configuration(&[(LATENCY, path.to_str().unwrap())]);
+        let mut latency = Latency::from_configuration(Some(&on), "fizz").unwrap();
+
+        let mut measure = Measure::start(now());
+        measure.enter();
+        measure.exit();
+        for _ in 1..REPORT_PERIOD {
+            assert_eq!(latency.record(&measure).unwrap(), None);
+        }
+        let report = latency.record(&measure).unwrap().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            format!("{}\n", report)
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        let report: serde_json::Value = serde_json::from_str(&report).unwrap();
+        assert_eq!(report["component"], "fizz");
+        assert_eq!(report["runs"], REPORT_PERIOD);
+        assert_eq!(report["stages"]["compute"]["count"], REPORT_PERIOD);
+        // Without the timestamps of the inputs, nor their origin.
+        assert!(report["stages"].get("transit").is_none());
+        assert!(report["stages"].get("end_to_end").is_none());
+    }
+
+    #[test]
+    fn statistics_track_min_mean_max() {
+        let mut statistics = Statistics::default();
+        assert_eq!(statistics.mean(), 0);
+        for value in [4, 2, 9] {
+            statistics.add(value);
+        }
+        assert_eq!(
+            (
+                statistics.count,
+                statistics.min,
+                statistics.mean(),
+                statistics.max
+            ),
+            (3, 2, 5, 9)
+        );
+    }
+}
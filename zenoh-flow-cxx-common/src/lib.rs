@@ -0,0 +1,28 @@
+//! Rust-side helpers shared by the wrappers of the C++ components.
+//!
+//! The `cxx` bridges cannot be shared (each component has its own `ffi` module) so everything in
+//! this crate works on plain Rust types, but for the wrappers around the bridges, generated by the
+//! `export_cxx_*!` macros (see `wrapper`).
+
+extern crate zenoh_flow;
+
+pub mod batch;
+pub mod checkpoint;
+pub mod codec;
+pub mod compression;
+pub mod envelope;
+#[cfg(feature = "flatbuffers")]
+pub mod flatbuffers;
+pub mod instance;
+pub mod latency;
+pub mod metadata;
+pub mod opaque;
+pub mod pool;
+pub mod ports;
+pub mod pretty;
+#[cfg(feature = "protobuf")]
+pub mod protobuf;
+pub mod shm;
+pub mod threading;
+pub mod trace;
+mod wrapper;
@@ -0,0 +1,95 @@
+//! Key/value metadata attached to the messages of C++ components (frame id, sensor id, ...).
+//!
+//! Metadata travels in the envelope of a payload (see `crate::envelope`), next to it, on the ports
+//! carrying envelopes. On the other ports, Rust components in particular expect the payload alone:
+//! the metadata is dropped, and the wrapper warns about it once per port.
+//!
+//! An operator only forwards the metadata of its inputs to its outputs if it opts in:
+//!
+//! ```yaml
+//! configuration:
+//!   forward_metadata: "true"
+//! ```
+
+use std::collections::HashMap;
+
+/// Configuration key enabling the forwarding of the metadata of the inputs to the outputs.
+pub const FORWARD_METADATA: &str = "forward_metadata";
+
+/// Metadata of a message, in insertion order. Keys are unique.
+pub type Metadata = Vec<(String, String)>;
+
+/// Whether `forward_metadata` is set in `configuration`.
+pub fn forwards(configuration: Option<&HashMap<String, String>>) -> bool {
+    configuration
+        .and_then(|configuration| configuration.get(FORWARD_METADATA))
+        .map(|value| value == "true")
+        .unwrap_or(false)
+}
+
+/// Inserts or replaces `key` in `metadata`.
+pub fn set(metadata: &mut Metadata, key: &str, value: &str) {
+    match metadata.iter_mut().find(|(k, _)| k == key) {
+        Some((_, v)) => *v = value.to_string(),
+        None => metadata.push((key.to_string(), value.to_string())),
+    }
+}
+
+/// Adds to `output` the entries of `inputs` it does not define: what the component set on its
+/// output takes precedence, then the inputs in the order given.
+pub fn forward<'a, I>(inputs: I, output: &mut Metadata)
+where
+    I: IntoIterator<Item = &'a Metadata>,
+{
+    for input in inputs {
+        for (key, value) in input {
+            if !output.iter().any(|(k, _)| k == key) {
+                output.push((key.clone(), value.clone()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(entries: &[(&str, &str)]) -> Metadata {
+        entries
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn forwarding_is_opt_in() {
+        let mut configuration = HashMap::new();
+        assert!(!forwards(None));
+        assert!(!forwards(Some(&configuration)));
+        configuration.insert(FORWARD_METADATA.to_string(), "true".to_string());
+        assert!(forwards(Some(&configuration)));
+    }
+
+    #[test]
+    fn set_replaces_in_place() {
+        let mut entries = metadata(&[("frame", "1"), ("sensor", "lidar")]);
+        set(&mut entries, "frame", "2");
+        set(&mut entries, "unit", "m");
+        assert_eq!(
+            entries,
+            metadata(&[("frame", "2"), ("sensor", "lidar"), ("unit", "m")])
+        );
+    }
+
+    #[test]
+    fn outputs_take_precedence_over_inputs() {
+        let first = metadata(&[("frame", "1"), ("sensor", "lidar")]);
+        let second = metadata(&[("sensor", "camera"), ("unit", "m")]);
+        let mut output = metadata(&[("frame", "9")]);
+        forward(&[first, second], &mut output);
+        assert_eq!(
+            output,
+            metadata(&[("frame", "9"), ("sensor", "lidar"), ("unit", "m")])
+        );
+    }
+}
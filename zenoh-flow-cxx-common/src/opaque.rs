@@ -0,0 +1,126 @@
+//! Rust data passed through C++ operators without serialization.
+//!
+//! Within a runtime, a component hands its outputs to the next one as Rust values. The wrapper of
+//! a C++ operator serializes them for the C++ component, and a Rust component downstream has to
+//! deserialize them again. With `opaque_inputs` set in the configuration of the operator, the
+//! wrapper keeps them as they are: `Input::data` is empty and `Input::handle` refers to the value.
+//! The component forwards it by setting `Output::handle`, and the value goes downstream untouched:
+//!
+//! ```yaml
+//! configuration:
+//!   opaque_inputs: "true"
+//! ```
+//!
+//! The payload of an opaque input is still available through `payload(input)`, serialized on
+//! first access (see `zenoh_flow.hpp`). A handle is only valid in the call to `run` that receives
+//! its input.
+
+use crate::ports::Ports;
+use std::{collections::HashMap, fmt, sync::Arc};
+use zenoh_flow::{Data, ZFError, ZFResult};
+
+/// Configuration key enabling the opaque inputs.
+pub const OPAQUE_INPUTS: &str = "opaque_inputs";
+/// Handle of an input or output without Rust value.
+pub const NO_HANDLE: u64 = 0;
+
+/// Whether `opaque_inputs` is set in `configuration`.
+pub fn enabled(configuration: Option<&HashMap<String, String>>) -> bool {
+    configuration
+        .and_then(|configuration| configuration.get(OPAQUE_INPUTS))
+        .map(|value| value == "true")
+        .unwrap_or(false)
+}
+
+/// The Rust values of the inputs given to the C++ component, until its next call to `run`.
+#[derive(Default)]
+pub struct Handles(Vec<Arc<dyn Data>>);
+
+impl Handles {
+    /// Keeps `data` and returns its handle.
+    pub fn insert(&mut self, data: Arc<dyn Data>) -> u64 {
+        self.0.push(data);
+        self.0.len() as u64
+    }
+
+    pub fn get(&self, handle: u64) -> ZFResult<Arc<dyn Data>> {
+        (handle as usize)
+            .checked_sub(1)
+            .and_then(|index| self.0.get(index))
+            .cloned()
+            .ok_or_else(|| ZFError::InvalidData(format!("Unknown handle: {}", handle)))
+    }
+
+    /// Returns the value of `handle`, forwarded by the C++ component on the output `port_id`: the
+    /// port is checked as for any other output.
+    pub fn forward(&self, ports: &Ports, port_id: &str, handle: u64) -> ZFResult<Arc<dyn Data>> {
+        ports.check_port(port_id)?;
+        self.get(handle)
+    }
+}
+
+impl fmt::Debug for Handles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Handles")
+            .field("len", &self.0.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::Typed;
+
+    fn number(data: &Arc<dyn Data>) -> Option<u64> {
+        data.as_any()
+            .downcast_ref::<Typed<u64>>()
+            .map(|typed| typed.0)
+    }
+
+    #[test]
+    fn opaque_inputs_are_opt_in() {
+        let mut configuration = HashMap::new();
+        assert!(!enabled(None));
+        configuration.insert(OPAQUE_INPUTS.to_string(), "false".to_string());
+        assert!(!enabled(Some(&configuration)));
+        configuration.insert(OPAQUE_INPUTS.to_string(), "true".to_string());
+        assert!(enabled(Some(&configuration)));
+    }
+
+    #[test]
+    fn handles_refer_to_their_value() {
+        let mut handles = Handles::default();
+        let first = handles.insert(Arc::new(Typed(1_u64)));
+        let second = handles.insert(Arc::new(Typed(2_u64)));
+        assert_ne!(first, NO_HANDLE);
+        assert_ne!(first, second);
+        assert_eq!(number(&handles.get(first).unwrap()), Some(1));
+        assert_eq!(number(&handles.get(second).unwrap()), Some(2));
+    }
+
+    #[test]
+    fn unknown_handles_are_refused() {
+        let mut handles = Handles::default();
+        assert!(handles.get(NO_HANDLE).is_err());
+        let handle = handles.insert(Arc::new(Typed(1_u64)));
+        assert!(handles.get(handle + 1).is_err());
+        assert!(handles.get(NO_HANDLE).is_err());
+    }
+
+    #[test]
+    fn handles_are_forwarded_on_declared_ports() {
+        let mut handles = Handles::default();
+        let handle = handles.insert(Arc::new(Typed(1_u64)));
+
+        let undeclared = Ports::default();
+        assert!(handles.forward(&undeclared, "buzz", handle).is_ok());
+
+        let mut configuration = HashMap::new();
+        configuration.insert("output/fizz".to_string(), "u64".to_string());
+        let ports = Ports::from_configuration(Some(&configuration)).unwrap();
+        let forwarded = handles.forward(&ports, "fizz", handle).unwrap();
+        assert_eq!(number(&forwarded), Some(1));
+        assert!(handles.forward(&ports, "buzz", handle).is_err());
+    }
+}
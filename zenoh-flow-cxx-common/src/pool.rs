@@ -0,0 +1,272 @@
+//! Pooled payload buffers of the C++ components.
+//!
+//! C++ components get the buffers of their outputs from `allocate(size)` rather than building
+//! fresh vectors. When `buffer_pool` is set in the configuration of a component, the buffers of
+//! its outputs return to a pool when the last reference to the message is dropped (downstream, or
+//! once serialized if it leaves the runtime), and `allocate` reuses them:
+//!
+//! ```yaml
+//! configuration:
+//!   buffer_pool: "16"   # buffers kept in the pool
+//! ```
+//!
+//! Each instance of a component has its own pool (see `crate::instance`): `allocate` uses the
+//! pool of the instance whose `run` is being called on the thread. With fixed-size payloads
+//! (frames, ...) no allocation happens once it is warm.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+};
+use zenoh_flow::{Data, DowncastAny, ZFError, ZFResult};
+
+/// Configuration key enabling the pool: number of buffers it keeps.
+pub const BUFFER_POOL: &str = "buffer_pool";
+
+#[derive(Debug)]
+pub struct BufferPool {
+    buffers: Mutex<Vec<Vec<u8>>>,
+    /// Number of buffers kept.
+    capacity: usize,
+}
+
+impl BufferPool {
+    /// Returns a pool if `buffer_pool` is set, to at least one buffer, in `configuration`.
+    pub fn from_configuration(
+        configuration: Option<&HashMap<String, String>>,
+    ) -> ZFResult<Option<Arc<Self>>> {
+        let capacity = match configuration.and_then(|configuration| configuration.get(BUFFER_POOL))
+        {
+            Some(capacity) => capacity.parse::<usize>().map_err(|_| {
+                ZFError::ParsingError(format!("Invalid `{}`: {}", BUFFER_POOL, capacity))
+            })?,
+            None => return Ok(None),
+        };
+        if capacity == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(Arc::new(Self {
+            buffers: Mutex::new(Vec::with_capacity(capacity)),
+            capacity,
+        })))
+    }
+
+    /// Returns a buffer of `size` bytes, a pooled one if one is large enough. The content of a
+    /// pooled buffer is whatever its previous message left.
+    pub fn allocate(&self, size: usize) -> Vec<u8> {
+        let pooled = match self.buffers.lock() {
+            Ok(mut buffers) => buffers
+                .iter()
+                .rposition(|buffer| buffer.capacity() >= size)
+                .map(|index| buffers.swap_remove(index)),
+            Err(_) => None,
+        };
+
+        match pooled {
+            Some(mut buffer) => {
+                buffer.resize(size, 0);
+                buffer
+            }
+            None => vec![0; size],
+        }
+    }
+
+    /// Returns `buffer` to the pool, unless the pool is full.
+    pub fn recycle(&self, buffer: Vec<u8>) {
+        if buffer.capacity() == 0 {
+            return;
+        }
+        if let Ok(mut buffers) = self.buffers.lock() {
+            if buffers.len() < self.capacity {
+                buffers.push(buffer);
+            }
+        }
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Arc<BufferPool>>> = const { RefCell::new(None) };
+}
+
+/// Makes `pool` the pool of `allocate` on this thread, until the scope is dropped.
+pub fn enter(pool: Option<&Arc<BufferPool>>) -> Scope {
+    Scope(CURRENT.with(|current| current.replace(pool.cloned())))
+}
+
+/// The pool of an instance in use on the thread, see `enter`.
+pub struct Scope(Option<Arc<BufferPool>>);
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+/// Returns a buffer of `size` bytes, from the pool of the current instance if it has one.
+pub fn allocate(size: usize) -> Vec<u8> {
+    CURRENT.with(|current| match current.borrow().as_ref() {
+        Some(pool) => pool.allocate(size),
+        None => vec![0; size],
+    })
+}
+
+/// A message whose payload buffer can be handed back to the pool.
+pub trait Recycle {
+    fn take_buffer(&mut self) -> Vec<u8>;
+}
+
+/// A message returning its buffer to the pool of its instance once dropped.
+pub struct Pooled<T: Recycle> {
+    data: T,
+    pool: Arc<BufferPool>,
+}
+
+impl<T: Recycle> Drop for Pooled<T> {
+    fn drop(&mut self) {
+        self.pool.recycle(self.data.take_buffer());
+    }
+}
+
+impl<T: Recycle + fmt::Debug> fmt::Debug for Pooled<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.data.fmt(f)
+    }
+}
+
+impl<T: Recycle + DowncastAny + 'static> DowncastAny for Pooled<T> {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self.data.as_any()
+    }
+
+    fn as_mut_any(&mut self) -> &mut dyn std::any::Any {
+        self.data.as_mut_any()
+    }
+}
+
+impl<T: Recycle + Data + 'static> Data for Pooled<T> {
+    fn try_serialize(&self) -> ZFResult<Vec<u8>> {
+        self.data.try_serialize()
+    }
+}
+
+/// Shares `data` with the runtime, returning its buffer to `pool` once dropped.
+pub fn share<T: Recycle + Data + 'static>(
+    pool: Option<&Arc<BufferPool>>,
+    data: T,
+) -> Arc<dyn Data> {
+    match pool {
+        Some(pool) => Arc::new(Pooled {
+            data,
+            pool: pool.clone(),
+        }),
+        None => Arc::new(data),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A message owning its payload, as produced by a wrapper.
+    #[derive(Debug)]
+    struct Buffer(Vec<u8>);
+
+    impl DowncastAny for Buffer {
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+
+        fn as_mut_any(&mut self) -> &mut dyn std::any::Any {
+            self
+        }
+    }
+
+    impl Data for Buffer {
+        fn try_serialize(&self) -> ZFResult<Vec<u8>> {
+            Ok(self.0.clone())
+        }
+    }
+
+    impl Recycle for Buffer {
+        fn take_buffer(&mut self) -> Vec<u8> {
+            std::mem::take(&mut self.0)
+        }
+    }
+
+    fn pool(capacity: &str) -> ZFResult<Option<Arc<BufferPool>>> {
+        let mut configuration = HashMap::new();
+        configuration.insert(BUFFER_POOL.to_string(), capacity.to_string());
+        BufferPool::from_configuration(Some(&configuration))
+    }
+
+    fn pooled(pool: &BufferPool) -> usize {
+        pool.buffers.lock().unwrap().len()
+    }
+
+    #[test]
+    fn pool_is_configured() {
+        assert!(BufferPool::from_configuration(None).unwrap().is_none());
+        assert!(pool("0").unwrap().is_none());
+        assert!(pool("2").unwrap().is_some());
+        assert!(pool("many").is_err());
+    }
+
+    #[test]
+    fn buffers_are_reused() {
+        let pool = pool("1").unwrap().unwrap();
+        let buffer = pool.allocate(64);
+        assert_eq!(buffer.len(), 64);
+        let address = buffer.as_ptr();
+        pool.recycle(buffer);
+
+        let reused = pool.allocate(16);
+        assert_eq!((reused.len(), reused.as_ptr()), (16, address));
+        assert_eq!(pooled(&pool), 0);
+        // Too small for the next one.
+        pool.recycle(reused);
+        let larger = pool.allocate(128);
+        assert_ne!(larger.as_ptr(), address);
+        assert_eq!(pooled(&pool), 1);
+    }
+
+    #[test]
+    fn a_full_pool_drops_buffers() {
+        let pool = pool("1").unwrap().unwrap();
+        pool.recycle(vec![0; 8]);
+        pool.recycle(vec![0; 8]);
+        pool.recycle(Vec::new());
+        assert_eq!(pooled(&pool), 1);
+    }
+
+    #[test]
+    fn shared_messages_return_their_buffer() {
+        let pool = pool("1").unwrap().unwrap();
+        let data = share(Some(&pool), Buffer(vec![1, 2, 3]));
+        assert_eq!(data.try_serialize().unwrap(), vec![1, 2, 3]);
+        assert!(data.as_any().downcast_ref::<Buffer>().is_some());
+        assert_eq!(pooled(&pool), 0);
+        drop(data);
+        assert_eq!(pooled(&pool), 1);
+
+        drop(share(None, Buffer(vec![1, 2, 3])));
+        assert_eq!(pooled(&pool), 1);
+    }
+
+    #[test]
+    fn allocate_uses_the_pool_of_the_scope() {
+        let pool = pool("1").unwrap().unwrap();
+        pool.recycle(vec![0; 32]);
+        {
+            let _scope = enter(Some(&pool));
+            assert_eq!(allocate(8).len(), 8);
+            assert_eq!(pooled(&pool), 0);
+        }
+        pool.recycle(vec![0; 32]);
+        assert_eq!(allocate(8).len(), 8);
+        assert_eq!(pooled(&pool), 1);
+    }
+}
@@ -0,0 +1,377 @@
+use crate::{
+    compression,
+    envelope::{Framing, Reception, Sequence, ENVELOPE_INPUTS, ENVELOPE_OUTPUTS},
+};
+use std::{collections::HashMap, fmt, sync::Arc};
+use zenoh_flow::{Data, PortId, ZFError, ZFResult};
+
+/// Configuration key prefix declaring an input: `input/<port id>: <type>`.
+pub const INPUT_PREFIX: &str = "input/";
+/// Configuration key prefix declaring an output: `output/<port id>: <type>`.
+pub const OUTPUT_PREFIX: &str = "output/";
+/// Configuration key enabling the payload size check of fixed-width types.
+pub const VALIDATE_PAYLOADS: &str = "validate_payloads";
+/// Configuration key allowing several outputs on the same port in one call to `run`, on the ports
+/// carrying envelopes: they are sent as one message, that only C++ components can read.
+pub const OUTPUT_SEQUENCES: &str = "output_sequences";
+/// Configuration key leaving the `port_id` of the inputs empty: components use the handles.
+pub const PORT_HANDLES: &str = "port_handles";
+/// Handle of a port that is not declared in the configuration.
+pub const NO_HANDLE: u32 = u32::MAX;
+
+/// Returns the size, in bytes, of the payload of a fixed-width type or `None` if the size of its
+/// payloads can vary.
+pub fn fixed_width(port_type: &str) -> Option<usize> {
+    match port_type {
+        "bool" | "u8" | "i8" | "char" | "unsigned char" => Some(1),
+        "u16" | "i16" => Some(2),
+        "u32" | "i32" | "f32" | "float" => Some(4),
+        "u64" | "i64" | "f64" | "double" => Some(8),
+        _ => None,
+    }
+}
+
+/// The ports declared in the descriptor of a component, and their types.
+///
+/// The wrappers cannot access the descriptor: the ports are read from the configuration of the
+/// component, where `zenoh-flow-cxx-graphs` copies them when rendering a graph.
+///
+/// Each declared port also gets a handle, its index among the inputs (or the outputs) sorted by
+/// id: the C++ components can exchange handles rather than strings with their wrapper, see the
+/// headers generated by `zenoh-flow-cxx-graphs --headers`.
+#[derive(Debug, Clone, Default)]
+pub struct Ports {
+    inputs: HashMap<String, String>,
+    outputs: HashMap<String, String>,
+    input_handles: Vec<String>,
+    output_handles: Vec<PortId>,
+    validate_payloads: bool,
+    output_sequences: bool,
+    port_handles: bool,
+    envelope_inputs: Framing,
+    envelope_outputs: Framing,
+    max_payload: usize,
+}
+
+impl Ports {
+    /// Reads the ports from `configuration`, and checks it: `output_sequences` needs outputs
+    /// carrying envelopes.
+    pub fn from_configuration(configuration: Option<&HashMap<String, String>>) -> ZFResult<Self> {
+        let mut ports = Self {
+            envelope_inputs: Framing::from_configuration(configuration, ENVELOPE_INPUTS),
+            envelope_outputs: Framing::from_configuration(configuration, ENVELOPE_OUTPUTS),
+            max_payload: compression::max_payload(configuration)?,
+            ..Self::default()
+        };
+        let configuration = match configuration {
+            Some(configuration) => configuration,
+            None => return Ok(ports),
+        };
+
+        for (key, value) in configuration {
+            if let Some(port_id) = key.strip_prefix(INPUT_PREFIX) {
+                ports.inputs.insert(port_id.to_string(), value.clone());
+            } else if let Some(port_id) = key.strip_prefix(OUTPUT_PREFIX) {
+                ports.outputs.insert(port_id.to_string(), value.clone());
+            } else if key == VALIDATE_PAYLOADS {
+                ports.validate_payloads = value == "true";
+            } else if key == OUTPUT_SEQUENCES {
+                ports.output_sequences = value == "true";
+            } else if key == PORT_HANDLES {
+                ports.port_handles = value == "true";
+            }
+        }
+
+        ports.input_handles = ports.inputs.keys().cloned().collect();
+        ports.input_handles.sort_unstable();
+        let mut outputs: Vec<&String> = ports.outputs.keys().collect();
+        outputs.sort_unstable();
+        ports.output_handles = outputs
+            .into_iter()
+            .map(|port_id| PortId::from(port_id.clone()))
+            .collect();
+
+        if ports.output_sequences && ports.envelope_outputs == Framing::None {
+            return Err(ZFError::InvalidData(format!(
+                "`{}` is set but no output is in `{}`: sequences can only be sent to C++ \
+                 components",
+                OUTPUT_SEQUENCES, ENVELOPE_OUTPUTS
+            )));
+        }
+        Ok(ports)
+    }
+
+    /// Returns the handle of the input `port_id`, `NO_HANDLE` if it is not declared.
+    pub fn input_handle(&self, port_id: &str) -> u32 {
+        self.input_handles
+            .binary_search_by(|id| id.as_str().cmp(port_id))
+            .map(|index| index as u32)
+            .unwrap_or(NO_HANDLE)
+    }
+
+    /// Returns the `port_id` given to the C++ component for the input `port_id`: empty if
+    /// `port_handles` is set and the input is declared (the component uses its handle).
+    pub fn input_id(&self, port_id: &str) -> String {
+        if self.port_handles && self.input_handle(port_id) != NO_HANDLE {
+            String::new()
+        } else {
+            port_id.to_string()
+        }
+    }
+
+    /// Returns the id of the output of handle `handle`, without allocating.
+    pub fn output_port(&self, handle: u32) -> ZFResult<&PortId> {
+        self.output_handles.get(handle as usize).ok_or_else(|| {
+            ZFError::InvalidData(format!(
+                "Output on unknown port handle {}, {} outputs declared",
+                handle,
+                self.output_handles.len()
+            ))
+        })
+    }
+
+    /// How the messages of the input `port_id` are received: whether they are envelopes, see
+    /// `crate::envelope`, and the size of their decompressed payloads.
+    pub fn reception(&self, port_id: &str) -> Reception {
+        Reception {
+            framed: self.envelope_inputs.contains(port_id),
+            max_payload: self.max_payload,
+        }
+    }
+
+    /// Whether the messages sent on the output `port_id` are envelopes.
+    pub fn frames_output(&self, port_id: &str) -> bool {
+        self.envelope_outputs.contains(port_id)
+    }
+
+    pub fn input_type(&self, port_id: &str) -> Option<&str> {
+        self.inputs.get(port_id).map(|port_type| port_type.as_str())
+    }
+
+    pub fn output_type(&self, port_id: &str) -> Option<&str> {
+        self.outputs
+            .get(port_id)
+            .map(|port_type| port_type.as_str())
+    }
+
+    /// Checks that the C++ component produced an output on a declared port, and returns its type.
+    ///
+    /// Nothing is checked if no output is declared.
+    pub fn check_port(&self, port_id: &str) -> ZFResult<Option<&str>> {
+        if self.outputs.is_empty() {
+            return Ok(None);
+        }
+
+        self.output_type(port_id).map(Some).ok_or_else(|| {
+            let mut declared: Vec<&str> = self.outputs.keys().map(|id| id.as_str()).collect();
+            declared.sort_unstable();
+            ZFError::InvalidData(format!(
+                "Output on unknown port <{}>, declared outputs: {:?}",
+                port_id, declared
+            ))
+        })
+    }
+
+    /// Checks that the C++ component produced `data` on a declared output.
+    ///
+    /// Nothing is checked if no output is declared. The size of the payload is only checked if
+    /// `validate_payloads` is set and the type of the port has a fixed width.
+    pub fn check_output(&self, port_id: &str, data: &[u8]) -> ZFResult<()> {
+        let port_type = match self.check_port(port_id)? {
+            Some(port_type) => port_type,
+            None => return Ok(()),
+        };
+
+        if self.validate_payloads {
+            if let Some(size) = fixed_width(port_type) {
+                if data.len() != size {
+                    return Err(ZFError::InvalidData(format!(
+                        "Output on port <{}> of type `{}` should be {} bytes, got {}",
+                        port_id,
+                        port_type,
+                        size,
+                        data.len()
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Groups the outputs of a call to `run` by port, in the order of their first output.
+    ///
+    /// Several outputs on the same port are an error, unless `output_sequences` is set and the
+    /// port carries envelopes: they are then sent in order, as a `Sequence` (only C++ components
+    /// know how to receive it).
+    pub fn group_outputs<K: PartialEq + fmt::Display>(
+        &self,
+        outputs: Vec<(K, Arc<dyn Data>)>,
+    ) -> ZFResult<Vec<(K, Arc<dyn Data>)>> {
+        let mut grouped: Vec<(K, Vec<Arc<dyn Data>>)> = Vec::with_capacity(outputs.len());
+        for (port_id, data) in outputs {
+            match grouped.iter_mut().find(|(id, _)| *id == port_id) {
+                Some(_) if !self.output_sequences => {
+                    return Err(ZFError::InvalidData(format!(
+                        "Several outputs on port <{}> in one call, set `{}` to send them in order",
+                        port_id, OUTPUT_SEQUENCES
+                    )))
+                }
+                Some(_) if !self.frames_output(&port_id.to_string()) => {
+                    return Err(ZFError::InvalidData(format!(
+                        "Several outputs on port <{}> in one call, only sent in order on the ports \
+                         of `{}`",
+                        port_id, ENVELOPE_OUTPUTS
+                    )))
+                }
+                Some((_, sequence)) => sequence.push(data),
+                None => grouped.push((port_id, vec![data])),
+            }
+        }
+
+        Ok(grouped
+            .into_iter()
+            .map(|(port_id, mut sequence)| {
+                let data: Arc<dyn Data> = if sequence.len() == 1 {
+                    sequence.remove(0)
+                } else {
+                    Arc::new(Sequence(sequence))
+                };
+                (port_id, data)
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zenoh_flow::DowncastAny;
+
+    #[derive(Debug)]
+    struct Number(u64);
+
+    impl DowncastAny for Number {
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+
+        fn as_mut_any(&mut self) -> &mut dyn std::any::Any {
+            self
+        }
+    }
+
+    impl Data for Number {
+        fn try_serialize(&self) -> ZFResult<Vec<u8>> {
+            Ok(self.0.to_le_bytes().to_vec())
+        }
+    }
+
+    fn ports(entries: &[(&str, &str)]) -> ZFResult<Ports> {
+        let configuration: HashMap<String, String> = entries
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        Ports::from_configuration(Some(&configuration))
+    }
+
+    fn outputs() -> Vec<(String, Arc<dyn Data>)> {
+        vec![
+            ("fizz".to_string(), Arc::new(Number(1)) as Arc<dyn Data>),
+            ("fizz".to_string(), Arc::new(Number(2)) as Arc<dyn Data>),
+        ]
+    }
+
+    #[test]
+    fn sequences_need_envelopes() {
+        assert!(ports(&[(OUTPUT_SEQUENCES, "true")]).is_err());
+
+        // Only on the ports carrying envelopes.
+        let other = ports(&[(OUTPUT_SEQUENCES, "true"), (ENVELOPE_OUTPUTS, "buzz")]).unwrap();
+        assert!(other.group_outputs(outputs()).is_err());
+
+        let framed = ports(&[(OUTPUT_SEQUENCES, "true"), (ENVELOPE_OUTPUTS, "fizz")]).unwrap();
+        let grouped = framed.group_outputs(outputs()).unwrap();
+        assert_eq!(grouped.len(), 1);
+        assert!(grouped[0].1.as_any().is::<Sequence>());
+    }
+
+    #[test]
+    fn framing_is_per_port() {
+        let ports = ports(&[(ENVELOPE_INPUTS, "number"), (ENVELOPE_OUTPUTS, "true")]).unwrap();
+        assert!(ports.reception("number").framed);
+        assert!(!ports.reception("tick").framed);
+        assert!(ports.frames_output("fizz"));
+    }
+
+    #[test]
+    fn max_payload_is_parsed() {
+        let capped = ports(&[(compression::MAX_PAYLOAD, "1024")]).unwrap();
+        assert_eq!(capped.reception("number").max_payload, 1024);
+        assert_eq!(
+            Ports::from_configuration(None)
+                .unwrap()
+                .reception("number")
+                .max_payload,
+            compression::DEFAULT_MAX_PAYLOAD
+        );
+        assert!(ports(&[(compression::MAX_PAYLOAD, "1MB")]).is_err());
+    }
+
+    #[test]
+    fn fixed_widths() {
+        for port_type in &["bool", "u8", "i8", "char", "unsigned char"] {
+            assert_eq!(fixed_width(port_type), Some(1), "{}", port_type);
+        }
+        for port_type in &["u16", "i16"] {
+            assert_eq!(fixed_width(port_type), Some(2), "{}", port_type);
+        }
+        for port_type in &["u32", "i32", "f32", "float"] {
+            assert_eq!(fixed_width(port_type), Some(4), "{}", port_type);
+        }
+        for port_type in &["u64", "i64", "f64", "double"] {
+            assert_eq!(fixed_width(port_type), Some(8), "{}", port_type);
+        }
+        for port_type in &[
+            "",
+            "string",
+            "bytes",
+            "u128",
+            "json",
+            "protobuf:sensors.Imu",
+        ] {
+            assert_eq!(fixed_width(port_type), None, "{}", port_type);
+        }
+    }
+
+    #[test]
+    fn outputs_on_unknown_ports_are_refused() {
+        let declared = ports(&[("output/fizz", "u64")]).unwrap();
+        assert!(declared.check_output("fizz", &[0; 8]).is_ok());
+        assert!(declared.check_output("buzz", &[0; 8]).is_err());
+
+        // Nothing is checked without declared outputs.
+        let undeclared = ports(&[("input/number", "u64")]).unwrap();
+        assert!(undeclared.check_output("buzz", &[0; 8]).is_ok());
+    }
+
+    #[test]
+    fn payload_sizes_are_validated_on_demand() {
+        let unchecked = ports(&[("output/fizz", "u64"), ("output/text", "string")]).unwrap();
+        assert!(unchecked.check_output("fizz", &[0; 4]).is_ok());
+
+        let checked = ports(&[
+            ("output/fizz", "u64"),
+            ("output/text", "string"),
+            (VALIDATE_PAYLOADS, "true"),
+        ])
+        .unwrap();
+        assert!(checked.check_output("fizz", &[0; 8]).is_ok());
+        assert!(checked.check_output("fizz", &[0; 4]).is_err());
+        assert!(checked.check_output("fizz", &[0; 9]).is_err());
+        assert!(checked.check_output("fizz", &[]).is_err());
+        // The payloads of the other types can be of any size.
+        assert!(checked.check_output("text", &[0; 4]).is_ok());
+        assert!(checked.check_output("text", &[]).is_ok());
+    }
+}
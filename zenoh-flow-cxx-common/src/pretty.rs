@@ -0,0 +1,101 @@
+//! Human readable payloads, for logs and sinks.
+
+use crate::codec::decode;
+use std::fmt;
+
+/// Formats a payload according to the type of its port:
+///
+/// - the primitive types of the descriptors are decoded with `crate::codec`,
+/// - `json` and `cbor` payloads are printed as indented JSON,
+/// - anything else, or payloads that fail to decode, as their bytes.
+pub fn pretty(port_type: Option<&str>, bytes: &[u8]) -> String {
+    port_type
+        .and_then(|port_type| decoded(port_type, bytes))
+        .unwrap_or_else(|| {
+            bytes
+                .iter()
+                .map(|byte| byte.to_string())
+                .collect::<Vec<String>>()
+                .join(" ")
+        })
+}
+
+fn decoded(port_type: &str, bytes: &[u8]) -> Option<String> {
+    match port_type {
+        "json" => serde_json::from_slice::<serde_json::Value>(bytes)
+            .ok()
+            .and_then(|value| serde_json::to_string_pretty(&value).ok()),
+        "cbor" => ciborium::de::from_reader::<serde_json::Value, _>(bytes)
+            .ok()
+            .and_then(|value| serde_json::to_string_pretty(&value).ok()),
+        "bool" => decode::<bool>(bytes).ok().map(|v| v.to_string()),
+        "u8" | "unsigned char" => decode::<u8>(bytes).ok().map(|v| v.to_string()),
+        "u16" => decode::<u16>(bytes).ok().map(|v| v.to_string()),
+        "u32" => decode::<u32>(bytes).ok().map(|v| v.to_string()),
+        "u64" => decode::<u64>(bytes).ok().map(|v| v.to_string()),
+        "i8" | "char" => decode::<i8>(bytes).ok().map(|v| v.to_string()),
+        "i16" => decode::<i16>(bytes).ok().map(|v| v.to_string()),
+        "i32" => decode::<i32>(bytes).ok().map(|v| v.to_string()),
+        "i64" => decode::<i64>(bytes).ok().map(|v| v.to_string()),
+        "f32" | "float" => decode::<f32>(bytes).ok().map(|v| v.to_string()),
+        "f64" | "double" => decode::<f64>(bytes).ok().map(|v| v.to_string()),
+        "string" => decode::<String>(bytes).ok(),
+        _ => None,
+    }
+}
+
+/// `Display` adapter of `pretty`, to format payloads lazily in log statements.
+pub struct Pretty<'a> {
+    port_type: Option<&'a str>,
+    bytes: &'a [u8],
+}
+
+impl<'a> Pretty<'a> {
+    pub fn new(port_type: Option<&'a str>, bytes: &'a [u8]) -> Self {
+        Self { port_type, bytes }
+    }
+}
+
+impl fmt::Display for Pretty<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&pretty(self.port_type, self.bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::encode;
+
+    #[test]
+    fn primitives_are_decoded() {
+        assert_eq!(pretty(Some("u64"), &encode(&42_u64).unwrap()), "42");
+        assert_eq!(pretty(Some("i16"), &encode(&-3_i16).unwrap()), "-3");
+        assert_eq!(pretty(Some("double"), &encode(&1.5_f64).unwrap()), "1.5");
+        assert_eq!(pretty(Some("bool"), &encode(&true).unwrap()), "true");
+        assert_eq!(
+            pretty(Some("string"), &encode(&"fizz".to_string()).unwrap()),
+            "fizz"
+        );
+    }
+
+    #[test]
+    fn json_and_cbor_are_indented() {
+        let value = serde_json::json!({ "speed": 1.5 });
+        let expected = "{\n  \"speed\": 1.5\n}";
+        assert_eq!(pretty(Some("json"), br#"{"speed":1.5}"#), expected);
+
+        let mut cbor = Vec::new();
+        ciborium::ser::into_writer(&value, &mut cbor).unwrap();
+        assert_eq!(pretty(Some("cbor"), &cbor), expected);
+    }
+
+    #[test]
+    fn other_payloads_are_bytes() {
+        assert_eq!(pretty(None, &[1, 2, 255]), "1 2 255");
+        assert_eq!(pretty(Some("flatbuffers:sensors.Imu"), &[1, 2]), "1 2");
+        // Too short for a u64.
+        assert_eq!(pretty(Some("u64"), &[1, 2]), "1 2");
+        assert_eq!(Pretty::new(Some("u8"), &[7]).to_string(), "7");
+    }
+}
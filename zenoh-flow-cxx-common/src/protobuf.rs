@@ -0,0 +1,90 @@
+//! Protocol Buffers payloads, enabled with the `protobuf` feature.
+//!
+//! A port carries protobuf messages when its type, in the descriptor, is `protobuf:<full name>`
+//! (e.g. `protobuf:sensors.Imu`). The payloads are the protobuf wire encoding of the messages, as
+//! produced by `zenoh::flow::protobuf::encode` on the C++ side and by `prost` on the Rust side.
+
+use prost::Message;
+use std::fmt::Debug;
+use zenoh_flow::{Data, DowncastAny, ZFError, ZFResult};
+
+/// Prefix of the type of the ports carrying protobuf messages.
+pub const TYPE_PREFIX: &str = "protobuf:";
+
+/// Returns the full name of the protobuf message of a port type, `None` if the port does not
+/// carry protobuf messages.
+pub fn message_type(port_type: &str) -> Option<&str> {
+    port_type.strip_prefix(TYPE_PREFIX)
+}
+
+pub fn encode<M: Message>(message: &M) -> Vec<u8> {
+    message.encode_to_vec()
+}
+
+pub fn decode<M: Message + Default>(bytes: &[u8]) -> ZFResult<M> {
+    M::decode(bytes).map_err(|_| ZFError::DeseralizationError)
+}
+
+/// A `prost` message exchanged with C++ components.
+///
+/// Rust components can return it from their `run` so that C++ components downstream can parse it
+/// with `zenoh::flow::protobuf::decode`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Protobuf<M>(pub M);
+
+impl<M> DowncastAny for Protobuf<M>
+where
+    M: Message + Send + Sync + 'static,
+{
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_mut_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl<M> Data for Protobuf<M>
+where
+    M: Message + Debug + Send + Sync + 'static,
+{
+    fn try_serialize(&self) -> ZFResult<Vec<u8>> {
+        Ok(encode(&self.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct Imu {
+        #[prost(double, tag = "1")]
+        x: f64,
+        #[prost(string, tag = "2")]
+        frame: String,
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        let imu = Imu {
+            x: -9.81,
+            frame: "base_link".to_string(),
+        };
+        let bytes = Protobuf(imu.clone()).try_serialize().unwrap();
+        assert_eq!(bytes, encode(&imu));
+        assert_eq!(decode::<Imu>(&bytes).unwrap(), imu);
+    }
+
+    #[test]
+    fn invalid_messages_are_refused() {
+        assert!(decode::<Imu>(&[0x09, 0x00]).is_err());
+    }
+
+    #[test]
+    fn port_types_name_the_messages() {
+        assert_eq!(message_type("protobuf:sensors.Imu"), Some("sensors.Imu"));
+        assert_eq!(message_type("flatbuffers:sensors.Imu"), None);
+    }
+}
@@ -0,0 +1,531 @@
+//! Shared-memory transport of large payloads between C++ components of the same host.
+//!
+//! With `shm_threshold` set in the configuration of a component, the payloads of its outputs
+//! carrying envelopes of at least that many bytes are placed in a POSIX shared-memory segment
+//! when they leave the runtime: only the name of the segment travels, after `SHM_MAGIC`, with the
+//! envelope of the payload (see `crate::envelope`). The wrapper of the receiving C++ component maps
+//! the segment and the component reads the payload in place, see `payload(input)` in
+//! `zenoh_flow.hpp`:
+//!
+//! ```yaml
+//! configuration:
+//!   shm_threshold: "1048576"
+//!   shm_segments: "16"   # segments kept by the producer, 16 by default
+//! ```
+//!
+//! The producer owns the segments: a message is placed in a segment once, however many receivers
+//! it has, and the producer keeps the last `shm_segments` segments, unlinking the older ones and
+//! all of them when it is dropped. A receiver never unlinks a segment, its mapping stays valid
+//! until it drops the input even once unlinked; a message must be received before
+//! `shm_segments` newer messages are sent. The segments are named
+//! `/zenoh-flow-cxx-<pid>-<n>`: those of processes that are gone are removed when a producer
+//! starts.
+
+use crate::{codec::encode, envelope::SHM_MAGIC, pool::Recycle};
+use std::{
+    collections::{HashMap, VecDeque},
+    ffi::CString,
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, Once, OnceLock,
+    },
+};
+use zenoh_flow::{Data, DowncastAny, ZFError, ZFResult};
+
+/// Configuration key enabling the shared-memory transport: size, in bytes, from which a payload
+/// is placed in shared memory.
+pub const SHM_THRESHOLD: &str = "shm_threshold";
+/// Configuration key of the number of segments a producer keeps.
+pub const SHM_SEGMENTS: &str = "shm_segments";
+pub const DEFAULT_SHM_SEGMENTS: usize = 16;
+/// Prefix of the names of the segments, followed by `<pid>-<n>`.
+pub const SEGMENT_PREFIX: &str = "/zenoh-flow-cxx-";
+
+fn parse(configuration: &HashMap<String, String>, key: &str) -> ZFResult<Option<usize>> {
+    match configuration.get(key) {
+        Some(value) => value
+            .parse::<usize>()
+            .map(Some)
+            .map_err(|_| ZFError::ParsingError(format!("Invalid `{}`: {}", key, value))),
+        None => Ok(None),
+    }
+}
+
+/// A message whose payload can be placed in shared memory.
+pub trait Placeable {
+    fn payload(&self) -> &[u8];
+
+    /// The envelope sent with the name of the segment, without the payload.
+    fn header(&self) -> ZFResult<Vec<u8>>;
+}
+
+/// The shared-memory transport of a producer, and the segments it owns.
+pub struct SharedMemory {
+    threshold: usize,
+    capacity: usize,
+    segments: Mutex<VecDeque<Owned>>,
+}
+
+impl SharedMemory {
+    /// Returns the transport if `shm_threshold` is set in `configuration`.
+    pub fn from_configuration(
+        configuration: Option<&HashMap<String, String>>,
+    ) -> ZFResult<Option<Arc<Self>>> {
+        let configuration = match configuration {
+            Some(configuration) => configuration,
+            None => return Ok(None),
+        };
+        let threshold = match parse(configuration, SHM_THRESHOLD)? {
+            Some(threshold) => threshold.max(1),
+            None => return Ok(None),
+        };
+        let capacity = parse(configuration, SHM_SEGMENTS)?
+            .unwrap_or(DEFAULT_SHM_SEGMENTS)
+            .max(1);
+
+        static SWEEP: Once = Once::new();
+        SWEEP.call_once(sweep);
+
+        Ok(Some(Arc::new(Self::new(threshold, capacity))))
+    }
+
+    pub fn new(threshold: usize, capacity: usize) -> Self {
+        Self {
+            threshold,
+            capacity,
+            segments: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Whether a payload of `len` bytes is placed in shared memory.
+    pub fn accepts(&self, len: usize) -> bool {
+        len >= self.threshold
+    }
+
+    /// Places the payload of `data` in a new segment and returns the bytes to transmit. The
+    /// oldest segment is unlinked if there are more than `shm_segments`.
+    pub fn seal<P: Placeable + ?Sized>(&self, data: &P) -> ZFResult<Vec<u8>> {
+        let header = data.header()?;
+        let payload = data.payload();
+        let segment = Owned::create(payload)?;
+        let fields = encode(&(&segment.name, payload.len() as u64, header))?;
+
+        let mut segments = self
+            .segments
+            .lock()
+            .map_err(|_| ZFError::IOError("Poisoned segments".to_string()))?;
+        segments.push_back(segment);
+        while segments.len() > self.capacity {
+            segments.pop_front();
+        }
+
+        let mut bytes = Vec::with_capacity(SHM_MAGIC.len() + fields.len());
+        bytes.extend_from_slice(&SHM_MAGIC);
+        bytes.extend_from_slice(&fields);
+        Ok(bytes)
+    }
+}
+
+impl fmt::Debug for SharedMemory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedMemory")
+            .field("threshold", &self.threshold)
+            .field("capacity", &self.capacity)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A message whose payload is placed in shared memory when it is first serialized: all its
+/// receivers get the same segment.
+pub struct Shared<T> {
+    data: T,
+    shm: Arc<SharedMemory>,
+    /// The error is formatted, `ZFError` cannot be cloned.
+    sealed: OnceLock<Result<Vec<u8>, String>>,
+}
+
+impl<T> Shared<T> {
+    pub fn new(data: T, shm: Arc<SharedMemory>) -> Self {
+        Self {
+            data,
+            shm,
+            sealed: OnceLock::new(),
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Shared<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.data.fmt(f)
+    }
+}
+
+impl<T: DowncastAny> DowncastAny for Shared<T> {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self.data.as_any()
+    }
+
+    fn as_mut_any(&mut self) -> &mut dyn std::any::Any {
+        self.data.as_mut_any()
+    }
+}
+
+impl<T: Recycle> Recycle for Shared<T> {
+    fn take_buffer(&mut self) -> Vec<u8> {
+        self.data.take_buffer()
+    }
+}
+
+impl<T: Placeable + Data + 'static> Data for Shared<T> {
+    fn try_serialize(&self) -> ZFResult<Vec<u8>> {
+        self.sealed
+            .get_or_init(|| self.shm.seal(&self.data).map_err(|e| format!("{:?}", e)))
+            .clone()
+            .map_err(ZFError::IOError)
+    }
+}
+
+static SEGMENTS: AtomicU64 = AtomicU64::new(0);
+
+fn c_name(name: &str) -> ZFResult<CString> {
+    CString::new(name).map_err(|_| ZFError::InvalidData(format!("Invalid segment name: {}", name)))
+}
+
+fn last_error(name: &str) -> ZFError {
+    ZFError::IOError(format!("{}: {}", name, std::io::Error::last_os_error()))
+}
+
+/// Checks that `name` is the name of a segment, `/zenoh-flow-cxx-<pid>-<n>`, before opening it.
+fn check_name(name: &str) -> ZFResult<()> {
+    let valid = name
+        .strip_prefix(SEGMENT_PREFIX)
+        .and_then(|suffix| suffix.split_once('-'))
+        .is_some_and(|(pid, n)| {
+            [pid, n]
+                .iter()
+                .all(|number| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()))
+        });
+    if valid {
+        Ok(())
+    } else {
+        Err(ZFError::InvalidData(format!(
+            "Invalid segment name: {}",
+            name
+        )))
+    }
+}
+
+/// Removes the segments left by the processes that are gone.
+#[cfg(target_os = "linux")]
+fn sweep() {
+    let entries = match std::fs::read_dir("/dev/shm") {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let name = format!("/{}", entry.file_name().to_string_lossy());
+        if check_name(&name).is_err() {
+            continue;
+        }
+        let pid = name[SEGMENT_PREFIX.len()..]
+            .split('-')
+            .next()
+            .and_then(|pid| pid.parse::<libc::pid_t>().ok());
+        let gone = pid.is_some_and(|pid| unsafe {
+            libc::kill(pid, 0) != 0
+                && std::io::Error::last_os_error().raw_os_error() == Some(libc::ESRCH)
+        });
+        if gone {
+            if let Ok(c_name) = c_name(&name) {
+                unsafe {
+                    libc::shm_unlink(c_name.as_ptr());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn sweep() {}
+
+/// A segment created by this process, unlinked when dropped.
+struct Owned {
+    name: String,
+}
+
+impl Owned {
+    /// Places `bytes` in a new segment.
+    fn create(bytes: &[u8]) -> ZFResult<Self> {
+        let name = format!(
+            "{}{}-{}",
+            SEGMENT_PREFIX,
+            std::process::id(),
+            SEGMENTS.fetch_add(1, Ordering::Relaxed)
+        );
+        let c_name = c_name(&name)?;
+
+        unsafe {
+            let fd = libc::shm_open(
+                c_name.as_ptr(),
+                libc::O_CREAT | libc::O_EXCL | libc::O_RDWR,
+                0o600,
+            );
+            if fd < 0 {
+                return Err(last_error(&name));
+            }
+            // Unlinked on error from here.
+            let owned = Self { name };
+
+            let written = (|| {
+                if libc::ftruncate(fd, bytes.len() as libc::off_t) != 0 {
+                    return Err(last_error(&owned.name));
+                }
+                if bytes.is_empty() {
+                    return Ok(());
+                }
+                let address = libc::mmap(
+                    std::ptr::null_mut(),
+                    bytes.len(),
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_SHARED,
+                    fd,
+                    0,
+                );
+                if address == libc::MAP_FAILED {
+                    return Err(last_error(&owned.name));
+                }
+                std::ptr::copy_nonoverlapping(bytes.as_ptr(), address as *mut u8, bytes.len());
+                libc::munmap(address, bytes.len());
+                Ok(())
+            })();
+
+            libc::close(fd);
+            written.map(|_| owned)
+        }
+    }
+}
+
+impl Drop for Owned {
+    fn drop(&mut self) {
+        if let Ok(c_name) = c_name(&self.name) {
+            unsafe {
+                libc::shm_unlink(c_name.as_ptr());
+            }
+        }
+    }
+}
+
+/// A shared-memory segment, mapped read-only until dropped.
+pub struct Segment {
+    name: String,
+    address: *mut libc::c_void,
+    len: usize,
+}
+
+// The mapping is read-only, and only unmapped when the segment is dropped.
+unsafe impl Send for Segment {}
+unsafe impl Sync for Segment {}
+
+impl Segment {
+    /// Maps the segment `name`, of `len` bytes. The segment belongs to its producer: it is not
+    /// unlinked.
+    pub fn open(name: &str, len: usize) -> ZFResult<Self> {
+        check_name(name)?;
+        let c_name = c_name(name)?;
+
+        unsafe {
+            let fd = libc::shm_open(c_name.as_ptr(), libc::O_RDONLY, 0);
+            if fd < 0 {
+                return Err(last_error(name));
+            }
+
+            let mapped = (|| {
+                let mut stat: libc::stat = std::mem::zeroed();
+                if libc::fstat(fd, &mut stat) != 0 {
+                    return Err(last_error(name));
+                }
+                if stat.st_size as u64 != len as u64 {
+                    return Err(ZFError::InvalidData(format!(
+                        "{}: {} bytes announced, the segment has {}",
+                        name, len, stat.st_size
+                    )));
+                }
+
+                let address = if len == 0 {
+                    std::ptr::null_mut()
+                } else {
+                    libc::mmap(
+                        std::ptr::null_mut(),
+                        len,
+                        libc::PROT_READ,
+                        libc::MAP_SHARED,
+                        fd,
+                        0,
+                    )
+                };
+                if address == libc::MAP_FAILED {
+                    return Err(last_error(name));
+                }
+                Ok(Self {
+                    name: name.to_string(),
+                    address,
+                    len,
+                })
+            })();
+            libc::close(fd);
+            mapped
+        }
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        if self.len == 0 {
+            return &[];
+        }
+        unsafe { std::slice::from_raw_parts(self.address as *const u8, self.len) }
+    }
+}
+
+impl Drop for Segment {
+    fn drop(&mut self) {
+        if self.len > 0 {
+            unsafe {
+                libc::munmap(self.address, self.len);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Segment")
+            .field("name", &self.name)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+impl PartialEq for Segment {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        compression::{Compression, DEFAULT_MAX_PAYLOAD},
+        envelope::{self, Envelope},
+    };
+
+    #[derive(Debug)]
+    struct Frame(Vec<u8>);
+
+    impl Placeable for Frame {
+        fn payload(&self) -> &[u8] {
+            &self.0
+        }
+
+        fn header(&self) -> ZFResult<Vec<u8>> {
+            envelope::seal("", Compression::None, &[], &[])
+        }
+    }
+
+    impl DowncastAny for Frame {
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+
+        fn as_mut_any(&mut self) -> &mut dyn std::any::Any {
+            self
+        }
+    }
+
+    impl Data for Frame {
+        fn try_serialize(&self) -> ZFResult<Vec<u8>> {
+            Ok(self.0.clone())
+        }
+    }
+
+    fn segment_name(bytes: &[u8]) -> String {
+        let (name, _, _) =
+            crate::codec::decode::<(String, u64, Vec<u8>)>(&bytes[SHM_MAGIC.len()..]).unwrap();
+        name
+    }
+
+    #[test]
+    fn names_outside_the_prefix_are_refused() {
+        for name in [
+            "/dev-shm-secret",
+            "/zenoh-flow-cxx-",
+            "/zenoh-flow-cxx-1",
+            "/zenoh-flow-cxx-1-",
+            "/zenoh-flow-cxx-../1-2",
+            "/zenoh-flow-cxx-1-2/3",
+        ] {
+            assert!(Segment::open(name, 0).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn announced_size_must_match() {
+        let shm = SharedMemory::new(1, 4);
+        let bytes = shm.seal(&Frame(vec![1, 2, 3, 4])).unwrap();
+        let name = segment_name(&bytes);
+
+        assert!(Segment::open(&name, 8).is_err());
+        assert!(Segment::open(&name, 2).is_err());
+        assert_eq!(Segment::open(&name, 4).unwrap().as_slice(), &[1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn every_receiver_opens_the_segment() {
+        let shm = Arc::new(SharedMemory::new(1, 4));
+        let shared = Shared::new(Frame(vec![7; 16]), shm);
+
+        // Serialized once, whatever the number of receivers.
+        let first = shared.try_serialize().unwrap();
+        let second = shared.try_serialize().unwrap();
+        assert_eq!(first, second);
+
+        let a = Envelope::open(first, DEFAULT_MAX_PAYLOAD).unwrap();
+        let b = Envelope::open(second, DEFAULT_MAX_PAYLOAD).unwrap();
+        assert_eq!(a.shared.unwrap().as_slice(), &[7; 16]);
+        assert_eq!(b.shared.unwrap().as_slice(), &[7; 16]);
+    }
+
+    #[test]
+    fn producer_unlinks_its_segments() {
+        let shm = SharedMemory::new(1, 1);
+        let old = segment_name(&shm.seal(&Frame(vec![1])).unwrap());
+        let mapped = Segment::open(&old, 1).unwrap();
+
+        // The oldest segment goes beyond `shm_segments`, mapped ones stay readable.
+        let new = segment_name(&shm.seal(&Frame(vec![2])).unwrap());
+        assert!(Segment::open(&old, 1).is_err());
+        assert_eq!(mapped.as_slice(), &[1]);
+        assert!(Segment::open(&new, 1).is_ok());
+
+        drop(shm);
+        assert!(Segment::open(&new, 1).is_err());
+    }
+
+    #[test]
+    fn configuration_is_parsed() {
+        let mut configuration = HashMap::new();
+        assert!(SharedMemory::from_configuration(Some(&configuration))
+            .unwrap()
+            .is_none());
+
+        configuration.insert(SHM_THRESHOLD.to_string(), "1024".to_string());
+        let shm = SharedMemory::from_configuration(Some(&configuration))
+            .unwrap()
+            .unwrap();
+        assert!(shm.accepts(1024) && !shm.accepts(1023));
+        assert_eq!(shm.capacity, DEFAULT_SHM_SEGMENTS);
+
+        configuration.insert(SHM_SEGMENTS.to_string(), "many".to_string());
+        assert!(SharedMemory::from_configuration(Some(&configuration)).is_err());
+    }
+}
@@ -0,0 +1,249 @@
+//! Threading models of the C++ components.
+//!
+//! The runtime calls a component from whichever thread of its executor is free. A C++ component
+//! declares, with `threading(configuration)`, what its state tolerates, and the wrapper enforces
+//! it:
+//!
+//! - `Affine`: the state is created, used and destroyed on a dedicated thread, the calls are sent
+//!   to it (thread-local storage, GPU contexts, interpreters, ...),
+//! - `Send`: the state can move between threads, but is only used by one thread at a time,
+//! - `Concurrent`: the state can be used from any thread, at any time: the wrapper calls it
+//!   in place, without the thread of `Affine` nor the mutex of `Send`.
+//!
+//! The runtime currently serializes the calls to a component, so a `Concurrent` state is not used
+//! from several threads at once yet: the model states what the component tolerates, and costs
+//! nothing.
+
+use std::{
+    sync::{mpsc, Mutex},
+    thread::JoinHandle,
+};
+use zenoh_flow::{ZFError, ZFResult};
+
+/// The threading model declared by a component.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Model {
+    Affine,
+    Send,
+    Concurrent,
+}
+
+/// A value the component declared `Send`: it is only used behind a mutex.
+struct Movable<T>(T);
+
+// Only built for the `Send` model, on the word of the component.
+unsafe impl<T> Send for Movable<T> {}
+
+/// A value the component declared usable from any thread.
+struct Unrestricted<T>(T);
+
+// Only built for the `Concurrent` model, on the word of the component.
+unsafe impl<T> Send for Unrestricted<T> {}
+unsafe impl<T> Sync for Unrestricted<T> {}
+
+type Job<T> = Box<dyn FnOnce(&mut T) + Send>;
+
+/// A value living on its own thread.
+struct Pinned<T> {
+    jobs: Option<mpsc::Sender<Job<T>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl<T: 'static> Pinned<T> {
+    fn new<F>(name: &str, init: F) -> ZFResult<Self>
+    where
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let (jobs, receiver) = mpsc::channel::<Job<T>>();
+        let thread = std::thread::Builder::new()
+            .name(name.to_string())
+            .spawn(move || {
+                let mut value = init();
+                for job in receiver {
+                    job(&mut value);
+                }
+            })
+            .map_err(|e| ZFError::IOError(format!("{}: {}", name, e)))?;
+
+        Ok(Self {
+            jobs: Some(jobs),
+            thread: Some(thread),
+        })
+    }
+
+    fn with<R, F>(&self, f: F) -> ZFResult<R>
+    where
+        F: FnOnce(&mut T) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (sender, receiver) = mpsc::sync_channel(1);
+        let job: Job<T> = Box::new(move |value| {
+            let _ = sender.send(f(value));
+        });
+        let stopped = || ZFError::IOError("The thread of the component stopped".to_string());
+        self.jobs
+            .as_ref()
+            .ok_or_else(stopped)?
+            .send(job)
+            .map_err(|_| stopped())?;
+        receiver.recv().map_err(|_| stopped())
+    }
+}
+
+impl<T> Drop for Pinned<T> {
+    fn drop(&mut self) {
+        // Closing the queue ends the thread, that destroys the value.
+        self.jobs.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+enum Inner<T> {
+    Pinned(Pinned<T>),
+    Locked(Mutex<Movable<T>>),
+    Free(Unrestricted<T>),
+}
+
+/// The state of a component, used according to its threading model.
+pub struct Confined<T: 'static>(Inner<T>);
+
+impl<T: 'static> Confined<T> {
+    /// Builds the value with `init`, on a thread named `name` for the `Affine` model.
+    pub fn new<F>(model: Model, name: &str, init: F) -> ZFResult<Self>
+    where
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let inner = match model {
+            Model::Affine => Inner::Pinned(Pinned::new(name, init)?),
+            Model::Send => Inner::Locked(Mutex::new(Movable(init()))),
+            Model::Concurrent => Inner::Free(Unrestricted(init())),
+        };
+        Ok(Self(inner))
+    }
+
+    /// Calls `f` with the value, on its thread for the `Affine` model.
+    pub fn with<R, F>(&mut self, f: F) -> ZFResult<R>
+    where
+        F: FnOnce(&mut T) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        match &mut self.0 {
+            Inner::Pinned(pinned) => pinned.with(f),
+            Inner::Locked(locked) => match locked.get_mut() {
+                Ok(value) => Ok(f(&mut value.0)),
+                Err(_) => Err(ZFError::GenericError),
+            },
+            Inner::Free(value) => Ok(f(&mut value.0)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread::{self, ThreadId};
+
+    /// Records the threads that create, use and destroy it.
+    struct Probe {
+        created: ThreadId,
+        dropped: mpsc::Sender<ThreadId>,
+    }
+
+    impl Probe {
+        fn new(dropped: mpsc::Sender<ThreadId>) -> Self {
+            Self {
+                created: thread::current().id(),
+                dropped,
+            }
+        }
+    }
+
+    impl Drop for Probe {
+        fn drop(&mut self) {
+            let _ = self.dropped.send(thread::current().id());
+        }
+    }
+
+    #[test]
+    fn affine_state_lives_on_its_thread() {
+        let (sender, dropped) = mpsc::channel();
+        let mut state = Confined::new(Model::Affine, "affine", move || Probe::new(sender)).unwrap();
+
+        let (created, used, name) = state
+            .with(|probe| {
+                let current = thread::current();
+                (
+                    probe.created,
+                    current.id(),
+                    current.name().map(String::from),
+                )
+            })
+            .unwrap();
+        assert_eq!(created, used);
+        assert_ne!(created, thread::current().id());
+        assert_eq!(name.as_deref(), Some("affine"));
+
+        // Moved to another thread, the state is still used on its own.
+        let used_elsewhere = thread::spawn(move || {
+            let used = state.with(|_| thread::current().id()).unwrap();
+            drop(state);
+            used
+        })
+        .join()
+        .unwrap();
+        assert_eq!(used_elsewhere, created);
+        assert_eq!(dropped.recv().unwrap(), created);
+    }
+
+    #[test]
+    fn send_state_moves_with_its_owner() {
+        let (sender, dropped) = mpsc::channel();
+        let mut state = Confined::new(Model::Send, "send", move || Probe::new(sender)).unwrap();
+        assert_eq!(
+            state.with(|probe| probe.created).unwrap(),
+            thread::current().id()
+        );
+
+        let owner = thread::spawn(move || {
+            let used = state.with(|_| thread::current().id()).unwrap();
+            drop(state);
+            (thread::current().id(), used)
+        })
+        .join()
+        .unwrap();
+        assert_eq!(owner.0, owner.1);
+        assert_eq!(dropped.recv().unwrap(), owner.0);
+    }
+
+    #[test]
+    fn concurrent_state_is_used_in_place() {
+        let (sender, dropped) = mpsc::channel();
+        let mut state =
+            Confined::new(Model::Concurrent, "concurrent", move || Probe::new(sender)).unwrap();
+        assert_eq!(
+            state.with(|probe| probe.created).unwrap(),
+            thread::current().id()
+        );
+
+        let caller = thread::spawn(move || {
+            let used = state.with(|_| thread::current().id()).unwrap();
+            drop(state);
+            (thread::current().id(), used)
+        })
+        .join()
+        .unwrap();
+        assert_eq!(caller.0, caller.1);
+        assert_eq!(dropped.recv().unwrap(), caller.0);
+    }
+
+    #[test]
+    fn affine_state_keeps_its_value_between_calls() {
+        let mut state = Confined::new(Model::Affine, "counter", || 0u32).unwrap();
+        for _ in 0..3 {
+            state.with(|counter| *counter += 1).unwrap();
+        }
+        assert_eq!(state.with(|counter| *counter).unwrap(), 3);
+    }
+}
@@ -0,0 +1,614 @@
+//! Distributed tracing of the C++ components.
+//!
+//! When `trace` is set in the configuration of a component, its wrapper records a span around
+//! every call to the `run` function of the C++ component and exports it, with the spans the C++
+//! component added below it, in the OTLP/JSON format:
+//!
+//! ```yaml
+//! configuration:
+//!   trace: /tmp/spans.jsonl        # one `ExportTraceServiceRequest` per line
+//!   # trace: http://localhost:4318 # or POSTed to an OTLP/HTTP collector
+//! ```
+//!
+//! Requests to a collector are sent by a background thread, in batches, so that a slow or
+//! unreachable collector does not hold the component: when its queue is full, the spans are
+//! dropped. The failures of the thread are reported by the next calls to `Tracer::export`.
+//!
+//! The span context is propagated, as a W3C `traceparent`, in the metadata of the messages (see
+//! `crate::metadata`): the span of a component receiving a message is a child of the span of the
+//! component that produced it.
+
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::{File, OpenOptions},
+    io::{Read, Write},
+    mem,
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, SyncSender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use zenoh_flow::{ZFError, ZFResult};
+
+/// Configuration key enabling tracing: path of a file, or URL of an OTLP/HTTP collector.
+pub const TRACE: &str = "trace";
+/// Configuration key overriding the `service.name` of the exported spans.
+pub const TRACE_SERVICE: &str = "trace_service";
+/// Metadata key of the span context, as defined by W3C Trace Context.
+pub const TRACEPARENT: &str = "traceparent";
+
+/// Requests waiting for the collector, beyond which the spans are dropped.
+const QUEUE: usize = 1024;
+/// Requests sent to the collector at once.
+const BATCH: usize = 64;
+/// Delay after which a batch is sent, full or not.
+const BATCH_DELAY: Duration = Duration::from_secs(1);
+/// Timeout of the connection to the collector, and of each read and write.
+const COLLECTOR_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Current time, in nanoseconds since the UNIX epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn unhex(s: &str, bytes: &mut [u8]) -> Option<()> {
+    if s.len() != 2 * bytes.len() || s.bytes().any(|c| c.is_ascii_uppercase()) {
+        return None;
+    }
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(s.get(2 * i..2 * i + 2)?, 16).ok()?;
+    }
+    Some(())
+}
+
+/// A span context: `00-<trace id>-<span id>-<flags>` once formatted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceContext {
+    pub trace_id: [u8; 16],
+    pub span_id: [u8; 8],
+    pub flags: u8,
+}
+
+impl TraceContext {
+    /// The context of a new, sampled, trace.
+    pub fn root() -> Self {
+        Self {
+            trace_id: rand::random(),
+            span_id: rand::random(),
+            flags: 1,
+        }
+    }
+
+    /// The context of a new span of the same trace.
+    pub fn child(&self) -> Self {
+        Self {
+            span_id: rand::random(),
+            ..*self
+        }
+    }
+}
+
+impl FromStr for TraceContext {
+    type Err = ZFError;
+
+    fn from_str(s: &str) -> ZFResult<Self> {
+        let invalid = || ZFError::ParsingError(format!("Invalid traceparent: {}", s));
+        let fields: Vec<&str> = s.split('-').collect();
+        if fields.len() != 4 || fields[0] != "00" {
+            return Err(invalid());
+        }
+
+        let mut context = Self {
+            trace_id: [0; 16],
+            span_id: [0; 8],
+            flags: 0,
+        };
+        let mut flags = [0; 1];
+        unhex(fields[1], &mut context.trace_id).ok_or_else(invalid)?;
+        unhex(fields[2], &mut context.span_id).ok_or_else(invalid)?;
+        unhex(fields[3], &mut flags).ok_or_else(invalid)?;
+        context.flags = flags[0];
+
+        if context.trace_id == [0; 16] || context.span_id == [0; 8] {
+            return Err(invalid());
+        }
+        Ok(context)
+    }
+}
+
+impl fmt::Display for TraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "00-{}-{}-{:02x}",
+            hex(&self.trace_id),
+            hex(&self.span_id),
+            self.flags
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Span {
+    pub name: String,
+    pub context: TraceContext,
+    pub parent_span_id: Option<[u8; 8]>,
+    /// Nanoseconds since the UNIX epoch.
+    pub start: u64,
+    pub end: u64,
+    pub attributes: Vec<(String, String)>,
+}
+
+impl Span {
+    /// Starts a span, child of `parent` or root of a new trace.
+    pub fn start(name: &str, parent: Option<TraceContext>) -> Self {
+        Self {
+            name: name.to_string(),
+            context: parent
+                .map(|parent| parent.child())
+                .unwrap_or_else(TraceContext::root),
+            parent_span_id: parent.map(|parent| parent.span_id),
+            start: now(),
+            end: 0,
+            attributes: Vec::new(),
+        }
+    }
+
+    /// A span, child of this one, that already ended.
+    pub fn child(&self, name: &str, start: u64, end: u64) -> Self {
+        Self {
+            name: name.to_string(),
+            context: self.context.child(),
+            parent_span_id: Some(self.context.span_id),
+            start,
+            end,
+            attributes: Vec::new(),
+        }
+    }
+
+    pub fn end(&mut self) {
+        self.end = now();
+    }
+
+    fn to_otlp(&self) -> Value {
+        let mut span = json!({
+            "traceId": hex(&self.context.trace_id),
+            "spanId": hex(&self.context.span_id),
+            "name": self.name,
+            "kind": 1,
+            "startTimeUnixNano": self.start.to_string(),
+            "endTimeUnixNano": self.end.to_string(),
+            "attributes": attributes(&self.attributes),
+        });
+        if let Some(parent_span_id) = self.parent_span_id {
+            span["parentSpanId"] = Value::from(hex(&parent_span_id));
+        }
+        span
+    }
+}
+
+fn attributes(attributes: &[(String, String)]) -> Value {
+    attributes
+        .iter()
+        .map(|(key, value)| json!({ "key": key, "value": { "stringValue": value } }))
+        .collect()
+}
+
+/// An OTLP/HTTP collector.
+#[derive(Debug)]
+struct Collector {
+    address: String,
+    host: String,
+    path: String,
+    timeout: Duration,
+}
+
+impl Collector {
+    fn new(url: &str, timeout: Duration) -> Self {
+        let (host, path) = match url.find('/') {
+            Some(index) => (&url[..index], &url[index..]),
+            None => (url, "/v1/traces"),
+        };
+        let address = if host.contains(':') {
+            host.to_string()
+        } else {
+            format!("{}:80", host)
+        };
+        Self {
+            address,
+            host: host.to_string(),
+            path: path.to_string(),
+            timeout,
+        }
+    }
+
+    fn connect(&self) -> std::io::Result<TcpStream> {
+        let addresses: Vec<SocketAddr> = self.address.to_socket_addrs()?.collect();
+        let mut error = None;
+        for address in addresses {
+            match TcpStream::connect_timeout(&address, self.timeout) {
+                Ok(stream) => {
+                    stream.set_read_timeout(Some(self.timeout))?;
+                    stream.set_write_timeout(Some(self.timeout))?;
+                    return Ok(stream);
+                }
+                Err(e) => error = Some(e),
+            }
+        }
+        Err(error
+            .unwrap_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "No address")))
+    }
+
+    /// Sends `requests` as one `ExportTraceServiceRequest`.
+    fn post(&self, requests: Vec<Value>) -> Result<(), String> {
+        let resource_spans: Vec<Value> = requests
+            .into_iter()
+            .flat_map(|mut request| match request["resourceSpans"].take() {
+                Value::Array(resource_spans) => resource_spans,
+                _ => Vec::new(),
+            })
+            .collect();
+        let body = json!({ "resourceSpans": resource_spans }).to_string();
+
+        let io_error = |e: std::io::Error| format!("{}: {}", self.address, e);
+        let mut stream = self.connect().map_err(io_error)?;
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.path,
+            self.host,
+            body.len(),
+            body
+        )
+        .map_err(io_error)?;
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).map_err(io_error)?;
+        let status = response.split_whitespace().nth(1).unwrap_or_default();
+        if !status.starts_with('2') {
+            return Err(format!(
+                "The collector at {} answered: {}",
+                self.address,
+                response.lines().next().unwrap_or_default()
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// What the thread sending the requests to a collector reports.
+#[derive(Debug, Default)]
+struct Failures {
+    /// Requests dropped since the last batch, the queue being full.
+    dropped: AtomicUsize,
+    errors: Mutex<Vec<String>>,
+}
+
+impl Failures {
+    fn push(&self, error: String) {
+        if let Ok(mut errors) = self.errors.lock() {
+            errors.push(error);
+        }
+    }
+
+    fn take(&self) -> Vec<String> {
+        self.errors
+            .lock()
+            .map(|mut errors| mem::take(&mut *errors))
+            .unwrap_or_default()
+    }
+}
+
+/// Sends the requests of `queue` to `collector`, by batches of up to `BATCH` requests waiting
+/// at most `BATCH_DELAY`, until the queue is closed.
+fn send_batches(collector: Collector, queue: Receiver<Value>, failures: Arc<Failures>) {
+    let mut closed = false;
+    while !closed {
+        let mut batch = match queue.recv() {
+            Ok(request) => vec![request],
+            Err(_) => return,
+        };
+        let deadline = Instant::now() + BATCH_DELAY;
+        while batch.len() < BATCH {
+            match queue.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(request) => batch.push(request),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => {
+                    closed = true;
+                    break;
+                }
+            }
+        }
+
+        if let Err(e) = collector.post(batch) {
+            failures.push(e);
+        }
+        let dropped = failures.dropped.swap(0, Ordering::Relaxed);
+        if dropped > 0 {
+            failures.push(format!(
+                "{} requests dropped, the queue of the collector at {} is full",
+                dropped, collector.address
+            ));
+        }
+    }
+}
+
+enum Exporter {
+    File(File),
+    Http {
+        queue: Option<SyncSender<Value>>,
+        failures: Arc<Failures>,
+        thread: Option<JoinHandle<()>>,
+    },
+}
+
+impl Exporter {
+    fn new(destination: &str) -> ZFResult<Self> {
+        match destination.strip_prefix("http://") {
+            Some(url) => Self::http(Collector::new(url, COLLECTOR_TIMEOUT)),
+            None => OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(destination)
+                .map(Exporter::File)
+                .map_err(|e| ZFError::IOError(format!("{}: {}", destination, e))),
+        }
+    }
+
+    fn http(collector: Collector) -> ZFResult<Self> {
+        let (queue, receiver) = mpsc::sync_channel(QUEUE);
+        let failures = Arc::new(Failures::default());
+        let thread_failures = failures.clone();
+        let thread = thread::Builder::new()
+            .name("zenoh-flow-cxx-trace".to_string())
+            .spawn(move || send_batches(collector, receiver, thread_failures))
+            .map_err(|e| ZFError::IOError(format!("{}", e)))?;
+        Ok(Exporter::Http {
+            queue: Some(queue),
+            failures,
+            thread: Some(thread),
+        })
+    }
+
+    fn export(&mut self, request: Value) -> ZFResult<()> {
+        match self {
+            // One write per request: components of the same process may share the file.
+            Exporter::File(file) => file
+                .write_all(format!("{}\n", request).as_bytes())
+                .map_err(|e| ZFError::IOError(format!("{}", e))),
+            Exporter::Http {
+                queue, failures, ..
+            } => {
+                if let Some(queue) = queue {
+                    if queue.try_send(request).is_err() {
+                        failures.dropped.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+                let errors = failures.take();
+                if errors.is_empty() {
+                    Ok(())
+                } else {
+                    Err(ZFError::IOError(errors.join("; ")))
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Exporter {
+    /// Sends the requests still queued, within the timeouts of the collector.
+    fn drop(&mut self) {
+        if let Exporter::Http { queue, thread, .. } = self {
+            queue.take();
+            if let Some(thread) = thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+/// Exports the spans of a component.
+pub struct Tracer {
+    service: String,
+    instance: String,
+    exporter: Exporter,
+}
+
+impl fmt::Debug for Tracer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tracer")
+            .field("service", &self.service)
+            .field("instance", &self.instance)
+            .finish()
+    }
+}
+
+impl Tracer {
+    /// Returns a tracer if `trace` is set in `configuration`, `service` being the default name of
+    /// the component in the exported spans and `instance` the name of its instance.
+    pub fn from_configuration(
+        configuration: Option<&HashMap<String, String>>,
+        service: &str,
+        instance: &str,
+    ) -> ZFResult<Option<Self>> {
+        let configuration = match configuration {
+            Some(configuration) => configuration,
+            None => return Ok(None),
+        };
+
+        match configuration.get(TRACE) {
+            Some(destination) => Ok(Some(Self {
+                service: configuration
+                    .get(TRACE_SERVICE)
+                    .cloned()
+                    .unwrap_or_else(|| service.to_string()),
+                instance: instance.to_string(),
+                exporter: Exporter::new(destination)?,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    /// Ends `span` and exports it with its `children`, given as `(name, start, end)`.
+    pub fn finish<'a, I>(&mut self, span: &mut Span, children: I) -> ZFResult<()>
+    where
+        I: IntoIterator<Item = (&'a str, u64, u64)>,
+    {
+        span.end();
+        let mut spans = vec![span.clone()];
+        spans.extend(
+            children
+                .into_iter()
+                .map(|(name, start, end)| span.child(name, start, end)),
+        );
+        self.export(&spans)
+    }
+
+    /// Exports `spans` as one `ExportTraceServiceRequest`.
+    pub fn export(&mut self, spans: &[Span]) -> ZFResult<()> {
+        let request = json!({
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": attributes(&[
+                        ("service.name".to_string(), self.service.clone()),
+                        ("service.instance.id".to_string(), self.instance.clone()),
+                    ]),
+                },
+                "scopeSpans": [{
+                    "scope": { "name": "zenoh-flow-cxx" },
+                    "spans": spans.iter().map(Span::to_otlp).collect::<Vec<Value>>(),
+                }],
+            }],
+        });
+        self.exporter.export(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    /// Answers one request and returns its body.
+    fn answer(listener: &TcpListener) -> Value {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = Vec::new();
+        let mut buffer = [0; 4096];
+        let body = loop {
+            let read = stream.read(&mut buffer).unwrap();
+            request.extend_from_slice(&buffer[..read]);
+            let text = String::from_utf8_lossy(&request);
+            if let Some(end) = text.find("\r\n\r\n") {
+                let length: usize = text
+                    .lines()
+                    .find_map(|line| line.strip_prefix("Content-Length: "))
+                    .unwrap()
+                    .parse()
+                    .unwrap();
+                if request.len() >= end + 4 + length {
+                    break request[end + 4..end + 4 + length].to_vec();
+                }
+            }
+        };
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    /// An exporter whose queue is never read: `receiver` holds the queued requests.
+    fn stalled() -> (Exporter, Receiver<Value>) {
+        let (queue, receiver) = mpsc::sync_channel(QUEUE);
+        let exporter = Exporter::Http {
+            queue: Some(queue),
+            failures: Arc::default(),
+            thread: None,
+        };
+        (exporter, receiver)
+    }
+
+    #[test]
+    fn requests_are_batched() {
+        let (exporter, receiver) = stalled();
+        let mut tracer = Tracer {
+            service: "service".to_string(),
+            instance: "instance".to_string(),
+            exporter,
+        };
+        for name in ["a", "b", "c"] {
+            tracer.export(&[Span::start(name, None)]).unwrap();
+        }
+        // Closes the queue: the requests are sent at once.
+        drop(tracer);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let collector = Collector::new(
+            &listener.local_addr().unwrap().to_string(),
+            COLLECTOR_TIMEOUT,
+        );
+        let answered = thread::spawn(move || answer(&listener));
+        let failures = Arc::new(Failures::default());
+        send_batches(collector, receiver, failures.clone());
+
+        let request = answered.join().unwrap();
+        assert_eq!(request["resourceSpans"].as_array().unwrap().len(), 3);
+        assert!(failures.take().is_empty());
+    }
+
+    #[test]
+    fn a_full_queue_drops_the_requests() {
+        let (mut exporter, receiver) = stalled();
+        for _ in 0..QUEUE + 8 {
+            exporter.export(json!({})).unwrap();
+        }
+
+        assert_eq!(receiver.try_iter().count(), QUEUE);
+        match &exporter {
+            Exporter::Http { failures, .. } => {
+                assert_eq!(failures.dropped.load(Ordering::Relaxed), 8)
+            }
+            Exporter::File(_) => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn dropped_requests_are_reported() {
+        // Refuses the connections: the request fails at once.
+        let address = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string();
+        let (queue, receiver) = mpsc::sync_channel(QUEUE);
+        queue.send(json!({})).unwrap();
+        drop(queue);
+        let failures = Arc::new(Failures::default());
+        failures.dropped.store(8, Ordering::Relaxed);
+
+        send_batches(
+            Collector::new(&address, COLLECTOR_TIMEOUT),
+            receiver,
+            failures.clone(),
+        );
+
+        let errors = failures.take();
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert!(errors[1].starts_with("8 requests dropped"), "{:?}", errors);
+        assert_eq!(failures.dropped.load(Ordering::Relaxed), 0);
+    }
+}
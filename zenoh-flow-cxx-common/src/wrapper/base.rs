@@ -0,0 +1,344 @@
+//! The parts of the wrappers shared by several kinds of components. They expand in the module
+//! generated by the `export_cxx_*!` macros, where `ffi` is the bridge of the component.
+
+/// Conversions of the configuration, the metadata, the threading model and the context.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __cxx_wrapper_base {
+    () => {
+        impl From<HashMap<String, String>> for ffi::ConfigurationMap {
+            fn from(configuration: HashMap<String, String>) -> Self {
+                ffi::ConfigurationMap {
+                    map: configuration
+                        .iter()
+                        .map(|(key, value)| ffi::Configuration {
+                            key: key.clone(),
+                            value: value.clone(),
+                        })
+                        .collect(),
+                }
+            }
+        }
+
+        impl From<(String, String)> for ffi::Attribute {
+            fn from((key, value): (String, String)) -> Self {
+                ffi::Attribute { key, value }
+            }
+        }
+
+        fn to_metadata(attributes: &[ffi::Attribute]) -> Metadata {
+            attributes
+                .iter()
+                .map(|attribute| (attribute.key.clone(), attribute.value.clone()))
+                .collect()
+        }
+
+        impl From<ffi::Threading> for Model {
+            fn from(threading: ffi::Threading) -> Self {
+                match threading {
+                    ffi::Threading::Send => Model::Send,
+                    ffi::Threading::Concurrent => Model::Concurrent,
+                    _ => Model::Affine,
+                }
+            }
+        }
+
+        impl From<&mut zenoh_flow::Context> for ffi::Context {
+            fn from(context: &mut zenoh_flow::Context) -> Self {
+                Self {
+                    mode: context.mode,
+                    trace_parent: String::new(),
+                    spans: Vec::new(),
+                }
+            }
+        }
+
+        /// The threading model declared by the component for `configuration`.
+        fn threading(configuration: &ffi::ConfigurationMap) -> Model {
+            #[allow(unused_unsafe)]
+            unsafe {
+                Model::from(ffi::threading(configuration))
+            }
+        }
+
+        /// Exports the span of a call to `run` and the spans recorded below it by the component.
+        fn finish_span(
+            wrapper: &mut StateWrapper,
+            span: Option<&mut Span>,
+            context: &ffi::Context,
+        ) {
+            if let (Some(tracer), Some(span)) = (wrapper.tracer.as_mut(), span) {
+                let children = context
+                    .spans
+                    .iter()
+                    .map(|child| (child.name.as_str(), child.start, child.end));
+                if let Err(e) = tracer.finish(span, children) {
+                    log::warn!("[{}] Could not export the spans: {:?}", wrapper.instance, e);
+                }
+            }
+        }
+
+        /// Reports the latency of a call to `run`, see `zenoh_flow_cxx_common::latency`.
+        fn record_latency(wrapper: &mut StateWrapper, measure: Option<&Measure>) {
+            if let (Some(latency), Some(measure)) = (wrapper.latency.as_mut(), measure) {
+                match latency.record(measure) {
+                    Ok(Some(report)) => log::info!("[{}] Latency: {}", wrapper.instance, report),
+                    Ok(None) => (),
+                    Err(e) => log::warn!(
+                        "[{}] Could not write the latency report: {:?}",
+                        wrapper.instance,
+                        e
+                    ),
+                }
+            }
+        }
+    };
+}
+
+/// The outputs: the buffers given to the component and the data sent downstream.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __cxx_wrapper_outputs {
+    () => {
+        /// Returns a buffer of `size` bytes for an output, see `zenoh_flow_cxx_common::pool`.
+        pub fn allocate(size: usize) -> Vec<u8> {
+            pool::allocate(size)
+        }
+
+        /// Warns, once per port, that the encoding and the metadata of the outputs of `port_id`
+        /// are not sent: the port does not carry envelopes.
+        fn check_unframed(
+            wrapper: &mut StateWrapper,
+            port_id: &zenoh_flow::PortId,
+            encoding: &str,
+            metadata: &Metadata,
+        ) {
+            if (!encoding.is_empty() || !metadata.is_empty())
+                && wrapper.unframed.insert(port_id.clone())
+            {
+                log::warn!(
+                    "[{}] The encoding and the metadata of the outputs on <{}> are dropped, the \
+                     port is not in `{}`",
+                    wrapper.instance,
+                    port_id,
+                    envelope::ENVELOPE_OUTPUTS
+                );
+            }
+        }
+
+        impl ffi::Data {
+            pub fn new(
+                bytes: Vec<u8>,
+                encoding: String,
+                compression: Compression,
+                metadata: Metadata,
+                framed: bool,
+            ) -> Self {
+                Self {
+                    bytes,
+                    encoding,
+                    compression: compression.to_string(),
+                    metadata: metadata.into_iter().map(ffi::Attribute::from).collect(),
+                    framed,
+                }
+            }
+        }
+
+        impl Debug for ffi::Data {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.debug_struct("Data")
+                    .field("bytes", &self.bytes)
+                    .field("encoding", &self.encoding)
+                    .field("compression", &self.compression)
+                    .field("metadata", &to_metadata(&self.metadata))
+                    .field("framed", &self.framed)
+                    .finish()
+            }
+        }
+
+        impl DowncastAny for ffi::Data {
+            fn as_any(&self) -> &dyn std::any::Any {
+                self
+            }
+
+            fn as_mut_any(&mut self) -> &mut dyn std::any::Any {
+                self
+            }
+        }
+
+        impl Recycle for ffi::Data {
+            fn take_buffer(&mut self) -> Vec<u8> {
+                std::mem::take(&mut self.bytes)
+            }
+        }
+
+        impl shm::Placeable for ffi::Data {
+            fn payload(&self) -> &[u8] {
+                &self.bytes
+            }
+
+            fn header(&self) -> ZFResult<Vec<u8>> {
+                envelope::seal(
+                    &self.encoding,
+                    Compression::None,
+                    &to_metadata(&self.metadata),
+                    &[],
+                )
+            }
+        }
+
+        impl Data for ffi::Data {
+            fn try_serialize(&self) -> ZFResult<Vec<u8>> {
+                if !self.framed {
+                    return Ok(self.bytes.clone());
+                }
+                envelope::seal(
+                    &self.encoding,
+                    self.compression.parse()?,
+                    &to_metadata(&self.metadata),
+                    &self.bytes,
+                )
+            }
+        }
+    };
+}
+
+/// The inputs: the tokens given to `input_rule` and the span context of the inputs.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __cxx_wrapper_inputs {
+    () => {
+        /// The content of a token, only opened if `input_rule` reads it.
+        pub struct Payload(LazyEnvelope<SerDeData>);
+
+        impl Payload {
+            fn envelope(&self) -> Result<&Envelope, String> {
+                self.0.get(|message| match message {
+                    SerDeData::Serialized(ser) => Ok(ser.clone()),
+                    SerDeData::Deserialized(de) => de.try_serialize().map(Arc::new),
+                })
+            }
+
+            pub fn data(&self) -> Result<&[u8], String> {
+                self.envelope().map(Envelope::data)
+            }
+
+            pub fn encoding(&self) -> Result<&str, String> {
+                self.envelope().map(|envelope| envelope.encoding.as_str())
+            }
+
+            pub fn metadata(&self) -> Result<Vec<ffi::Attribute>, String> {
+                self.envelope().map(|envelope| {
+                    envelope
+                        .metadata
+                        .iter()
+                        .cloned()
+                        .map(ffi::Attribute::from)
+                        .collect()
+                })
+            }
+        }
+
+        impl ffi::Token {
+            pub fn from_token(token: &Token, port_id: &str, ports: &Ports) -> Self {
+                let port = ports.input_handle(port_id);
+                match token {
+                    Token::NotReady => Self {
+                        status: ffi::TokenStatus::Pending,
+                        action: ffi::TokenAction::Wait,
+                        port_id: ports.input_id(port_id),
+                        payload: Box::new(Payload(LazyEnvelope::new(None, Reception::default()))),
+                        timestamp: 0,
+                        pending: 0,
+                        port,
+                    },
+
+                    Token::Ready(token) => {
+                        let message = match &token.data.data {
+                            SerDeData::Serialized(ser) => SerDeData::Serialized(ser.clone()),
+                            SerDeData::Deserialized(de) => SerDeData::Deserialized(de.clone()),
+                        };
+
+                        Self {
+                            status: ffi::TokenStatus::Ready,
+                            action: ffi::TokenAction::Consume,
+                            port_id: ports.input_id(port_id),
+                            payload: Box::new(Payload(LazyEnvelope::new(
+                                Some(message),
+                                ports.reception(port_id),
+                            ))),
+                            timestamp: token.data.timestamp.get_time().as_u64(),
+                            pending: 0,
+                            port,
+                        }
+                    }
+                }
+            }
+        }
+
+        impl From<TokenAction> for ffi::TokenAction {
+            fn from(action: TokenAction) -> Self {
+                match action {
+                    TokenAction::Consume => ffi::TokenAction::Consume,
+                    TokenAction::Drop => ffi::TokenAction::Drop,
+                    TokenAction::KeepRun => ffi::TokenAction::Keep,
+                    TokenAction::Keep => ffi::TokenAction::Keep,
+                    TokenAction::Wait => ffi::TokenAction::Wait,
+                }
+            }
+        }
+
+        /// The span context carried by the inputs, looked for in the order of their ports.
+        fn trace_parent(inputs: &[ffi::Input]) -> Option<TraceContext> {
+            let mut inputs: Vec<&ffi::Input> = inputs.iter().collect();
+            // The handles follow the order of the ids, that the component may not send.
+            inputs.sort_by(|a, b| (a.port, &a.port_id).cmp(&(b.port, &b.port_id)));
+            inputs.iter().find_map(|input| {
+                input
+                    .metadata
+                    .iter()
+                    .filter(|attribute| attribute.key == trace::TRACEPARENT)
+                    .find_map(|attribute| attribute.value.parse().ok())
+            })
+        }
+
+        /// Calls `input_rule` of the component with the tokens of the runtime, unless a batch
+        /// timed out: `run` then delivers it.
+        fn input_rule(
+            wrapper: &mut StateWrapper,
+            context: &mut zenoh_flow::Context,
+            tokens: &mut HashMap<zenoh_flow::PortId, Token>,
+        ) -> ZFResult<bool> {
+            if let Some(batcher) = &wrapper.batcher {
+                let flushed = tokens.iter().any(|(port_id, token)| {
+                    batcher.flushes(port_id) && matches!(token, Token::Ready(_))
+                });
+                if flushed || batcher.timed_out(std::time::Instant::now()) {
+                    return Ok(true);
+                }
+            }
+
+            let batcher = wrapper.batcher.as_ref();
+            let mut cxx_tokens: Vec<ffi::Token> = tokens
+                .iter()
+                .filter(|(port_id, _)| !batcher.is_some_and(|batcher| batcher.flushes(port_id)))
+                .map(|(port_id, token)| {
+                    let mut cxx_token = ffi::Token::from_token(token, port_id, &wrapper.ports);
+                    if let Some(batcher) = &wrapper.batcher {
+                        cxx_token.pending = batcher.pending(port_id) as u64;
+                    }
+                    cxx_token
+                })
+                .collect();
+            let mut cxx_context = ffi::Context::from(context);
+
+            let ready = wrapper.state.with(move |state| {
+                #[allow(unused_unsafe)]
+                unsafe {
+                    ffi::input_rule(&mut cxx_context, state, &mut cxx_tokens)
+                }
+            })?;
+            ready.map_err(|_| ZFError::GenericError)
+        }
+    };
+}
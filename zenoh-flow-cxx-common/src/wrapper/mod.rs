@@ -0,0 +1,23 @@
+//! The Rust side of the C++ components, the same for every component of a kind.
+//!
+//! The `cxx` bridge of a component stays in its crate, where `cxx_build` reads it, and
+//! `export_cxx_source!`, `export_cxx_operator!` or `export_cxx_sink!`, called at the root of the
+//! crate with the name of the bridge module, generates the wrapper around it and exports the
+//! component to the runtime:
+//!
+//! ```ignore
+//! #[cxx::bridge(namespace = "zenoh::flow")]
+//! pub mod ffi {
+//!     // The shared structures and the functions of an operator.
+//! }
+//!
+//! zenoh_flow_cxx_common::export_cxx_operator!(ffi);
+//! ```
+//!
+//! The bridge declares the structures and the functions of its kind, as the examples do, and the
+//! crate depends on `cxx`, `zenoh-flow`, `async-trait` and `log`.
+
+mod base;
+mod operator;
+mod sink;
+mod source;
@@ -0,0 +1,542 @@
+/// Generates the wrapper of a C++ operator around the bridge `$ffi` and exports it.
+///
+/// With `checkpoints`, the bridge also declares `snapshot` and `restore`, see
+/// `zenoh_flow_cxx_common::checkpoint`: `zenoh_flow_cxx_common::export_cxx_operator!(ffi,
+/// checkpoints);`.
+#[macro_export]
+macro_rules! export_cxx_operator {
+    ($ffi:ident) => {
+        $crate::__export_cxx_operator!($ffi, ());
+    };
+    ($ffi:ident, checkpoints) => {
+        $crate::__export_cxx_operator!($ffi, (checkpoints));
+    };
+}
+
+/// Snapshots and restores of the state, if the bridge declares them.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __cxx_checkpoint_hooks {
+    () => {
+        /// Whether the bridge declares `snapshot` and `restore`.
+        const CHECKPOINTS: bool = false;
+
+        fn snapshot(_state: &UniquePtr<ffi::State>) -> Result<Vec<u8>, String> {
+            Err("The component does not declare `snapshot`".to_string())
+        }
+
+        fn restore(
+            _configuration: &ffi::ConfigurationMap,
+            _snapshot: &[u8],
+        ) -> Result<UniquePtr<ffi::State>, String> {
+            Err("The component does not declare `restore`".to_string())
+        }
+    };
+    (checkpoints) => {
+        /// Whether the bridge declares `snapshot` and `restore`.
+        const CHECKPOINTS: bool = true;
+
+        fn snapshot(state: &UniquePtr<ffi::State>) -> Result<Vec<u8>, String> {
+            #[allow(unused_unsafe)]
+            let snapshot = unsafe { ffi::snapshot(state) };
+            snapshot.map_err(|e| e.to_string())
+        }
+
+        fn restore(
+            configuration: &ffi::ConfigurationMap,
+            snapshot: &[u8],
+        ) -> Result<UniquePtr<ffi::State>, String> {
+            #[allow(unused_unsafe)]
+            let state = unsafe { ffi::restore(configuration, snapshot) };
+            state.map_err(|e| e.to_string())
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __export_cxx_operator {
+    ($ffi:ident, ($($checkpoints:ident)?)) => {
+        pub use cxx_wrapper::{allocate, Payload, Shared};
+
+        zenoh_flow::export_operator!(cxx_wrapper::register);
+
+        #[doc(hidden)]
+        pub mod cxx_wrapper {
+            use super::$ffi as ffi;
+            use cxx::UniquePtr;
+            use std::{
+                collections::{HashMap, HashSet},
+                fmt::Debug,
+                sync::Arc,
+            };
+            use zenoh_flow::{
+                downcast_mut, runtime::message::SerDeData, Component, ComponentOutput, Data,
+                DowncastAny, InputRule, Operator, OutputRule, State, Token, TokenAction, ZFError,
+                ZFResult,
+            };
+            use $crate::{
+                batch::Batcher,
+                checkpoint::{self, Checkpoints},
+                compression::{Compression, Compressions},
+                envelope::{self, Envelope, LazyEnvelope, Reception},
+                instance,
+                latency::{Latency, Measure},
+                metadata::{self, Metadata},
+                opaque::{self, Handles, NO_HANDLE},
+                pool::{self, BufferPool, Recycle},
+                ports::Ports,
+                pretty::Pretty,
+                shm::{self, SharedMemory},
+                threading::{Confined, Model},
+                trace::{self, Span, TraceContext, Tracer},
+            };
+
+            $crate::__cxx_wrapper_base!();
+            $crate::__cxx_wrapper_outputs!();
+            $crate::__cxx_wrapper_inputs!();
+            $crate::__cxx_checkpoint_hooks!($($checkpoints)?);
+
+            pub struct StateWrapper {
+                pub state: Confined<UniquePtr<ffi::State>>,
+                pub instance: String,
+                pub pool: Option<Arc<BufferPool>>,
+                pub ports: Ports,
+                pub compressions: Compressions,
+                pub shm: Option<Arc<SharedMemory>>,
+                pub forward_metadata: bool,
+                /// The outputs without envelope that dropped an encoding or metadata.
+                pub unframed: HashSet<zenoh_flow::PortId>,
+                pub tracer: Option<Tracer>,
+                pub latency: Option<Latency>,
+                pub batcher: Option<Batcher<ffi::Input>>,
+                pub handles: Option<Handles>,
+                pub checkpoints: Option<Checkpoints>,
+            }
+
+            impl StateWrapper {
+                /// Saves a snapshot of the C++ state, see `zenoh_flow_cxx_common::checkpoint`.
+                fn checkpoint(&mut self) -> ZFResult<()> {
+                    let checkpoints = match &self.checkpoints {
+                        Some(checkpoints) => checkpoints,
+                        None => return Ok(()),
+                    };
+                    let snapshot = self.state.with(|state| snapshot(state))?;
+                    checkpoints.save(&snapshot.map_err(ZFError::InvalidData)?)
+                }
+            }
+
+            impl State for StateWrapper {
+                fn as_any(&self) -> &dyn std::any::Any {
+                    self
+                }
+
+                fn as_mut_any(&mut self) -> &mut dyn std::any::Any {
+                    self
+                }
+            }
+
+            impl Debug for StateWrapper {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    f.debug_struct("StateWrapper")
+                        .field("instance", &self.instance)
+                        .finish_non_exhaustive()
+                }
+            }
+
+            /// The payload of a received input, shared with the message (or mapped from shared
+            /// memory) until the input is dropped, or of an opaque input, serialized on first
+            /// access.
+            pub enum Shared {
+                Received(Envelope),
+                Opaque(LazyEnvelope<Arc<dyn Data>>),
+            }
+
+            impl Shared {
+                pub fn data(&self) -> Result<&[u8], String> {
+                    match self {
+                        Shared::Received(envelope) => Ok(envelope.data()),
+                        Shared::Opaque(opaque) => opaque
+                            .get(|data| data.try_serialize().map(Arc::new))
+                            .map(Envelope::data),
+                    }
+                }
+            }
+
+            impl ffi::Input {
+                /// Returns one input per message of `data_message`, see `envelope::Sequence`.
+                /// With `handles`, a Rust value is kept opaque rather than serialized.
+                fn from_data_message(
+                    port_id: &str,
+                    data_message: &zenoh_flow::runtime::message::DataMessage,
+                    ports: &Ports,
+                    handles: Option<&mut Handles>,
+                ) -> ZFResult<Vec<Self>> {
+                    let port = ports.input_handle(port_id);
+                    let timestamp = data_message.timestamp.get_time().as_u64();
+                    let bytes = match (&data_message.data, handles) {
+                        (SerDeData::Serialized(ser), _) => ser.clone(),
+                        (SerDeData::Deserialized(de), Some(handles)) => {
+                            return Ok(vec![Self {
+                                port_id: ports.input_id(port_id),
+                                data: Vec::new(),
+                                shared: Box::new(Shared::Opaque(LazyEnvelope::new(
+                                    Some(de.clone()),
+                                    ports.reception(port_id),
+                                ))),
+                                encoding: String::new(),
+                                metadata: Vec::new(),
+                                timestamp,
+                                port,
+                                handle: handles.insert(de.clone()),
+                            }]);
+                        }
+                        (SerDeData::Deserialized(de), None) => Arc::new(de.try_serialize()?),
+                    };
+
+                    Ok(envelope::receive(bytes, ports.reception(port_id))?
+                        .into_iter()
+                        .map(|mut envelope| Self {
+                            port_id: ports.input_id(port_id),
+                            data: envelope.payload.take_owned(),
+                            encoding: std::mem::take(&mut envelope.encoding),
+                            metadata: std::mem::take(&mut envelope.metadata)
+                                .into_iter()
+                                .map(ffi::Attribute::from)
+                                .collect(),
+                            shared: Box::new(Shared::Received(envelope)),
+                            timestamp,
+                            port,
+                            handle: NO_HANDLE,
+                        })
+                        .collect())
+                }
+            }
+
+            /*
+
+            Operator implementation.
+
+            */
+            pub struct MyOperator;
+
+            impl Component for MyOperator {
+                fn initialize(
+                    &self,
+                    configuration: &Option<std::collections::HashMap<String, String>>,
+                ) -> Box<dyn zenoh_flow::State> {
+                    // Before `instance` is set: only a named instance can be checkpointed.
+                    let checkpoints = Checkpoints::from_configuration(configuration.as_ref())
+                        .unwrap_or_else(|e| panic!("Invalid checkpoint configuration: {:?}", e));
+                    if checkpoints.is_some() && !CHECKPOINTS {
+                        panic!(
+                            "Invalid checkpoint configuration: `{}` is set but the component does \
+                             not declare `snapshot` and `restore`",
+                            checkpoint::CHECKPOINT
+                        );
+                    }
+                    let (instance, configuration) =
+                        instance::configure(configuration, env!("CARGO_PKG_NAME"));
+                    let configuration = Some(configuration);
+                    let ports = Ports::from_configuration(configuration.as_ref())
+                        .unwrap_or_else(|e| panic!("Invalid port configuration: {:?}", e));
+                    let pool = BufferPool::from_configuration(configuration.as_ref())
+                        .unwrap_or_else(|e| panic!("Invalid buffer pool configuration: {:?}", e));
+                    let compressions = Compressions::from_configuration(configuration.as_ref())
+                        .unwrap_or_else(|e| panic!("Invalid compression configuration: {:?}", e));
+                    let shm = SharedMemory::from_configuration(configuration.as_ref())
+                        .unwrap_or_else(|e| panic!("Invalid shared memory configuration: {:?}", e));
+                    let forward_metadata = metadata::forwards(configuration.as_ref());
+                    let tracer = Tracer::from_configuration(
+                        configuration.as_ref(),
+                        env!("CARGO_PKG_NAME"),
+                        &instance,
+                    )
+                    .unwrap_or_else(|e| panic!("Invalid trace configuration: {:?}", e));
+                    let latency = Latency::from_configuration(configuration.as_ref(), &instance);
+                    let batcher = Batcher::from_configuration(configuration.as_ref())
+                        .unwrap_or_else(|e| panic!("Invalid batch configuration: {:?}", e));
+                    let handles = if opaque::enabled(configuration.as_ref()) {
+                        Some(Handles::default())
+                    } else {
+                        None
+                    };
+                    let snapshot = checkpoints.as_ref().and_then(|checkpoints| {
+                        checkpoints.load().unwrap_or_else(|e| {
+                            log::warn!("[{}] Could not read the checkpoint: {:?}", instance, e);
+                            None
+                        })
+                    });
+                    let configuration =
+                        ffi::ConfigurationMap::from(configuration.unwrap_or_default());
+
+                    let model = threading(&configuration);
+                    let name = instance.clone();
+                    let state = Confined::new(model, &instance, move || {
+                        // The last snapshot, if any, rather than a fresh state.
+                        if let Some(snapshot) = snapshot {
+                            match restore(&configuration, &snapshot) {
+                                Ok(state) => return state,
+                                Err(e) => log::warn!(
+                                    "[{}] Could not restore the checkpoint: {}",
+                                    name,
+                                    e
+                                ),
+                            }
+                        }
+                        #[allow(unused_unsafe)]
+                        unsafe {
+                            ffi::initialize(&configuration)
+                        }
+                    })
+                    .unwrap_or_else(|e| panic!("Could not start the component: {:?}", e));
+                    Box::new(StateWrapper {
+                        state,
+                        instance,
+                        pool,
+                        ports,
+                        compressions,
+                        shm,
+                        forward_metadata,
+                        unframed: HashSet::new(),
+                        tracer,
+                        latency,
+                        batcher,
+                        handles,
+                        checkpoints,
+                    })
+                }
+
+                fn clean(&self, dyn_state: &mut Box<dyn zenoh_flow::State>) -> ZFResult<()> {
+                    let wrapper = downcast_mut!(StateWrapper, dyn_state).unwrap();
+                    wrapper.checkpoint()
+                }
+            }
+
+            impl InputRule for MyOperator {
+                fn input_rule(
+                    &self,
+                    context: &mut zenoh_flow::Context,
+                    dyn_state: &mut Box<dyn zenoh_flow::State>,
+                    tokens: &mut HashMap<zenoh_flow::PortId, zenoh_flow::Token>,
+                ) -> zenoh_flow::ZFResult<bool> {
+                    let wrapper = downcast_mut!(StateWrapper, dyn_state).unwrap();
+                    input_rule(wrapper, context, tokens)
+                }
+            }
+
+            impl OutputRule for MyOperator {
+                fn output_rule(
+                    &self,
+                    _context: &mut zenoh_flow::Context,
+                    _dyn_state: &mut Box<dyn zenoh_flow::State>,
+                    outputs: &HashMap<zenoh_flow::PortId, std::sync::Arc<dyn zenoh_flow::Data>>,
+                ) -> ZFResult<HashMap<zenoh_flow::PortId, zenoh_flow::ComponentOutput>> {
+                    let mut results = HashMap::with_capacity(outputs.len());
+                    // NOTE: default output rule for now.
+                    for (port_id, data) in outputs {
+                        results.insert(port_id.clone(), ComponentOutput::Data(data.clone()));
+                    }
+
+                    Ok(results)
+                }
+            }
+
+            impl Operator for MyOperator {
+                fn run(
+                    &self,
+                    context: &mut zenoh_flow::Context,
+                    dyn_state: &mut Box<dyn zenoh_flow::State>,
+                    inputs: &mut HashMap<
+                        zenoh_flow::PortId,
+                        zenoh_flow::runtime::message::DataMessage,
+                    >,
+                ) -> ZFResult<HashMap<zenoh_flow::PortId, std::sync::Arc<dyn zenoh_flow::Data>>>
+                {
+                    let received = trace::now();
+                    let mut cxx_context = ffi::Context::from(context);
+                    let wrapper = downcast_mut!(StateWrapper, dyn_state).unwrap();
+                    let cxx_inputs = match wrapper.batcher.as_mut() {
+                        Some(batcher) => {
+                            let now = std::time::Instant::now();
+                            for (port_id, data_message) in inputs.iter() {
+                                if batcher.flushes(port_id) {
+                                    continue;
+                                }
+                                for input in ffi::Input::from_data_message(
+                                    port_id,
+                                    data_message,
+                                    &wrapper.ports,
+                                    wrapper.handles.as_mut(),
+                                )? {
+                                    batcher.push(port_id, input, now);
+                                }
+                            }
+                            if !batcher.is_ready(now) {
+                                return Ok(HashMap::new());
+                            }
+                            batcher.take()
+                        }
+                        None => {
+                            let mut messages: Vec<ffi::Input> = Vec::with_capacity(inputs.len());
+                            for (port_id, data_message) in inputs.iter() {
+                                messages.extend(ffi::Input::from_data_message(
+                                    port_id,
+                                    data_message,
+                                    &wrapper.ports,
+                                    wrapper.handles.as_mut(),
+                                )?);
+                            }
+                            messages
+                        }
+                    };
+
+                    // The metadata of the inputs, ordered by port, if the operator forwards it.
+                    let forwarded: Vec<Metadata> = if wrapper.forward_metadata {
+                        let mut inputs: Vec<&ffi::Input> = cxx_inputs.iter().collect();
+                        inputs.sort_by(|a, b| (a.port, &a.port_id).cmp(&(b.port, &b.port_id)));
+                        inputs
+                            .iter()
+                            .map(|input| to_metadata(&input.metadata))
+                            .collect()
+                    } else {
+                        Vec::new()
+                    };
+
+                    let mut span = wrapper
+                        .tracer
+                        .as_ref()
+                        .map(|_| Span::start("run", trace_parent(&cxx_inputs)));
+                    if let Some(span) = &span {
+                        cxx_context.trace_parent = span.context.to_string();
+                    }
+
+                    let mut measure = wrapper.latency.as_ref().map(|_| Measure::start(received));
+                    if let Some(measure) = measure.as_mut() {
+                        for input in cxx_inputs.iter() {
+                            measure.input(input.timestamp, &to_metadata(&input.metadata));
+                        }
+                        measure.enter();
+                    }
+
+                    let pool = wrapper.pool.clone();
+                    let (cxx_context, cxx_outputs) = wrapper.state.with(move |state| {
+                        // `allocate` is called on the thread of the state.
+                        let _pool = pool::enter(pool.as_ref());
+                        #[allow(unused_unsafe)]
+                        let outputs = unsafe { ffi::run(&mut cxx_context, state, cxx_inputs) };
+                        (cxx_context, outputs)
+                    })?;
+                    // The values of the opaque inputs only live as long as the outputs
+                    // forwarding them.
+                    let handles = wrapper.handles.as_mut().map(std::mem::take);
+                    let cxx_outputs = cxx_outputs.map_err(|_| ZFError::GenericError)?;
+
+                    if let Some(measure) = measure.as_mut() {
+                        measure.exit();
+                    }
+                    finish_span(wrapper, span.as_mut(), &cxx_context);
+
+                    let mut outputs: Vec<(zenoh_flow::PortId, Arc<dyn zenoh_flow::Data>)> =
+                        Vec::with_capacity(cxx_outputs.len());
+                    for cxx_output in cxx_outputs.into_iter() {
+                        let ffi::Output {
+                            port_id,
+                            data,
+                            encoding,
+                            metadata,
+                            port,
+                            handle,
+                        } = cxx_output;
+                        // The component names the port, or gives its handle.
+                        let port_id: zenoh_flow::PortId = if port_id.is_empty() {
+                            wrapper.ports.output_port(port)?.clone()
+                        } else {
+                            port_id.into()
+                        };
+                        // An opaque input goes downstream as it came, without envelope.
+                        if handle != NO_HANDLE {
+                            let handles = handles.as_ref().ok_or_else(|| {
+                                ZFError::InvalidData(format!(
+                                    "Output handle without `{}`",
+                                    opaque::OPAQUE_INPUTS
+                                ))
+                            })?;
+                            let data = handles.forward(&wrapper.ports, &port_id, handle)?;
+                            outputs.push((port_id, data));
+                            continue;
+                        }
+                        wrapper.ports.check_output(&port_id, &data)?;
+                        log::debug!(
+                            "[{}] Output on <{}>: {}",
+                            wrapper.instance,
+                            port_id,
+                            Pretty::new(wrapper.ports.output_type(&port_id), &data)
+                        );
+                        let compression = wrapper.compressions.output(&port_id);
+                        let framed = wrapper.ports.frames_output(&port_id);
+                        // The segment is named in an envelope.
+                        let shared = wrapper
+                            .shm
+                            .as_ref()
+                            .filter(|shm| framed && shm.accepts(data.len()))
+                            .cloned();
+                        let mut metadata = to_metadata(&metadata);
+                        metadata::forward(&forwarded, &mut metadata);
+                        if let Some(span) = &span {
+                            metadata::set(
+                                &mut metadata,
+                                trace::TRACEPARENT,
+                                &span.context.to_string(),
+                            );
+                        }
+                        if let (Some(latency), Some(measure)) = (&wrapper.latency, &measure) {
+                            latency.embed(measure, &mut metadata);
+                        }
+                        if !framed {
+                            check_unframed(wrapper, &port_id, &encoding, &metadata);
+                        }
+                        let data =
+                            ffi::Data::new(data, encoding, compression, metadata, framed);
+                        let data = match shared {
+                            Some(shm) => {
+                                pool::share(wrapper.pool.as_ref(), shm::Shared::new(data, shm))
+                            }
+                            None => pool::share(wrapper.pool.as_ref(), data),
+                        };
+                        outputs.push((port_id, data));
+                    }
+
+                    let outputs = wrapper.ports.group_outputs(outputs)?;
+                    let mut result: HashMap<zenoh_flow::PortId, Arc<dyn zenoh_flow::Data>> =
+                        HashMap::with_capacity(outputs.len());
+                    for (port_id, data) in outputs {
+                        result.insert(port_id, data);
+                    }
+
+                    record_latency(wrapper, measure.as_ref());
+
+                    if wrapper
+                        .checkpoints
+                        .as_mut()
+                        .map_or(false, Checkpoints::is_due)
+                    {
+                        if let Err(e) = wrapper.checkpoint() {
+                            log::warn!(
+                                "[{}] Could not save the checkpoint: {:?}",
+                                wrapper.instance,
+                                e
+                            );
+                        }
+                    }
+
+                    Ok(result)
+                }
+            }
+
+            pub fn register() -> ZFResult<Arc<dyn Operator>> {
+                Ok(Arc::new(MyOperator) as Arc<dyn Operator>)
+            }
+        }
+    };
+}
@@ -0,0 +1,263 @@
+/// Generates the wrapper of a C++ sink around the bridge `$ffi` and exports it.
+#[macro_export]
+macro_rules! export_cxx_sink {
+    ($ffi:ident) => {
+        pub use cxx_wrapper::{pretty_print, Payload, Shared};
+
+        zenoh_flow::export_sink!(cxx_wrapper::register);
+
+        #[doc(hidden)]
+        pub mod cxx_wrapper {
+            use super::$ffi as ffi;
+            use async_trait::async_trait;
+            use cxx::UniquePtr;
+            use std::{collections::HashMap, fmt::Debug, sync::Arc};
+            use $crate::{
+                batch::Batcher,
+                envelope::{self, Envelope, LazyEnvelope, Reception},
+                instance,
+                latency::{Latency, Measure},
+                metadata::Metadata,
+                ports::Ports,
+                pretty::pretty,
+                threading::{Confined, Model},
+                trace::{self, Span, TraceContext, Tracer},
+            };
+            use zenoh_flow::{
+                downcast_mut,
+                runtime::message::{DataMessage, SerDeData},
+                Component, Context, Data, InputRule, PortId, Sink, State, Token, TokenAction,
+                ZFError, ZFResult,
+            };
+
+            $crate::__cxx_wrapper_base!();
+            $crate::__cxx_wrapper_inputs!();
+
+            /// Formats a payload for display, see `zenoh_flow_cxx_common::pretty`. An empty
+            /// `port_type` stands for an unknown type, in which case the `encoding` of the
+            /// payload, if any, is used.
+            pub fn pretty_print(port_type: &str, encoding: &str, data: &[u8]) -> String {
+                let port_type = if port_type.is_empty() {
+                    envelope::port_type(encoding)
+                } else {
+                    Some(port_type)
+                };
+                pretty(port_type, data)
+            }
+
+            pub struct StateWrapper {
+                pub state: Confined<UniquePtr<ffi::State>>,
+                pub instance: String,
+                pub ports: Ports,
+                pub tracer: Option<Tracer>,
+                pub latency: Option<Latency>,
+                pub batcher: Option<Batcher<ffi::Input>>,
+            }
+
+            impl State for StateWrapper {
+                fn as_any(&self) -> &dyn std::any::Any {
+                    self
+                }
+
+                fn as_mut_any(&mut self) -> &mut dyn std::any::Any {
+                    self
+                }
+            }
+
+            impl Debug for StateWrapper {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    f.debug_struct("StateWrapper")
+                        .field("instance", &self.instance)
+                        .finish_non_exhaustive()
+                }
+            }
+
+            /// The payload of a received input, shared with the message (or mapped from shared
+            /// memory) until the input is dropped.
+            pub struct Shared(Envelope);
+
+            impl Shared {
+                pub fn data(&self) -> &[u8] {
+                    self.0.data()
+                }
+            }
+
+            impl ffi::Input {
+                /// Returns one input per message of `data_message`, see `envelope::Sequence`.
+                fn from_data_message(
+                    port_id: &str,
+                    data_message: &DataMessage,
+                    ports: &Ports,
+                ) -> ZFResult<Vec<Self>> {
+                    let bytes = match &data_message.data {
+                        SerDeData::Serialized(ser) => ser.clone(),
+                        SerDeData::Deserialized(de) => Arc::new(de.try_serialize()?),
+                    };
+                    let envelopes = envelope::receive(bytes, ports.reception(port_id))?;
+
+                    let port = ports.input_handle(port_id);
+                    Ok(envelopes
+                        .into_iter()
+                        .map(|mut envelope| Self {
+                            port_id: ports.input_id(port_id),
+                            data: envelope.payload.take_owned(),
+                            encoding: std::mem::take(&mut envelope.encoding),
+                            metadata: std::mem::take(&mut envelope.metadata)
+                                .into_iter()
+                                .map(ffi::Attribute::from)
+                                .collect(),
+                            shared: Box::new(Shared(envelope)),
+                            timestamp: data_message.timestamp.get_time().as_u64(),
+                            port,
+                        })
+                        .collect())
+                }
+            }
+
+            /*
+
+            Sink implementation.
+
+            */
+            pub struct MySink;
+
+            impl Component for MySink {
+                fn initialize(
+                    &self,
+                    configuration: &Option<std::collections::HashMap<String, String>>,
+                ) -> Box<dyn zenoh_flow::State> {
+                    let (instance, configuration) =
+                        instance::configure(configuration, env!("CARGO_PKG_NAME"));
+                    let configuration = Some(configuration);
+                    let ports = Ports::from_configuration(configuration.as_ref())
+                        .unwrap_or_else(|e| panic!("Invalid port configuration: {:?}", e));
+                    let tracer = Tracer::from_configuration(
+                        configuration.as_ref(),
+                        env!("CARGO_PKG_NAME"),
+                        &instance,
+                    )
+                    .unwrap_or_else(|e| panic!("Invalid trace configuration: {:?}", e));
+                    let latency = Latency::from_configuration(configuration.as_ref(), &instance);
+                    let batcher = Batcher::from_configuration(configuration.as_ref())
+                        .unwrap_or_else(|e| panic!("Invalid batch configuration: {:?}", e));
+                    let configuration =
+                        ffi::ConfigurationMap::from(configuration.unwrap_or_default());
+
+                    let model = threading(&configuration);
+                    let state = Confined::new(model, &instance, move || {
+                        #[allow(unused_unsafe)]
+                        unsafe {
+                            ffi::initialize(&configuration)
+                        }
+                    })
+                    .unwrap_or_else(|e| panic!("Could not start the component: {:?}", e));
+                    Box::new(StateWrapper {
+                        state,
+                        instance,
+                        ports,
+                        tracer,
+                        latency,
+                        batcher,
+                    })
+                }
+
+                fn clean(&self, _state: &mut Box<dyn State>) -> ZFResult<()> {
+                    Ok(())
+                }
+            }
+
+            impl InputRule for MySink {
+                fn input_rule(
+                    &self,
+                    context: &mut zenoh_flow::Context,
+                    dyn_state: &mut Box<dyn State>,
+                    tokens: &mut HashMap<PortId, zenoh_flow::Token>,
+                ) -> zenoh_flow::ZFResult<bool> {
+                    let wrapper = downcast_mut!(StateWrapper, dyn_state).unwrap();
+                    input_rule(wrapper, context, tokens)
+                }
+            }
+
+            #[async_trait]
+            impl Sink for MySink {
+                async fn run(
+                    &self,
+                    context: &mut Context,
+                    dyn_state: &mut Box<dyn State>,
+                    inputs: &mut HashMap<PortId, DataMessage>,
+                ) -> ZFResult<()> {
+                    let received = trace::now();
+                    let mut cxx_context = ffi::Context::from(context);
+                    let wrapper = downcast_mut!(StateWrapper, dyn_state).unwrap();
+                    let cxx_inputs = match wrapper.batcher.as_mut() {
+                        Some(batcher) => {
+                            let now = std::time::Instant::now();
+                            for (port_id, data_message) in inputs.iter() {
+                                if batcher.flushes(port_id) {
+                                    continue;
+                                }
+                                for input in ffi::Input::from_data_message(
+                                    port_id,
+                                    data_message,
+                                    &wrapper.ports,
+                                )? {
+                                    batcher.push(port_id, input, now);
+                                }
+                            }
+                            if !batcher.is_ready(now) {
+                                return Ok(());
+                            }
+                            batcher.take()
+                        }
+                        None => {
+                            let mut messages: Vec<ffi::Input> = Vec::with_capacity(inputs.len());
+                            for (port_id, data_message) in inputs.iter() {
+                                messages.extend(ffi::Input::from_data_message(
+                                    port_id,
+                                    data_message,
+                                    &wrapper.ports,
+                                )?);
+                            }
+                            messages
+                        }
+                    };
+
+                    let mut span = wrapper
+                        .tracer
+                        .as_ref()
+                        .map(|_| Span::start("run", trace_parent(&cxx_inputs)));
+                    if let Some(span) = &span {
+                        cxx_context.trace_parent = span.context.to_string();
+                    }
+
+                    let mut measure = wrapper.latency.as_ref().map(|_| Measure::start(received));
+                    if let Some(measure) = measure.as_mut() {
+                        for input in cxx_inputs.iter() {
+                            measure.input(input.timestamp, &to_metadata(&input.metadata));
+                        }
+                        measure.enter();
+                    }
+
+                    let (cxx_context, result) = wrapper.state.with(move |state| {
+                        #[allow(unused_unsafe)]
+                        let result = unsafe { ffi::run(&mut cxx_context, state, cxx_inputs) };
+                        (cxx_context, result)
+                    })?;
+                    result.map_err(|_| ZFError::GenericError)?;
+
+                    if let Some(measure) = measure.as_mut() {
+                        measure.exit();
+                    }
+                    finish_span(wrapper, span.as_mut(), &cxx_context);
+                    record_latency(wrapper, measure.as_ref());
+
+                    Ok(())
+                }
+            }
+
+            pub fn register() -> ZFResult<Arc<dyn Sink>> {
+                Ok(Arc::new(MySink) as Arc<dyn Sink>)
+            }
+        }
+    };
+}
@@ -0,0 +1,255 @@
+/// Generates the wrapper of a C++ source around the bridge `$ffi` and exports it.
+#[macro_export]
+macro_rules! export_cxx_source {
+    ($ffi:ident) => {
+        pub use cxx_wrapper::allocate;
+
+        zenoh_flow::export_source!(cxx_wrapper::register);
+
+        #[doc(hidden)]
+        pub mod cxx_wrapper {
+            use super::$ffi as ffi;
+            use async_trait::async_trait;
+            use cxx::UniquePtr;
+            use std::{
+                collections::{HashMap, HashSet},
+                fmt::Debug,
+                sync::Arc,
+            };
+            use $crate::{
+                compression::{Compression, Compressions},
+                envelope, instance,
+                latency::{Latency, Measure},
+                metadata::{self, Metadata},
+                pool::{self, BufferPool, Recycle},
+                ports::Ports,
+                pretty::Pretty,
+                shm::{self, SharedMemory},
+                threading::{Confined, Model},
+                trace::{self, Span, Tracer},
+            };
+            use zenoh_flow::{
+                downcast_mut, Component, ComponentOutput, Context, Data, DowncastAny, OutputRule,
+                PortId, Source, State, ZFError, ZFResult,
+            };
+
+            $crate::__cxx_wrapper_base!();
+            $crate::__cxx_wrapper_outputs!();
+
+            pub struct StateWrapper {
+                pub state: Confined<UniquePtr<ffi::State>>,
+                pub instance: String,
+                pub pool: Option<Arc<BufferPool>>,
+                pub ports: Ports,
+                pub compressions: Compressions,
+                pub shm: Option<Arc<SharedMemory>>,
+                /// The outputs without envelope that dropped an encoding or metadata.
+                pub unframed: HashSet<zenoh_flow::PortId>,
+                pub tracer: Option<Tracer>,
+                pub latency: Option<Latency>,
+            }
+
+            impl State for StateWrapper {
+                fn as_any(&self) -> &dyn std::any::Any {
+                    self
+                }
+
+                fn as_mut_any(&mut self) -> &mut dyn std::any::Any {
+                    self
+                }
+            }
+
+            impl Debug for StateWrapper {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    f.debug_struct("StateWrapper")
+                        .field("instance", &self.instance)
+                        .finish_non_exhaustive()
+                }
+            }
+
+            /*
+
+            Source implementation.
+
+            */
+            pub struct MySource;
+
+            impl Component for MySource {
+                fn initialize(
+                    &self,
+                    configuration: &Option<std::collections::HashMap<String, String>>,
+                ) -> Box<dyn zenoh_flow::State> {
+                    let (instance, configuration) =
+                        instance::configure(configuration, env!("CARGO_PKG_NAME"));
+                    let configuration = Some(configuration);
+                    let ports = Ports::from_configuration(configuration.as_ref())
+                        .unwrap_or_else(|e| panic!("Invalid port configuration: {:?}", e));
+                    let pool = BufferPool::from_configuration(configuration.as_ref())
+                        .unwrap_or_else(|e| panic!("Invalid buffer pool configuration: {:?}", e));
+                    let compressions = Compressions::from_configuration(configuration.as_ref())
+                        .unwrap_or_else(|e| panic!("Invalid compression configuration: {:?}", e));
+                    let shm = SharedMemory::from_configuration(configuration.as_ref())
+                        .unwrap_or_else(|e| panic!("Invalid shared memory configuration: {:?}", e));
+                    let tracer = Tracer::from_configuration(
+                        configuration.as_ref(),
+                        env!("CARGO_PKG_NAME"),
+                        &instance,
+                    )
+                    .unwrap_or_else(|e| panic!("Invalid trace configuration: {:?}", e));
+                    let latency = Latency::from_configuration(configuration.as_ref(), &instance);
+                    let configuration =
+                        ffi::ConfigurationMap::from(configuration.unwrap_or_default());
+
+                    let model = threading(&configuration);
+                    let state = Confined::new(model, &instance, move || {
+                        #[allow(unused_unsafe)]
+                        unsafe {
+                            ffi::initialize(&configuration)
+                        }
+                    })
+                    .unwrap_or_else(|e| panic!("Could not start the component: {:?}", e));
+                    Box::new(StateWrapper {
+                        state,
+                        instance,
+                        pool,
+                        ports,
+                        compressions,
+                        shm,
+                        unframed: HashSet::new(),
+                        tracer,
+                        latency,
+                    })
+                }
+
+                fn clean(&self, _state: &mut Box<dyn State>) -> ZFResult<()> {
+                    Ok(())
+                }
+            }
+
+            impl OutputRule for MySource {
+                fn output_rule(
+                    &self,
+                    _context: &mut Context,
+                    _dyn_state: &mut Box<dyn State>,
+                    outputs: &HashMap<PortId, std::sync::Arc<dyn zenoh_flow::Data>>,
+                ) -> ZFResult<HashMap<zenoh_flow::PortId, zenoh_flow::ComponentOutput>> {
+                    let mut results = HashMap::with_capacity(outputs.len());
+                    // NOTE: default output rule for now.
+                    for (port_id, data) in outputs {
+                        results.insert(port_id.clone(), ComponentOutput::Data(data.clone()));
+                    }
+
+                    Ok(results)
+                }
+            }
+
+            #[async_trait]
+            impl Source for MySource {
+                async fn run(
+                    &self,
+                    context: &mut Context,
+                    dyn_state: &mut Box<dyn zenoh_flow::State>,
+                ) -> ZFResult<HashMap<PortId, Arc<dyn Data>>> {
+                    let received = trace::now();
+                    let mut cxx_context = ffi::Context::from(context);
+                    let wrapper = downcast_mut!(StateWrapper, dyn_state).unwrap();
+
+                    let mut span = wrapper.tracer.as_ref().map(|_| Span::start("run", None));
+                    if let Some(span) = &span {
+                        cxx_context.trace_parent = span.context.to_string();
+                    }
+
+                    let mut measure = wrapper.latency.as_ref().map(|_| Measure::start(received));
+                    if let Some(measure) = measure.as_mut() {
+                        measure.enter();
+                    }
+
+                    let pool = wrapper.pool.clone();
+                    let (cxx_context, cxx_outputs) = wrapper.state.with(move |state| {
+                        // `allocate` is called on the thread of the state.
+                        let _pool = pool::enter(pool.as_ref());
+                        #[allow(unused_unsafe)]
+                        let outputs = unsafe { ffi::run(&mut cxx_context, state) };
+                        (cxx_context, outputs)
+                    })?;
+                    let cxx_outputs = cxx_outputs.map_err(|_| ZFError::GenericError)?;
+
+                    if let Some(measure) = measure.as_mut() {
+                        measure.exit();
+                    }
+                    finish_span(wrapper, span.as_mut(), &cxx_context);
+
+                    let mut outputs: Vec<(zenoh_flow::PortId, Arc<dyn zenoh_flow::Data>)> =
+                        Vec::with_capacity(cxx_outputs.len());
+                    for cxx_output in cxx_outputs.into_iter() {
+                        let ffi::Output {
+                            port_id,
+                            data,
+                            encoding,
+                            metadata,
+                            port,
+                        } = cxx_output;
+                        // The component names the port, or gives its handle.
+                        let port_id: zenoh_flow::PortId = if port_id.is_empty() {
+                            wrapper.ports.output_port(port)?.clone()
+                        } else {
+                            port_id.into()
+                        };
+                        wrapper.ports.check_output(&port_id, &data)?;
+                        log::debug!(
+                            "[{}] Output on <{}>: {}",
+                            wrapper.instance,
+                            port_id,
+                            Pretty::new(wrapper.ports.output_type(&port_id), &data)
+                        );
+                        let compression = wrapper.compressions.output(&port_id);
+                        let framed = wrapper.ports.frames_output(&port_id);
+                        // The segment is named in an envelope.
+                        let shared = wrapper
+                            .shm
+                            .as_ref()
+                            .filter(|shm| framed && shm.accepts(data.len()))
+                            .cloned();
+                        let mut metadata = to_metadata(&metadata);
+                        if let Some(span) = &span {
+                            metadata::set(
+                                &mut metadata,
+                                trace::TRACEPARENT,
+                                &span.context.to_string(),
+                            );
+                        }
+                        if let (Some(latency), Some(measure)) = (&wrapper.latency, &measure) {
+                            latency.embed(measure, &mut metadata);
+                        }
+                        if !framed {
+                            check_unframed(wrapper, &port_id, &encoding, &metadata);
+                        }
+                        let data = ffi::Data::new(data, encoding, compression, metadata, framed);
+                        let data = match shared {
+                            Some(shm) => {
+                                pool::share(wrapper.pool.as_ref(), shm::Shared::new(data, shm))
+                            }
+                            None => pool::share(wrapper.pool.as_ref(), data),
+                        };
+                        outputs.push((port_id, data));
+                    }
+
+                    let outputs = wrapper.ports.group_outputs(outputs)?;
+                    let mut result: HashMap<PortId, Arc<dyn zenoh_flow::Data>> =
+                        HashMap::with_capacity(outputs.len());
+                    for (port_id, data) in outputs {
+                        result.insert(port_id, data);
+                    }
+
+                    record_latency(wrapper, measure.as_ref());
+
+                    Ok(result)
+                }
+            }
+
+            pub fn register() -> ZFResult<Arc<dyn Source>> {
+                Ok(Arc::new(MySource) as Arc<dyn Source>)
+            }
+        }
+    };
+}
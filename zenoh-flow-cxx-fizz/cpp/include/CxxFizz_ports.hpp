@@ -0,0 +1,17 @@
+// Generated by zenoh-flow-cxx-graphs from the descriptor of CxxFizz: do not edit.
+// Handles of the ports of CxxFizz, see `Input::port` and `Output::port`.
+#pragma once
+#include <cstdint>
+
+namespace zenoh {
+namespace flow {
+namespace ports {
+namespace input {
+constexpr std::uint32_t number = 0;
+} // namespace input
+namespace output {
+constexpr std::uint32_t fizz = 0;
+} // namespace output
+} // namespace ports
+} // namespace flow
+} // namespace zenoh
@@ -0,0 +1,29 @@
+#pragma once
+#include <algorithm>
+#include <cstdint>
+#include <memory>
+#include <string>
+#include <vector>
+#include "rust/cxx.h"
+#include <iostream>
+#include "zenoh-flow-cxx-fizz/../include/zenoh_flow.hpp"
+
+namespace zenoh {
+namespace flow {
+
+class State {
+public:
+  State ();
+  std::string fizzbuzz (std::uint64_t number);
+};
+
+Threading threading(const ConfigurationMap &configuration);
+std::unique_ptr<State> initialize(const ConfigurationMap &configuration);
+bool input_rule(Context &context, std::unique_ptr<State> &state,
+                rust::Vec<Token> &tokens);
+rust::Vec<Output> run(Context &context,
+                      std::unique_ptr<State> &state,
+                      rust::Vec<Input> inputs);
+
+} // namespace flow
+} // namespace zenoh
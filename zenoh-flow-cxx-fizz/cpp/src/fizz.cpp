@@ -0,0 +1,81 @@
+#include "zenoh-flow-cxx-fizz/cpp/include/fizz.hpp"
+#include "zenoh-flow-cxx-fizz/cpp/include/CxxFizz_ports.hpp"
+#include "zenoh-flow-cxx-fizz/src/lib.rs.h"
+#include <algorithm>
+#include <cstdint>
+#include <cstring>
+#include <memory>
+#include <string>
+#include <vector>
+
+namespace zenoh {
+namespace flow {
+
+State::State() {}
+
+std::string State::fizzbuzz(std::uint64_t number) {
+  std::string result;
+  if (number % 3 == 0) {
+    result += "Fizz";
+  }
+  if (number % 5 == 0) {
+    result += "Buzz";
+  }
+  if (result.empty()) {
+    result = std::to_string(number);
+  }
+  return result;
+}
+
+Threading
+threading(const ConfigurationMap &configuration) {
+  //
+  // The state is pinned to a thread of its own, as a component holding
+  // thread-bound resources (GPU context, interpreter, ...) would be.
+  //
+  return Threading::Affine;
+}
+
+std::unique_ptr<State>
+initialize(const ConfigurationMap &configuration) {
+  //
+  // /!\ NOTE: `make_unique` requires "c++14"
+  //
+  return std::make_unique<State>();
+}
+
+bool
+input_rule(Context &context, std::unique_ptr<State> &state, rust::Vec<Token> &tokens) {
+  for (const auto &token : tokens) {
+    if (token.status != TokenStatus::Ready) {
+        return false;
+      }
+  }
+
+  return true;
+}
+
+rust::Vec<Output>
+run(Context &context, std::unique_ptr<State> &state, rust::Vec<Input> inputs) {
+  rust::Vec<Output> results;
+
+  for (const auto &input : inputs) {
+    if (input.port != ports::input::number) {
+      continue;
+    }
+
+    std::uint64_t number = codec::decode<std::uint64_t>(payload(input));
+    //
+    // Exported only when tracing is enabled in the configuration.
+    //
+    trace::Scope<Context> scope(context, "fizzbuzz");
+    Output output{};
+    output.port = ports::output::fizz;
+    output.data = codec::encode(state->fizzbuzz(number));
+    results.push_back(std::move(output));
+  }
+
+  return results;
+}
+} // namespace flow
+} // namespace zenoh
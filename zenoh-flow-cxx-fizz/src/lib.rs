@@ -0,0 +1,135 @@
+#[cxx::bridge(namespace = "zenoh::flow")]
+pub mod ffi {
+
+    // Shared structures between Rust and C++
+    pub struct Context {
+        pub mode: usize,
+        pub trace_parent: String,
+        pub spans: Vec<Span>,
+    }
+
+    pub struct Span {
+        pub name: String,
+        pub start: u64,
+        pub end: u64,
+    }
+
+    pub enum TokenStatus {
+        Pending,
+        Ready,
+        DeadlineMiss,
+    }
+
+    pub enum TokenAction {
+        Consume,
+        Drop,
+        Keep,
+        Postpone,
+        Wait,
+    }
+
+    pub struct Token {
+        pub status: TokenStatus,
+        pub action: TokenAction,
+        pub port_id: String,
+        /// The payload, encoding and metadata of the message, read on demand.
+        pub payload: Box<Payload>,
+        pub timestamp: u64,
+        /// Messages waiting on the port for their batch to be complete, in batched mode.
+        pub pending: u64,
+        /// Handle of the port, see `zenoh_flow_cxx_common::ports::Ports`.
+        pub port: u32,
+    }
+
+    pub struct Input {
+        pub port_id: String,
+        pub data: Vec<u8>,
+        /// The payload, unless the wrapper decompressed it into `data`: `data` is then empty.
+        pub shared: Box<Shared>,
+        pub encoding: String,
+        pub metadata: Vec<Attribute>,
+        pub timestamp: u64,
+        /// Handle of the port, see `zenoh_flow_cxx_common::ports::Ports`.
+        pub port: u32,
+        /// Handle of the Rust value, see `zenoh_flow_cxx_common::opaque`.
+        pub handle: u64,
+    }
+
+    pub struct Output {
+        pub port_id: String,
+        pub data: Vec<u8>,
+        pub encoding: String,
+        pub metadata: Vec<Attribute>,
+        /// Handle of the port, used if `port_id` is empty.
+        pub port: u32,
+        /// Handle of an opaque input to forward, instead of `data`.
+        pub handle: u64,
+    }
+
+    pub struct Data {
+        pub bytes: Vec<u8>,
+        pub encoding: String,
+        pub compression: String,
+        pub metadata: Vec<Attribute>,
+        /// Sent in an envelope, see `zenoh_flow_cxx_common::envelope`.
+        pub framed: bool,
+    }
+
+    pub struct Attribute {
+        pub key: String,
+        pub value: String,
+    }
+
+    pub struct Configuration {
+        pub key: String,
+        pub value: String,
+    }
+
+    pub struct ConfigurationMap {
+        pub map: Vec<Configuration>,
+    }
+
+    /// How the state may be used across threads, see `zenoh_flow_cxx_common::threading`.
+    pub enum Threading {
+        Affine,
+        Send,
+        Concurrent,
+    }
+
+    unsafe extern "C++" {
+        include!("zenoh-flow-cxx-fizz/cpp/include/fizz.hpp");
+
+        type State;
+
+        fn threading(configuration: &ConfigurationMap) -> Threading;
+        fn initialize(configuration: &ConfigurationMap) -> UniquePtr<State>;
+
+        fn input_rule(
+            context: &mut Context,
+            state: &mut UniquePtr<State>,
+            tokens: &mut Vec<Token>,
+        ) -> Result<bool>;
+
+        fn run(
+            context: &mut Context,
+            state: &mut UniquePtr<State>,
+            inputs: Vec<Input>,
+        ) -> Result<Vec<Output>>;
+    }
+
+    extern "Rust" {
+        fn allocate(size: usize) -> Vec<u8>;
+
+        type Payload;
+
+        fn data(self: &Payload) -> Result<&[u8]>;
+        fn encoding(self: &Payload) -> Result<&str>;
+        fn metadata(self: &Payload) -> Result<Vec<Attribute>>;
+
+        type Shared;
+
+        fn data(self: &Shared) -> Result<&[u8]>;
+    }
+}
+
+zenoh_flow_cxx_common::export_cxx_operator!(ffi);
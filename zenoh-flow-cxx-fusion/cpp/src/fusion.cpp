@@ -0,0 +1,141 @@
+#include "zenoh-flow-cxx-fusion/cpp/include/fusion.hpp"
+#include "zenoh-flow-cxx-fusion/src/lib.rs.h"
+#include <algorithm>
+#include <cctype>
+#include <cstdint>
+#include <cstring>
+#include <memory>
+#include <string>
+#include <vector>
+
+namespace zenoh {
+namespace flow {
+
+//
+// `number` (u64) -> `fizz` (string).
+//
+class FizzBuzz : public fusion::Stage {
+public:
+  std::vector<fusion::Message> run(std::vector<fusion::Message> inputs) override {
+    std::vector<fusion::Message> outputs;
+    for (auto &input : inputs) {
+      if (input.port_id != "number") {
+        continue;
+      }
+
+      std::uint64_t number = codec::decode<std::uint64_t>(input.data);
+      std::string result;
+      if (number % 3 == 0) {
+        result += "Fizz";
+      }
+      if (number % 5 == 0) {
+        result += "Buzz";
+      }
+      if (result.empty()) {
+        result = std::to_string(number);
+      }
+
+      input.port_id = "fizz";
+      input.data = codec::encode(result);
+      outputs.push_back(std::move(input));
+    }
+    return outputs;
+  }
+};
+
+//
+// `fizz` (string) -> `fizz` (string), in upper case and followed by `suffix`.
+//
+class Shout : public fusion::Stage {
+public:
+  Shout(const fusion::Configuration &configuration) {
+    auto entry = configuration.find("suffix");
+    if (entry != configuration.end()) {
+      suffix = entry->second;
+    }
+  }
+
+  std::vector<fusion::Message> run(std::vector<fusion::Message> inputs) override {
+    for (auto &input : inputs) {
+      std::string text = codec::decode<std::string>(input.data);
+      std::transform(text.begin(), text.end(), text.begin(),
+                     [](unsigned char c) { return std::toupper(c); });
+      input.data = codec::encode(text + suffix);
+    }
+    return inputs;
+  }
+
+private:
+  std::string suffix;
+};
+
+//
+// Any port -> `to`, the messages untouched: opaque inputs stay opaque.
+//
+class Relay : public fusion::Stage {
+public:
+  Relay(const fusion::Configuration &configuration) {
+    auto entry = configuration.find("to");
+    if (entry != configuration.end()) {
+      to = entry->second;
+    }
+  }
+
+  std::vector<fusion::Message> run(std::vector<fusion::Message> inputs) override {
+    for (auto &input : inputs) {
+      if (!to.empty()) {
+        input.port_id = to;
+      }
+    }
+    return inputs;
+  }
+
+private:
+  std::string to;
+};
+
+const std::map<std::string, fusion::Factory> stages {
+  { "fizzbuzz", [](const fusion::Configuration &configuration) {
+      return std::unique_ptr<fusion::Stage>(new FizzBuzz());
+    } },
+  { "shout", [](const fusion::Configuration &configuration) {
+      return std::unique_ptr<fusion::Stage>(new Shout(configuration));
+    } },
+  { "relay", [](const fusion::Configuration &configuration) {
+      return std::unique_ptr<fusion::Stage>(new Relay(configuration));
+    } },
+};
+
+State::State(const ConfigurationMap &configuration)
+  : chain(stages, configuration) {}
+
+Threading
+threading(const ConfigurationMap &configuration) {
+  return Threading::Send;
+}
+
+std::unique_ptr<State>
+initialize(const ConfigurationMap &configuration) {
+  //
+  // /!\ NOTE: `make_unique` requires "c++14"
+  //
+  return std::make_unique<State>(configuration);
+}
+
+bool
+input_rule(Context &context, std::unique_ptr<State> &state, rust::Vec<Token> &tokens) {
+  for (const auto &token : tokens) {
+    if (token.status != TokenStatus::Ready) {
+        return false;
+      }
+  }
+
+  return true;
+}
+
+rust::Vec<Output>
+run(Context &context, std::unique_ptr<State> &state, rust::Vec<Input> inputs) {
+  return state->chain.run<Output>(context, std::move(inputs));
+}
+} // namespace flow
+} // namespace zenoh
@@ -0,0 +1,653 @@
+use serde_yaml::{Mapping, Value};
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    fmt,
+    path::{Path, PathBuf},
+};
+use zenoh_flow_cxx_common::{
+    envelope::{ENVELOPE_INPUTS, ENVELOPE_OUTPUTS},
+    instance::INSTANCE,
+    ports::{INPUT_PREFIX, OUTPUT_PREFIX},
+};
+
+/// Graph descriptors templates of the workspace.
+pub const GRAPHS_DIR: &str = "graphs";
+
+/// Configuration key selecting a component of a suite, see `zenoh_flow_suite.hpp`.
+pub const COMPONENT: &str = "component";
+
+#[derive(Debug)]
+pub enum Error {
+    IO(PathBuf, std::io::Error),
+    UnknownPlaceholder(String),
+    UnclosedPlaceholder(usize),
+    Yaml(serde_yaml::Error),
+    InvalidDescriptor(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::IO(path, e) => write!(f, "{}: {}", path.display(), e),
+            Error::UnknownPlaceholder(name) => write!(f, "Unknown placeholder: {{{{ {} }}}}", name),
+            Error::UnclosedPlaceholder(offset) => {
+                write!(f, "Unclosed placeholder starting at byte {}", offset)
+            }
+            Error::Yaml(e) => write!(f, "{}", e),
+            Error::InvalidDescriptor(reason) => write!(f, "Invalid descriptor: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+pub fn workspace_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .expect("The graphs crate lives inside the workspace")
+        .to_path_buf()
+}
+
+/// The values substituted to the `{{ name }}` placeholders of a graph template.
+///
+/// - `target_dir`: the cargo `target` directory of the workspace (or `CARGO_TARGET_DIR`, relative
+///   to the workspace if it is not absolute), as an absolute path,
+/// - `profile`: `debug` or `release`,
+/// - `dylib_prefix`: `lib` on Linux and macOS, empty on Windows,
+/// - `dylib_extension`: `so` on Linux, `dylib` on macOS, `dll` on Windows.
+#[derive(Debug, Clone)]
+pub struct Variables {
+    values: HashMap<String, String>,
+}
+
+impl Default for Variables {
+    fn default() -> Self {
+        Self::new()
+            .set("target_dir", &target_dir().display().to_string())
+            .set("profile", "debug")
+            .set("dylib_prefix", std::env::consts::DLL_PREFIX)
+            .set("dylib_extension", std::env::consts::DLL_EXTENSION)
+    }
+}
+
+/// The cargo `target` directory of the workspace, absolute so that it can be used in a `file://`
+/// URI.
+pub fn target_dir() -> PathBuf {
+    target_dir_from(std::env::var_os("CARGO_TARGET_DIR"))
+}
+
+/// The cargo `target` directory of the workspace, given the value of `CARGO_TARGET_DIR`.
+fn target_dir_from(cargo_target_dir: Option<OsString>) -> PathBuf {
+    let target_dir = match cargo_target_dir {
+        Some(dir) => workspace_dir().join(dir),
+        None => workspace_dir().join("target"),
+    };
+    // Canonicalizing fails if the directory does not exist yet: the path is already absolute.
+    target_dir.canonicalize().unwrap_or(target_dir)
+}
+
+impl Variables {
+    pub fn new() -> Self {
+        Self {
+            values: HashMap::new(),
+        }
+    }
+
+    pub fn set(mut self, name: &str, value: &str) -> Self {
+        self.values.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(|value| value.as_str())
+    }
+}
+
+/// Replaces every `{{ name }}` of `template` with its value in `variables`.
+pub fn render(template: &str, variables: &Variables) -> Result<String> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    let mut offset = 0;
+
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find("}}")
+            .ok_or(Error::UnclosedPlaceholder(offset + start))?;
+        let name = rest[start + 2..start + end].trim();
+        let value = variables
+            .get(name)
+            .ok_or_else(|| Error::UnknownPlaceholder(name.to_string()))?;
+        rendered.push_str(value);
+
+        offset += start + end + 2;
+        rest = &rest[start + end + 2..];
+    }
+    rendered.push_str(rest);
+
+    Ok(rendered)
+}
+
+/// Reads and renders the template at `path`.
+pub fn render_file(path: &Path, variables: &Variables) -> Result<String> {
+    let template = std::fs::read_to_string(path).map_err(|e| Error::IO(path.to_path_buf(), e))?;
+    render(&template, variables)
+}
+
+/// The sections declaring the ports of each kind of component, and the configuration key prefix
+/// of their ports.
+const KINDS: [(&str, &[(&str, &str)]); 3] = [
+    ("sources", &[("output", OUTPUT_PREFIX)]),
+    (
+        "operators",
+        &[("inputs", INPUT_PREFIX), ("outputs", OUTPUT_PREFIX)],
+    ),
+    ("sinks", &[("input", INPUT_PREFIX)]),
+];
+
+/// Returns the ports of `component`, as `(prefix, id, type)`.
+fn component_ports(
+    kind: &str,
+    component: &Value,
+    sections: &[(&str, &'static str)],
+) -> Result<Vec<(&'static str, String, String)>> {
+    let mut declarations = Vec::new();
+    for (section, prefix) in sections.iter() {
+        // Sources and sinks have a single port, operators a list of ports.
+        let ports = match component.get(*section) {
+            Some(Value::Sequence(ports)) => ports.clone(),
+            Some(port) => vec![port.clone()],
+            None => continue,
+        };
+
+        for port in ports {
+            let id = port.get("id").and_then(|id| id.as_str());
+            let port_type = port.get("type").and_then(|port_type| port_type.as_str());
+            match (id, port_type) {
+                (Some(id), Some(port_type)) => {
+                    declarations.push((*prefix, id.to_string(), port_type.to_string()))
+                }
+                _ => {
+                    return Err(Error::InvalidDescriptor(format!(
+                        "a port of {} misses its `id` or its `type`",
+                        kind
+                    )))
+                }
+            }
+        }
+    }
+
+    Ok(declarations)
+}
+
+/// Copies the ports declared by each component into its configuration, and its id as `instance`
+/// unless it is set. The fragment of a URI, `<library>#<component>`, is removed and set as
+/// `component` unless it is set.
+///
+/// The wrappers of the C++ components only receive their configuration: this is how they learn
+/// the ports, and their types, the descriptor declares, and which component they are.
+///
+/// Both ends of every link must agree on whether it carries envelopes.
+pub fn declare_ports(descriptor: &str) -> Result<String> {
+    let mut descriptor: Value = serde_yaml::from_str(descriptor).map_err(Error::Yaml)?;
+
+    for (kind, sections) in KINDS.iter() {
+        let components = match descriptor
+            .get_mut(*kind)
+            .and_then(|components| components.as_sequence_mut())
+        {
+            Some(components) => components,
+            None => continue,
+        };
+
+        for component in components.iter_mut() {
+            let declarations = component_ports(kind, component, sections)?;
+            let id = component.get("id").cloned();
+            let fragment = match component.get_mut("uri") {
+                Some(Value::String(uri)) => match uri.find('#') {
+                    Some(index) => {
+                        let fragment = uri[index + 1..].to_string();
+                        uri.truncate(index);
+                        Some(fragment)
+                    }
+                    None => None,
+                },
+                _ => None,
+            };
+
+            let mapping = match component {
+                Value::Mapping(mapping) => mapping,
+                _ => {
+                    return Err(Error::InvalidDescriptor(format!(
+                        "{} must be a list of mappings",
+                        kind
+                    )))
+                }
+            };
+            let configuration = mapping
+                .entry(Value::from("configuration"))
+                .or_insert_with(|| Value::Mapping(Mapping::new()));
+            if let Value::Null = configuration {
+                *configuration = Value::Mapping(Mapping::new());
+            }
+            let configuration = configuration.as_mapping_mut().ok_or_else(|| {
+                Error::InvalidDescriptor("`configuration` must be a mapping".to_string())
+            })?;
+            for (prefix, id, port_type) in declarations {
+                configuration.insert(
+                    Value::from(format!("{}{}", prefix, id)),
+                    Value::from(port_type),
+                );
+            }
+            if let Some(id) = id {
+                configuration.entry(Value::from(INSTANCE)).or_insert(id);
+            }
+            if let Some(fragment) = fragment {
+                configuration
+                    .entry(Value::from(COMPONENT))
+                    .or_insert_with(|| Value::from(fragment));
+            }
+        }
+    }
+
+    check_framing(&descriptor)?;
+    serde_yaml::to_string(&descriptor).map_err(Error::Yaml)
+}
+
+/// Returns the component and the port of an end of a link, written `<component>.<port>` or as a
+/// mapping of `component` and `port_key`.
+fn link_end<'a>(end: &'a Value, port_key: &str) -> Result<(&'a str, &'a str)> {
+    let parsed = match end {
+        Value::String(end) => end.split_once('.'),
+        _ => end
+            .get("component")
+            .and_then(|component| component.as_str())
+            .zip(end.get(port_key).and_then(|port| port.as_str())),
+    };
+    parsed.ok_or_else(|| Error::InvalidDescriptor(format!("invalid end of link: {:?}", end)))
+}
+
+/// Whether the configuration of `component` lists `port` under `key`.
+fn frames(descriptor: &Value, component: &str, key: &str, port: &str) -> bool {
+    let value = KINDS
+        .iter()
+        .filter_map(|(kind, _)| descriptor.get(*kind).and_then(|c| c.as_sequence()))
+        .flatten()
+        .find(|c| c.get("id").and_then(|id| id.as_str()) == Some(component))
+        .and_then(|c| c.get("configuration"))
+        .and_then(|configuration| configuration.get(key))
+        .and_then(|value| value.as_str());
+    match value {
+        Some("true") => true,
+        Some(ports) => ports.split(',').any(|id| id.trim() == port),
+        None => false,
+    }
+}
+
+/// Checks that both ends of every link agree on whether it carries envelopes: the bytes of a
+/// message do not tell.
+fn check_framing(descriptor: &Value) -> Result<()> {
+    let links = match descriptor
+        .get("links")
+        .and_then(|links| links.as_sequence())
+    {
+        Some(links) => links,
+        None => return Ok(()),
+    };
+
+    for link in links {
+        let (from, output) = link_end(&link["from"], "output")?;
+        let (to, input) = link_end(&link["to"], "input")?;
+        let sends = frames(descriptor, from, ENVELOPE_OUTPUTS, output);
+        let receives = frames(descriptor, to, ENVELOPE_INPUTS, input);
+        if sends != receives {
+            return Err(Error::InvalidDescriptor(format!(
+                "{}.{} {} envelopes but {}.{} {}, see `{}` and `{}`",
+                from,
+                output,
+                if sends { "sends" } else { "does not send" },
+                to,
+                input,
+                if receives { "expects them" } else { "does not" },
+                ENVELOPE_OUTPUTS,
+                ENVELOPE_INPUTS
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Turns a port id into a C++ identifier.
+fn identifier(id: &str) -> String {
+    let mut identifier: String = id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if identifier.starts_with(|c: char| c.is_ascii_digit()) {
+        identifier.insert(0, '_');
+    }
+    identifier
+}
+
+/// Returns, for each component of `descriptor`, its id and a C++ header defining the handles of
+/// its ports, as assigned by the wrappers (see `zenoh_flow_cxx_common::ports::Ports`):
+///
+/// ```cpp
+/// namespace zenoh { namespace flow { namespace ports {
+/// namespace input { constexpr std::uint32_t number = 0; }
+/// namespace output { constexpr std::uint32_t fizz = 0; }
+/// } } }
+/// ```
+pub fn port_headers(descriptor: &str) -> Result<Vec<(String, String)>> {
+    let descriptor: Value = serde_yaml::from_str(descriptor).map_err(Error::Yaml)?;
+    let flow = descriptor
+        .get("flow")
+        .and_then(|flow| flow.as_str())
+        .unwrap_or_default();
+
+    let mut headers = Vec::new();
+    for (kind, sections) in KINDS.iter() {
+        let components = match descriptor
+            .get(*kind)
+            .and_then(|components| components.as_sequence())
+        {
+            Some(components) => components,
+            None => continue,
+        };
+
+        for component in components {
+            let id = component
+                .get("id")
+                .and_then(|id| id.as_str())
+                .ok_or_else(|| {
+                    Error::InvalidDescriptor(format!("a component of {} misses its `id`", kind))
+                })?;
+            let ports = component_ports(kind, component, sections)?;
+
+            let mut header = format!(
+                "// Generated by zenoh-flow-cxx-graphs from the descriptor of {}: do not edit.\n\
+                 // Handles of the ports of {}, see `Input::port` and `Output::port`.\n\
+                 #pragma once\n\
+                 #include <cstdint>\n\n\
+                 namespace zenoh {{\n\
+                 namespace flow {{\n\
+                 namespace ports {{\n",
+                flow, id
+            );
+            for (namespace, prefix) in [("input", INPUT_PREFIX), ("output", OUTPUT_PREFIX)] {
+                let mut ids: Vec<&str> = ports
+                    .iter()
+                    .filter(|(port_prefix, _, _)| *port_prefix == prefix)
+                    .map(|(_, id, _)| id.as_str())
+                    .collect();
+                if ids.is_empty() {
+                    continue;
+                }
+                ids.sort_unstable();
+
+                header.push_str(&format!("namespace {} {{\n", namespace));
+                for (handle, id) in ids.iter().enumerate() {
+                    header.push_str(&format!(
+                        "constexpr std::uint32_t {} = {};\n",
+                        identifier(id),
+                        handle
+                    ));
+                }
+                header.push_str(&format!("}} // namespace {}\n", namespace));
+            }
+            header.push_str("} // namespace ports\n} // namespace flow\n} // namespace zenoh\n");
+
+            headers.push((id.to_string(), header));
+        }
+    }
+
+    Ok(headers)
+}
+
+/// Renders the template at `path` and declares the ports of its components.
+pub fn generate(path: &Path, variables: &Variables) -> Result<String> {
+    declare_ports(&render_file(path, variables)?)
+}
+
+/// Returns the path of the template of the graph `name` (without the `.yaml` extension).
+pub fn template_path(name: &str) -> PathBuf {
+    workspace_dir()
+        .join(GRAPHS_DIR)
+        .join(format!("{}.yaml", name))
+}
+
+/// Returns the paths of all the graph templates of the workspace.
+pub fn templates() -> Result<Vec<PathBuf>> {
+    let dir = workspace_dir().join(GRAPHS_DIR);
+    let entries = std::fs::read_dir(&dir).map_err(|e| Error::IO(dir.clone(), e))?;
+
+    let mut templates = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(|e| Error::IO(dir.clone(), e))?.path();
+        if path.extension().and_then(|ext| ext.to_str()) == Some("yaml") {
+            templates.push(path);
+        }
+    }
+    templates.sort();
+
+    Ok(templates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DESCRIPTOR: &str = "
+flow: Framing
+sources:
+  - id: Source
+    uri: file://libsource.so
+    output:
+      id: number
+      type: u64
+    configuration:
+      envelope_outputs: {outputs}
+sinks:
+  - id: Sink
+    uri: file://libsink.so
+    input:
+      id: number
+      type: u64
+    configuration:
+      envelope_inputs: {inputs}
+links:
+  - from: Source.number
+    to:
+      component: Sink
+      input: number
+";
+
+    fn descriptor(outputs: &str, inputs: &str) -> String {
+        DESCRIPTOR
+            .replace("{outputs}", outputs)
+            .replace("{inputs}", inputs)
+    }
+
+    #[test]
+    fn links_agree_on_envelopes() {
+        assert!(declare_ports(&descriptor("\"true\"", "number")).is_ok());
+        assert!(declare_ports(&descriptor("\"false\"", "other")).is_ok());
+    }
+
+    #[test]
+    fn links_disagreeing_on_envelopes_are_refused() {
+        assert!(matches!(
+            declare_ports(&descriptor("\"true\"", "\"false\"")),
+            Err(Error::InvalidDescriptor(_))
+        ));
+        assert!(matches!(
+            declare_ports(&descriptor("other", "number")),
+            Err(Error::InvalidDescriptor(_))
+        ));
+    }
+
+    const OPERATOR: &str = "
+flow: Ports
+operators:
+  - id: CxxFizz
+    uri: file://libsuite.so#fizzbuzz
+    inputs:
+      - id: tick
+        type: u64
+      - id: number
+        type: u64
+    outputs:
+      - id: fizz-buzz
+        type: string
+    configuration:
+sinks:
+  - id: CxxSink
+    uri: file://libsink.so
+    input:
+      id: fizz-buzz
+      type: string
+    configuration:
+      instance: Printer
+      component: custom
+";
+
+    fn configuration(descriptor: &Value, kind: &str) -> Mapping {
+        descriptor[kind][0]["configuration"]
+            .as_mapping()
+            .unwrap()
+            .clone()
+    }
+
+    fn entry<'a>(configuration: &'a Mapping, key: &str) -> Option<&'a str> {
+        configuration
+            .get(&Value::from(key))
+            .and_then(|value| value.as_str())
+    }
+
+    #[test]
+    fn placeholders_are_replaced() {
+        let variables = Variables::new()
+            .set("target_dir", "/target")
+            .set("profile", "debug");
+        assert_eq!(
+            render("file://{{ target_dir }}/{{profile}}/lib.so", &variables).unwrap(),
+            "file:///target/debug/lib.so"
+        );
+        assert_eq!(
+            render("no placeholder", &variables).unwrap(),
+            "no placeholder"
+        );
+    }
+
+    #[test]
+    fn invalid_placeholders_are_refused() {
+        let variables = Variables::new().set("profile", "debug");
+        assert!(matches!(
+            render("{{ profile }} {{ unknown }}", &variables),
+            Err(Error::UnknownPlaceholder(name)) if name == "unknown"
+        ));
+        assert!(matches!(
+            render("{{ profile }} {{ profile", &variables),
+            Err(Error::UnclosedPlaceholder(14))
+        ));
+    }
+
+    #[test]
+    fn ports_are_declared_in_the_configuration() {
+        let declared: Value = serde_yaml::from_str(&declare_ports(OPERATOR).unwrap()).unwrap();
+
+        let operator = configuration(&declared, "operators");
+        assert_eq!(entry(&operator, "input/tick"), Some("u64"));
+        assert_eq!(entry(&operator, "input/number"), Some("u64"));
+        assert_eq!(entry(&operator, "output/fizz-buzz"), Some("string"));
+        assert_eq!(entry(&operator, INSTANCE), Some("CxxFizz"));
+        assert_eq!(entry(&operator, COMPONENT), Some("fizzbuzz"));
+        assert_eq!(declared["operators"][0]["uri"], "file://libsuite.so");
+
+        // What the descriptor sets is kept.
+        let sink = configuration(&declared, "sinks");
+        assert_eq!(entry(&sink, "input/fizz-buzz"), Some("string"));
+        assert_eq!(entry(&sink, INSTANCE), Some("Printer"));
+        assert_eq!(entry(&sink, COMPONENT), Some("custom"));
+    }
+
+    #[test]
+    fn ports_without_type_are_refused() {
+        let descriptor = OPERATOR.replace("        type: string\n", "");
+        assert!(matches!(
+            declare_ports(&descriptor),
+            Err(Error::InvalidDescriptor(_))
+        ));
+        assert!(matches!(
+            port_headers(&descriptor),
+            Err(Error::InvalidDescriptor(_))
+        ));
+    }
+
+    #[test]
+    fn headers_give_the_handles_of_the_ports() {
+        let headers = port_headers(OPERATOR).unwrap();
+        let ids: Vec<&str> = headers.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, ["CxxFizz", "CxxSink"]);
+
+        // Sorted by id, as the wrappers do.
+        let operator = &headers[0].1;
+        assert!(operator.contains("descriptor of Ports"));
+        assert!(operator.contains(
+            "namespace input {\n\
+             constexpr std::uint32_t number = 0;\n\
+             constexpr std::uint32_t tick = 1;\n\
+             } // namespace input\n"
+        ));
+        assert!(operator.contains("constexpr std::uint32_t fizz_buzz = 0;"));
+
+        let sink = &headers[1].1;
+        assert!(!sink.contains("namespace output"));
+    }
+
+    #[test]
+    fn identifiers_are_valid_in_cxx() {
+        assert_eq!(identifier("fizz"), "fizz");
+        assert_eq!(identifier("fizz-buzz.0"), "fizz_buzz_0");
+        assert_eq!(identifier("3d"), "_3d");
+    }
+
+    #[test]
+    fn target_dirs_are_absolute() {
+        assert_eq!(target_dir_from(None), workspace_dir().join("target"));
+        assert_eq!(
+            target_dir_from(Some("relative-target".into())),
+            workspace_dir().join("relative-target")
+        );
+        let absolute = std::env::temp_dir().canonicalize().unwrap();
+        assert_eq!(target_dir_from(Some(absolute.clone().into())), absolute);
+    }
+
+    #[test]
+    fn templates_render_to_absolute_uris() {
+        let target_dir = target_dir_from(Some("relative-target".into()));
+        let variables = Variables::default().set("target_dir", &target_dir.display().to_string());
+        let library = format!(
+            "file://{}/debug/{}zenoh_flow_cxx_",
+            target_dir.display(),
+            std::env::consts::DLL_PREFIX
+        );
+
+        let templates = templates().unwrap();
+        assert!(!templates.is_empty());
+        for template in templates {
+            let descriptor: Value = serde_yaml::from_str(&generate(&template, &variables).unwrap())
+                .unwrap_or_else(|e| panic!("{}: {}", template.display(), e));
+            for (kind, _) in KINDS.iter() {
+                for component in descriptor[*kind].as_sequence().into_iter().flatten() {
+                    let uri = component["uri"].as_str().unwrap();
+                    assert!(uri.starts_with(&library), "{}: {}", template.display(), uri);
+                }
+            }
+        }
+    }
+}
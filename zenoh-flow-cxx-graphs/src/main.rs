@@ -0,0 +1,88 @@
+use std::path::PathBuf;
+use zenoh_flow_cxx_graphs::{generate, port_headers, templates, Error, Variables};
+
+const USAGE: &str = "\
+Renders the graph templates of `graphs/` into descriptors usable on this machine.
+The ports of each component are copied into its configuration.
+
+USAGE:
+    zenoh-flow-cxx-graphs [--release] [--output <DIR>] [--headers <DIR>] [TEMPLATE...]
+
+OPTIONS:
+    --release         Point to the libraries built with `--release`.
+    --output <DIR>    Where to write the descriptors [default: <target_dir>/graphs].
+    --headers <DIR>   Also write the handles of the ports of each component, as C++
+                      constants, to `<DIR>/<component id>_ports.hpp`.
+
+Without TEMPLATE, every `graphs/*.yaml` is rendered.";
+
+fn main() {
+    if let Err(e) = try_main() {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn try_main() -> Result<(), Error> {
+    let mut variables = Variables::default();
+    let mut output: Option<PathBuf> = None;
+    let mut headers: Option<PathBuf> = None;
+    let mut paths = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--release" => variables = variables.set("profile", "release"),
+            "--output" => match args.next() {
+                Some(dir) => output = Some(PathBuf::from(dir)),
+                None => {
+                    eprintln!("{}", USAGE);
+                    std::process::exit(1);
+                }
+            },
+            "--headers" => match args.next() {
+                Some(dir) => headers = Some(PathBuf::from(dir)),
+                None => {
+                    eprintln!("{}", USAGE);
+                    std::process::exit(1);
+                }
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            path => paths.push(PathBuf::from(path)),
+        }
+    }
+
+    if paths.is_empty() {
+        paths = templates()?;
+    }
+
+    let output = output.unwrap_or_else(|| {
+        PathBuf::from(variables.get("target_dir").unwrap_or("target")).join("graphs")
+    });
+    std::fs::create_dir_all(&output).map_err(|e| Error::IO(output.clone(), e))?;
+    if let Some(headers) = &headers {
+        std::fs::create_dir_all(headers).map_err(|e| Error::IO(headers.clone(), e))?;
+    }
+
+    for path in paths {
+        let descriptor = generate(&path, &variables)?;
+        let file_name = path.file_name().map(PathBuf::from).unwrap_or_default();
+        let destination = output.join(file_name);
+        std::fs::write(&destination, &descriptor).map_err(|e| Error::IO(destination.clone(), e))?;
+        println!("{}", destination.display());
+
+        if let Some(headers) = &headers {
+            for (component, header) in port_headers(&descriptor)? {
+                let destination = headers.join(format!("{}_ports.hpp", component));
+                std::fs::write(&destination, header)
+                    .map_err(|e| Error::IO(destination.clone(), e))?;
+                println!("{}", destination.display());
+            }
+        }
+    }
+
+    Ok(())
+}
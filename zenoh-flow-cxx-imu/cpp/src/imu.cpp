@@ -0,0 +1,102 @@
+#include "zenoh-flow-cxx-imu/cpp/include/imu.hpp"
+#include "zenoh-flow-cxx-imu/src/lib.rs.h"
+#include <cmath>
+#include <cstdint>
+#include <memory>
+#include <string>
+#include <vector>
+#ifdef ZENOH_FLOW_FLATBUFFERS
+#include "sensors_generated.h"
+#endif
+
+namespace zenoh {
+namespace flow {
+
+#ifdef ZENOH_FLOW_FLATBUFFERS
+//
+// `number` (u64) -> `imu` (flatbuffers:sensors.Imu), the reading
+// (n, 2n, 2n) built in place in the bytes of the output.
+//
+class Imu : public suite::Operator {
+public:
+  bool input_rule(Context &context, rust::Vec<Token> &tokens) override {
+    return suite::all_ready(tokens);
+  }
+
+  rust::Vec<Output> run(Context &context, rust::Vec<Input> inputs) override {
+    rust::Vec<Output> outputs;
+    for (const auto &input : inputs) {
+      double number = static_cast<double>(codec::decode<std::uint64_t>(payload(input)));
+      flatbuffers::Builder builder;
+      auto imu = sensors::CreateImu(builder.fbb(), number, 2 * number, 2 * number);
+      outputs.push_back(Output { "imu", builder.finish(imu) });
+    }
+    return outputs;
+  }
+};
+
+//
+// `imu` (flatbuffers:sensors.Imu) -> `magnitude` (f64), the reading viewed in
+// place in the received message.
+//
+class Magnitude : public suite::Operator {
+public:
+  bool input_rule(Context &context, rust::Vec<Token> &tokens) override {
+    return suite::all_ready(tokens);
+  }
+
+  rust::Vec<Output> run(Context &context, rust::Vec<Input> inputs) override {
+    rust::Vec<Output> outputs;
+    for (const auto &input : inputs) {
+      auto imu = flatbuffers::view<sensors::Imu>(payload(input));
+      double magnitude = std::sqrt(imu->x() * imu->x() + imu->y() * imu->y() +
+                                   imu->z() * imu->z());
+      outputs.push_back(Output { "magnitude", codec::encode(magnitude) });
+    }
+    return outputs;
+  }
+};
+#endif
+
+//
+// The operators need the schemas of `fbs/`: without the `flatbuffers` feature
+// the suite is empty, and refuses any `component`.
+//
+const std::map<std::string, suite::Factory> operators {
+#ifdef ZENOH_FLOW_FLATBUFFERS
+  { "imu", [](const ConfigurationMap &configuration) {
+      return std::unique_ptr<suite::Operator>(new Imu());
+    } },
+  { "magnitude", [](const ConfigurationMap &configuration) {
+      return std::unique_ptr<suite::Operator>(new Magnitude());
+    } },
+#endif
+};
+
+State::State(const ConfigurationMap &configuration)
+  : suite(operators, configuration) {}
+
+Threading
+threading(const ConfigurationMap &configuration) {
+  return Threading::Send;
+}
+
+std::unique_ptr<State>
+initialize(const ConfigurationMap &configuration) {
+  //
+  // /!\ NOTE: `make_unique` requires "c++14"
+  //
+  return std::make_unique<State>(configuration);
+}
+
+bool
+input_rule(Context &context, std::unique_ptr<State> &state, rust::Vec<Token> &tokens) {
+  return state->suite.input_rule(context, tokens);
+}
+
+rust::Vec<Output>
+run(Context &context, std::unique_ptr<State> &state, rust::Vec<Input> inputs) {
+  return state->suite.run(context, std::move(inputs));
+}
+} // namespace flow
+} // namespace zenoh
@@ -0,0 +1,28 @@
+#pragma once
+#include <algorithm>
+#include <cstdint>
+#include <memory>
+#include <vector>
+#include "rust/cxx.h"
+#include "zenoh-flow-cxx-manual-source/../include/zenoh_flow.hpp"
+
+namespace zenoh {
+namespace flow {
+
+class State {
+private:
+  bool interactive;
+  std::uint64_t number;
+public:
+  State (bool interactive, std::uint64_t start);
+  bool isInteractive ();
+  std::uint64_t nextNumber ();
+};
+
+Threading threading(const ConfigurationMap &configuration);
+std::unique_ptr<State>
+initialize(const ConfigurationMap &configuration);
+rust::Vec<Output> run(Context &context, std::unique_ptr<State> &state);
+
+} // namespace flow
+} // namespace zenoh
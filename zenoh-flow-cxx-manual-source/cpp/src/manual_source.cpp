@@ -0,0 +1,77 @@
+#include "zenoh-flow-cxx-manual-source/cpp/include/manual_source.hpp"
+#include "zenoh-flow-cxx-manual-source/src/lib.rs.h"
+#include <cstdint>
+#include <cstring>
+#include <memory>
+#include <string>
+#include <vector>
+#include <sstream>
+
+namespace zenoh {
+namespace flow {
+
+State::State(bool interactive, std::uint64_t start)
+    : interactive(interactive), number(start) {}
+
+bool State::isInteractive(void) {
+  return interactive;
+}
+
+std::uint64_t State::nextNumber(void) {
+  number += 1;
+  return number;
+}
+
+Threading
+threading(const ConfigurationMap &configuration) {
+  return Threading::Send;
+}
+
+std::unique_ptr<State>
+initialize(const ConfigurationMap &configuration)
+{
+  //
+  // Setting `interactive` to "false" removes the ENTER prompt: a number is
+  // then produced at every period of the source. The numbers follow `start`,
+  // 0 by default.
+  //
+  bool interactive = true;
+  std::uint64_t start = 0;
+  for (auto entry : configuration.map) {
+    if (std::string(entry.key) == "interactive") {
+      interactive = std::string(entry.value) != "false";
+    } else if (std::string(entry.key) == "start") {
+      start = std::stoull(std::string(entry.value));
+    }
+  }
+
+  //
+  // /!\ NOTE: `make_unique` requires "c++14"
+  //
+  return std::make_unique<State>(interactive, start);
+}
+
+rust::Vec<Output>
+run(Context &context, std::unique_ptr<State> &state)
+{
+  if (state->isInteractive()) {
+    std::string input;
+
+    std::cout << "Press ENTER.";
+    std::getline(std::cin, input);
+    std::cout << std::endl;
+  }
+
+  std::uint64_t number = state->nextNumber();
+  Output output { "number", codec::encode(number) };
+  //
+  // The sequence number travels next to the payload, the sink prints it when
+  // the operators in between forward it (see `forward_metadata`).
+  //
+  metadata::set(output.metadata, "sequence", std::to_string(number));
+
+  rust::Vec<Output> results { output };
+  return results;
+}
+} // namespace flow
+} // namespace zenoh
@@ -1,6 +1,3 @@
 fn main() {
-    cxx_build::bridge("src/lib.rs")
-        .file("cpp/src/operator.cpp")
-        .flag_if_supported("-std=c++14")
-        .compile("cxx_operator");
+    zenoh_flow_cxx_build::component("cpp/src/operator.cpp", "cxx_operator");
 }
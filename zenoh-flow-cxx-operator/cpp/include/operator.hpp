@@ -15,11 +15,16 @@ private:
   std::uint8_t counter;
 public:
   State ();
+  State (std::uint8_t counter);
   void increaseCounter ();
   std::uint8_t getCounter ();
 };
 
+Threading threading(const ConfigurationMap &configuration);
 std::unique_ptr<State> initialize(const ConfigurationMap &configuration);
+rust::Vec<std::uint8_t> snapshot(const std::unique_ptr<State> &state);
+std::unique_ptr<State> restore(const ConfigurationMap &configuration,
+                               rust::Slice<const std::uint8_t> snapshot);
 bool input_rule(Context &context, std::unique_ptr<State> &state,
                 rust::Vec<Token> &tokens);
 rust::Vec<Output> run(Context &context,
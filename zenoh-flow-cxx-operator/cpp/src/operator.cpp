@@ -14,6 +14,8 @@ State::State() {
   counter = 0;
 }
 
+State::State(std::uint8_t counter) : counter(counter) {}
+
 void State::increaseCounter(void) {
   counter += 1;
 }
@@ -22,6 +24,11 @@ std::uint8_t State::getCounter(void) {
   return counter;
 }
 
+Threading
+threading(const ConfigurationMap &configuration) {
+  return Threading::Send;
+}
+
 std::unique_ptr<State>
 initialize(const ConfigurationMap &configuration) {
   //
@@ -30,9 +37,25 @@ initialize(const ConfigurationMap &configuration) {
   return std::make_unique<State>();
 }
 
+rust::Vec<std::uint8_t>
+snapshot(const std::unique_ptr<State> &state) {
+  rust::Vec<std::uint8_t> bytes;
+  codec::encode_into(bytes, state->getCounter());
+  return bytes;
+}
+
+std::unique_ptr<State>
+restore(const ConfigurationMap &configuration,
+        rust::Slice<const std::uint8_t> snapshot) {
+  //
+  // Throws on a corrupted snapshot: the wrapper then calls `initialize`.
+  //
+  return std::make_unique<State>(codec::decode<std::uint8_t>(snapshot));
+}
+
 bool
 input_rule(Context &context, std::unique_ptr<State> &state, rust::Vec<Token> &tokens) {
-  for (auto token : tokens) {
+  for (const auto &token : tokens) {
     if (token.status != TokenStatus::Ready) {
         return false;
       }
@@ -44,8 +67,13 @@ input_rule(Context &context, std::unique_ptr<State> &state, rust::Vec<Token> &to
 rust::Vec<Output>
 run(Context &context, std::unique_ptr<State> &state, rust::Vec<Input> inputs) {
   state->increaseCounter();
-  rust::Vec<std::uint8_t> counter = { state->getCounter() };
-  Output count { "count", counter };
+  //
+  // The buffer comes from the pool of the library when `buffer_pool` is set in
+  // the configuration. It has the exact size of the encoded `u8`, its byte.
+  //
+  rust::Vec<std::uint8_t> bytes = allocate(sizeof(std::uint8_t));
+  bytes[0] = state->getCounter();
+  Output count { "count", std::move(bytes) };
   rust::Vec<Output> results { count };
   return results;
 }
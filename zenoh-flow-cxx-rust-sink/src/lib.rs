@@ -0,0 +1,112 @@
+//! A sink written in Rust, receiving what C++ components send to Rust components.
+//!
+//! Every message is written as a `<port>: <value>` line, its payload decoded according to the
+//! type of its port (see `zenoh_flow_cxx_common::pretty`), to the file `output` of the
+//! configuration or to the standard output.
+
+use async_trait::async_trait;
+use std::{collections::HashMap, fmt::Debug, fs::File, io::Write, sync::Arc};
+use zenoh_flow::{
+    downcast_mut,
+    runtime::message::{DataMessage, SerDeData},
+    Component, Context, InputRule, PortId, Sink, State, Token, ZFError, ZFResult,
+};
+use zenoh_flow_cxx_common::{ports::Ports, pretty::pretty};
+
+/// Configuration key of the file the messages are written to.
+pub const OUTPUT: &str = "output";
+
+pub struct RustSinkState {
+    ports: Ports,
+    output: Option<File>,
+}
+
+impl State for RustSinkState {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_mut_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+impl Debug for RustSinkState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RustSinkState")
+            .field("output", &self.output)
+            .finish_non_exhaustive()
+    }
+}
+
+pub struct RustSink;
+
+impl Component for RustSink {
+    fn initialize(&self, configuration: &Option<HashMap<String, String>>) -> Box<dyn State> {
+        let ports = Ports::from_configuration(configuration.as_ref())
+            .unwrap_or_else(|e| panic!("Invalid port configuration: {:?}", e));
+        let output = configuration
+            .as_ref()
+            .and_then(|configuration| configuration.get(OUTPUT))
+            .map(|path| {
+                File::create(path).unwrap_or_else(|e| panic!("Could not create {}: {}", path, e))
+            });
+
+        Box::new(RustSinkState { ports, output })
+    }
+
+    fn clean(&self, _state: &mut Box<dyn State>) -> ZFResult<()> {
+        Ok(())
+    }
+}
+
+impl InputRule for RustSink {
+    fn input_rule(
+        &self,
+        _context: &mut Context,
+        _state: &mut Box<dyn State>,
+        tokens: &mut HashMap<PortId, Token>,
+    ) -> ZFResult<bool> {
+        Ok(tokens
+            .values()
+            .all(|token| matches!(token, Token::Ready(_))))
+    }
+}
+
+#[async_trait]
+impl Sink for RustSink {
+    async fn run(
+        &self,
+        _context: &mut Context,
+        dyn_state: &mut Box<dyn State>,
+        inputs: &mut HashMap<PortId, DataMessage>,
+    ) -> ZFResult<()> {
+        let state = downcast_mut!(RustSinkState, dyn_state).unwrap();
+
+        let mut port_ids: Vec<&PortId> = inputs.keys().collect();
+        port_ids.sort_unstable();
+        for port_id in port_ids {
+            let port_type = state.ports.input_type(port_id);
+            let value = match &inputs[port_id].data {
+                SerDeData::Serialized(ser) => pretty(port_type, ser),
+                SerDeData::Deserialized(de) => pretty(port_type, &de.try_serialize()?),
+            };
+            let line = format!("{}: {}", port_id, value);
+
+            match state.output.as_mut() {
+                Some(output) => writeln!(output, "{}", line)
+                    .and_then(|_| output.flush())
+                    .map_err(|e| ZFError::IOError(format!("{}", e)))?,
+                None => println!("{}", line),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+zenoh_flow::export_sink!(register);
+
+fn register() -> ZFResult<Arc<dyn Sink>> {
+    Ok(Arc::new(RustSink) as Arc<dyn Sink>)
+}
@@ -1,6 +1,3 @@
 fn main() {
-    cxx_build::bridge("src/lib.rs")
-        .file("cpp/src/sink.cpp")
-        .flag_if_supported("-std=c++14")
-        .compile("cxx_sink");
+    zenoh_flow_cxx_build::component("cpp/src/sink.cpp", "cxx_sink");
 }
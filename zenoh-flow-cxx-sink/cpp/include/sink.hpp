@@ -1,6 +1,9 @@
 #pragma once
 #include <algorithm>
+#include <fstream>
+#include <map>
 #include <memory>
+#include <string>
 #include <vector>
 #include "rust/cxx.h"
 #include <iostream>
@@ -10,10 +13,17 @@ namespace zenoh {
 namespace flow {
 
 class State {
+private:
+  std::ofstream output;
+  std::map<std::string, std::string> types;
 public:
-  State ();
+  State (const std::string &path, const std::map<std::string, std::string> &types);
+  bool hasOutput ();
+  std::ofstream &getOutput ();
+  std::string format (const Input &input);
 };
 
+Threading threading(const ConfigurationMap &configuration);
 std::unique_ptr<State> initialize(const ConfigurationMap &configuration);
 bool input_rule(Context &context, std::unique_ptr<State> &state,
                 rust::Vec<Token> &tokens);
@@ -10,19 +10,65 @@
 namespace zenoh {
 namespace flow {
 
-State::State() {}
+State::State(const std::string &path,
+             const std::map<std::string, std::string> &types) : types(types) {
+  if (!path.empty()) {
+    output.open(path, std::ios::out | std::ios::app);
+  }
+}
+
+bool State::hasOutput(void) {
+  return output.is_open();
+}
+
+std::ofstream &State::getOutput(void) {
+  return output;
+}
+
+//
+// Formats the payload according to the type declared for its port, or else to
+// its encoding, see `pretty_print` on the Rust side: primitive types, `json`
+// and `cbor` payloads are decoded, anything else is formatted as its bytes.
+//
+std::string State::format(const Input &input) {
+  auto type = types.find(std::string(input.port_id));
+  std::string port_type = type != types.end() ? type->second : "";
+  return std::string(pretty_print(port_type, input.encoding, payload(input)));
+}
+
+Threading
+threading(const ConfigurationMap &configuration) {
+  return Threading::Send;
+}
 
 std::unique_ptr<State>
 initialize(const ConfigurationMap &configuration) {
+  //
+  // Setting `output` to a file path appends every received input to that file,
+  // as `<port id>: <payload>`, instead of printing it on the standard output.
+  // The types of the inputs are declared as `input/<port id>: <type>`.
+  //
+  std::string path;
+  std::map<std::string, std::string> types;
+  const std::string input_prefix = "input/";
+  for (auto entry : configuration.map) {
+    std::string key = std::string(entry.key);
+    if (key == "output") {
+      path = std::string(entry.value);
+    } else if (key.compare(0, input_prefix.size(), input_prefix) == 0) {
+      types[key.substr(input_prefix.size())] = std::string(entry.value);
+    }
+  }
+
   //
   // /!\ NOTE: `make_unique` requires "c++14"
   //
-  return std::make_unique<State>();
+  return std::make_unique<State>(path, types);
 }
 
 bool
 input_rule(Context &context, std::unique_ptr<State> &state, rust::Vec<Token> &tokens) {
-  for (auto token : tokens) {
+  for (const auto &token : tokens) {
     if (token.status != TokenStatus::Ready) {
         return false;
       }
@@ -33,13 +79,19 @@ input_rule(Context &context, std::unique_ptr<State> &state, rust::Vec<Token> &to
 
 void
 run(Context &context, std::unique_ptr<State> &state, rust::Vec<Input> inputs) {
-  for (auto input : inputs) {
-    std::cout << "Received on <" << input.port_id << ">: " << std::endl;
-    std::cout << "\t";
-    for (unsigned char c: input.data) {
-      std::cout << unsigned(c);
+  for (const auto &input : inputs) {
+    if (!state->hasOutput()) {
+      std::cout << "Received on <" << input.port_id << ">: " << std::endl;
+      std::cout << "\t" << state->format(input);
+      for (auto attribute : input.metadata) {
+        std::cout << std::endl << "\t" << attribute.key << " = " << attribute.value;
+      }
+      std::cout << std::endl << std::flush;
+    } else {
+      std::ofstream &output = state->getOutput();
+      output << input.port_id << ": " << state->format(input);
+      output << std::endl << std::flush;
     }
-    std::cout << std::endl << std::flush;
   }
 }
 
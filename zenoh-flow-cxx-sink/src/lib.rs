@@ -1,21 +1,17 @@
-use async_trait::async_trait;
-use cxx::UniquePtr;
-use std::{collections::HashMap, fmt::Debug, sync::Arc};
-use zenoh_flow::{
-    downcast_mut,
-    runtime::message::{DataMessage, SerDeData},
-    Component, Context, Data, DowncastAny, InputRule, PortId, Sink, State, Token, TokenAction,
-    ZFError, ZFResult,
-};
-
-extern crate zenoh_flow;
-
 #[cxx::bridge(namespace = "zenoh::flow")]
 pub mod ffi {
 
     // Shared structures between Rust and C++
     pub struct Context {
         pub mode: usize,
+        pub trace_parent: String,
+        pub spans: Vec<Span>,
+    }
+
+    pub struct Span {
+        pub name: String,
+        pub start: u64,
+        pub end: u64,
     }
 
     pub enum TokenStatus {
@@ -36,14 +32,25 @@ pub mod ffi {
         pub status: TokenStatus,
         pub action: TokenAction,
         pub port_id: String,
-        pub data: Vec<u8>,
+        /// The payload, encoding and metadata of the message, read on demand.
+        pub payload: Box<Payload>,
         pub timestamp: u64,
+        /// Messages waiting on the port for their batch to be complete, in batched mode.
+        pub pending: u64,
+        /// Handle of the port, see `zenoh_flow_cxx_common::ports::Ports`.
+        pub port: u32,
     }
 
     pub struct Input {
         pub port_id: String,
         pub data: Vec<u8>,
+        /// The payload, unless the wrapper decompressed it into `data`: `data` is then empty.
+        pub shared: Box<Shared>,
+        pub encoding: String,
+        pub metadata: Vec<Attribute>,
         pub timestamp: u64,
+        /// Handle of the port, see `zenoh_flow_cxx_common::ports::Ports`.
+        pub port: u32,
     }
 
     pub struct Output<'a> {
@@ -53,6 +60,14 @@ pub mod ffi {
 
     pub struct Data {
         pub bytes: Vec<u8>,
+        pub encoding: String,
+        pub compression: String,
+        pub metadata: Vec<Attribute>,
+    }
+
+    pub struct Attribute {
+        pub key: String,
+        pub value: String,
     }
 
     pub struct Configuration {
@@ -64,11 +79,19 @@ pub mod ffi {
         pub map: Vec<Configuration>,
     }
 
+    /// How the state may be used across threads, see `zenoh_flow_cxx_common::threading`.
+    pub enum Threading {
+        Affine,
+        Send,
+        Concurrent,
+    }
+
     unsafe extern "C++" {
         include!("zenoh-flow-cxx-sink/cpp/include/sink.hpp");
 
         type State;
 
+        fn threading(configuration: &ConfigurationMap) -> Threading;
         fn initialize(configuration: &ConfigurationMap) -> UniquePtr<State>;
 
         fn input_rule(
@@ -83,219 +106,20 @@ pub mod ffi {
             inputs: Vec<Input>,
         ) -> Result<()>;
     }
-}
-
-impl From<HashMap<String, String>> for ffi::ConfigurationMap {
-    fn from(configuration: HashMap<String, String>) -> Self {
-        ffi::ConfigurationMap {
-            map: configuration
-                .iter()
-                .map(|(key, value)| ffi::Configuration {
-                    key: key.clone(),
-                    value: value.clone(),
-                })
-                .collect(),
-        }
-    }
-}
-
-unsafe impl Send for ffi::State {}
-unsafe impl Sync for ffi::State {}
-
-pub struct StateWrapper {
-    pub state: UniquePtr<ffi::State>,
-}
-
-impl State for StateWrapper {
-    fn as_any(&self) -> &dyn std::any::Any {
-        self
-    }
 
-    fn as_mut_any(&mut self) -> &mut dyn std::any::Any {
-        self
-    }
-}
+    extern "Rust" {
+        fn pretty_print(port_type: &str, encoding: &str, data: &[u8]) -> String;
 
-impl Debug for StateWrapper {
-    fn fmt(&self, _f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        todo!()
-    }
-}
+        type Payload;
 
-impl ffi::Data {
-    pub fn new(bytes: Vec<u8>) -> Self {
-        Self { bytes }
-    }
-}
+        fn data(self: &Payload) -> Result<&[u8]>;
+        fn encoding(self: &Payload) -> Result<&str>;
+        fn metadata(self: &Payload) -> Result<Vec<Attribute>>;
 
-impl Debug for ffi::Data {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Data").field("bytes", &self.bytes).finish()
-    }
-}
-
-impl DowncastAny for ffi::Data {
-    fn as_any(&self) -> &dyn std::any::Any {
-        self
-    }
+        type Shared;
 
-    fn as_mut_any(&mut self) -> &mut dyn std::any::Any {
-        self
+        fn data(self: &Shared) -> &[u8];
     }
 }
 
-impl Data for ffi::Data {
-    fn try_serialize(&self) -> ZFResult<Vec<u8>> {
-        Ok(self.bytes.clone())
-    }
-}
-
-impl ffi::Token {
-    pub fn from_token(token: &Token, port_id: &str) -> ZFResult<Self> {
-        match token {
-            Token::NotReady => Ok(Self {
-                status: ffi::TokenStatus::Pending,
-                action: ffi::TokenAction::Wait,
-                port_id: port_id.to_string(),
-                data: Vec::new(),
-                timestamp: 0,
-            }),
-
-            Token::Ready(token) => {
-                let data = match &token.data.data {
-                    SerDeData::Serialized(ser) => ser.as_ref().clone(),
-                    SerDeData::Deserialized(de) => de.try_serialize()?,
-                };
-
-                Ok(Self {
-                    status: ffi::TokenStatus::Ready,
-                    action: ffi::TokenAction::Consume,
-                    port_id: port_id.to_string(),
-                    data,
-                    timestamp: token.data.timestamp.get_time().as_u64(),
-                })
-            }
-        }
-    }
-}
-
-impl From<&mut Context> for ffi::Context {
-    fn from(context: &mut Context) -> Self {
-        Self { mode: context.mode }
-    }
-}
-
-impl From<TokenAction> for ffi::TokenAction {
-    fn from(action: TokenAction) -> Self {
-        match action {
-            TokenAction::Consume => ffi::TokenAction::Consume,
-            TokenAction::Drop => ffi::TokenAction::Drop,
-            TokenAction::KeepRun => ffi::TokenAction::Keep,
-            TokenAction::Keep => ffi::TokenAction::Keep,
-            TokenAction::Wait => ffi::TokenAction::Wait,
-        }
-    }
-}
-
-impl ffi::Input {
-    fn from_data_message(port_id: &str, data_message: &DataMessage) -> ZFResult<Self> {
-        let data = match &data_message.data {
-            SerDeData::Serialized(ser) => ser.as_ref().clone(),
-            SerDeData::Deserialized(de) => de.try_serialize()?,
-        };
-
-        Ok(Self {
-            port_id: port_id.to_string(),
-            data,
-            timestamp: data_message.timestamp.get_time().as_u64(),
-        })
-    }
-}
-
-/*
-
-Operator implementation.
-
-*/
-pub struct MySink;
-
-impl Component for MySink {
-    fn initialize(
-        &self,
-        configuration: &Option<std::collections::HashMap<String, String>>,
-    ) -> Box<dyn zenoh_flow::State> {
-        let configuration = match configuration {
-            Some(config) => ffi::ConfigurationMap::from(config.clone()),
-            None => ffi::ConfigurationMap { map: Vec::new() },
-        };
-
-        let state = {
-            #[allow(unused_unsafe)]
-            unsafe {
-                ffi::initialize(&configuration)
-            }
-        };
-        Box::new(StateWrapper { state })
-    }
-
-    fn clean(&self, _state: &mut Box<dyn State>) -> ZFResult<()> {
-        Ok(())
-    }
-}
-
-impl InputRule for MySink {
-    fn input_rule(
-        &self,
-        context: &mut zenoh_flow::Context,
-        dyn_state: &mut Box<dyn State>,
-        tokens: &mut HashMap<PortId, zenoh_flow::Token>,
-    ) -> zenoh_flow::ZFResult<bool> {
-        let wrapper = downcast_mut!(StateWrapper, dyn_state).unwrap();
-        let res_cxx_tokens: Result<Vec<ffi::Token>, ZFError> = tokens
-            .iter()
-            .map(|(port_id, token)| ffi::Token::from_token(token, port_id))
-            .collect();
-        let mut cxx_tokens = res_cxx_tokens?;
-        let mut cxx_context = ffi::Context::from(context);
-
-        {
-            #[allow(unused_unsafe)]
-            unsafe {
-                ffi::input_rule(&mut cxx_context, &mut wrapper.state, &mut cxx_tokens)
-                    .map_err(|_| ZFError::GenericError)
-            }
-        }
-    }
-}
-
-#[async_trait]
-impl Sink for MySink {
-    async fn run(
-        &self,
-        context: &mut Context,
-        dyn_state: &mut Box<dyn State>,
-        inputs: &mut HashMap<PortId, DataMessage>,
-    ) -> ZFResult<()> {
-        let mut cxx_context = ffi::Context::from(context);
-        let wrapper = downcast_mut!(StateWrapper, dyn_state).unwrap();
-        let result_cxx_inputs: Result<Vec<ffi::Input>, ZFError> = inputs
-            .iter()
-            .map(|(port_id, data_message)| ffi::Input::from_data_message(port_id, data_message))
-            .collect();
-        let cxx_inputs = result_cxx_inputs?;
-
-        {
-            #[allow(unused_unsafe)]
-            unsafe {
-                Ok(ffi::run(&mut cxx_context, &mut wrapper.state, cxx_inputs)
-                    .map_err(|_| ZFError::GenericError)?)
-            }
-        }
-    }
-}
-
-zenoh_flow::export_sink!(register);
-
-fn register() -> ZFResult<Arc<dyn Sink>> {
-    Ok(Arc::new(MySink) as Arc<dyn Sink>)
-}
+zenoh_flow_cxx_common::export_cxx_sink!(ffi);
@@ -9,10 +9,14 @@ namespace zenoh {
 namespace flow {
 
 class State {
+private:
+  bool interactive;
 public:
-  State();
+  State (bool interactive);
+  bool isInteractive ();
 };
 
+Threading threading(const ConfigurationMap &configuration);
 std::unique_ptr<State>
 initialize(const ConfigurationMap &configuration);
 rust::Vec<Output> run(Context &context, std::unique_ptr<State> &state);
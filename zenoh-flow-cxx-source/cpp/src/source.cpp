@@ -12,29 +12,49 @@ namespace flow {
 
 using byte_t = unsigned char ;
 
-State::State() {}
+State::State(bool interactive) : interactive(interactive) {}
+
+bool State::isInteractive(void) {
+  return interactive;
+}
+
+Threading
+threading(const ConfigurationMap &configuration) {
+  return Threading::Send;
+}
 
 std::unique_ptr<State>
 initialize(const ConfigurationMap &configuration)
 {
+  //
+  // Setting `interactive` to "false" removes the ENTER prompt: a tick is then
+  // produced at every period of the source.
+  //
+  bool interactive = true;
+  for (auto entry : configuration.map) {
+    if (std::string(entry.key) == "interactive") {
+      interactive = std::string(entry.value) != "false";
+    }
+  }
+
   //
   // /!\ NOTE: `make_unique` requires "c++14"
   //
-  return std::make_unique<State>();
+  return std::make_unique<State>(interactive);
 }
 
 rust::Vec<Output>
 run(Context &context, std::unique_ptr<State> &state)
 {
-  std::string input;
-
-  std::cout << "Press ENTER.";
-  std::getline(std::cin, input);
-  std::cout << std::endl;
+  if (state->isInteractive()) {
+    std::string input;
 
-  rust::Vec<byte_t> tick = { 1 };
+    std::cout << "Press ENTER.";
+    std::getline(std::cin, input);
+    std::cout << std::endl;
+  }
 
-  Output output { "tick", tick };
+  Output output { "tick", codec::encode(byte_t(1)) };
 
   rust::Vec<Output> results { output };
   return results;
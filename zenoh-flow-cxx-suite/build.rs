@@ -0,0 +1,3 @@
+fn main() {
+    zenoh_flow_cxx_build::component("cpp/src/suite.cpp", "cxx_suite");
+}
@@ -0,0 +1,135 @@
+#include "zenoh-flow-cxx-suite/cpp/include/suite.hpp"
+#include "zenoh-flow-cxx-suite/src/lib.rs.h"
+#include <algorithm>
+#include <cctype>
+#include <cstdint>
+#include <cstring>
+#include <memory>
+#include <string>
+#include <vector>
+
+namespace zenoh {
+namespace flow {
+
+//
+// `number` (u64) -> `fizz` (string).
+//
+class FizzBuzz : public suite::Operator {
+public:
+  bool input_rule(Context &context, rust::Vec<Token> &tokens) override {
+    return suite::all_ready(tokens);
+  }
+
+  rust::Vec<Output> run(Context &context, rust::Vec<Input> inputs) override {
+    rust::Vec<Output> outputs;
+    for (const auto &input : inputs) {
+      std::uint64_t number = codec::decode<std::uint64_t>(payload(input));
+      std::string result;
+      if (number % 3 == 0) {
+        result += "Fizz";
+      }
+      if (number % 5 == 0) {
+        result += "Buzz";
+      }
+      if (result.empty()) {
+        result = std::to_string(number);
+      }
+      outputs.push_back(Output { "fizz", codec::encode(result) });
+    }
+    return outputs;
+  }
+};
+
+//
+// `fizz` (string) -> `fizz` (string), in upper case and followed by `suffix`.
+//
+class Shout : public suite::Operator {
+public:
+  Shout(const ConfigurationMap &configuration) : suffix("!") {
+    for (const auto &entry : configuration.map) {
+      if (std::string(entry.key) == "suffix") {
+        suffix = std::string(entry.value);
+      }
+    }
+  }
+
+  bool input_rule(Context &context, rust::Vec<Token> &tokens) override {
+    return suite::all_ready(tokens);
+  }
+
+  rust::Vec<Output> run(Context &context, rust::Vec<Input> inputs) override {
+    rust::Vec<Output> outputs;
+    for (const auto &input : inputs) {
+      std::string text = codec::decode<std::string>(payload(input));
+      std::transform(text.begin(), text.end(), text.begin(),
+                     [](unsigned char c) { return std::toupper(c); });
+      outputs.push_back(Output { "fizz", codec::encode(text + suffix) });
+    }
+    return outputs;
+  }
+
+private:
+  std::string suffix;
+};
+
+//
+// `number` (u64) -> `negated` (i64), `half` (f64) and `text` (string): the
+// encodings of `zenoh_flow_codec.hpp`, decoded by Rust components.
+//
+class Convert : public suite::Operator {
+public:
+  bool input_rule(Context &context, rust::Vec<Token> &tokens) override {
+    return suite::all_ready(tokens);
+  }
+
+  rust::Vec<Output> run(Context &context, rust::Vec<Input> inputs) override {
+    rust::Vec<Output> outputs;
+    for (const auto &input : inputs) {
+      std::uint64_t number = codec::decode<std::uint64_t>(payload(input));
+      outputs.push_back(Output { "negated", codec::encode(-static_cast<std::int64_t>(number)) });
+      outputs.push_back(Output { "half", codec::encode(static_cast<double>(number) / 2) });
+      outputs.push_back(Output { "text", codec::encode(std::to_string(number)) });
+    }
+    return outputs;
+  }
+};
+
+const std::map<std::string, suite::Factory> operators {
+  { "fizzbuzz", [](const ConfigurationMap &configuration) {
+      return std::unique_ptr<suite::Operator>(new FizzBuzz());
+    } },
+  { "shout", [](const ConfigurationMap &configuration) {
+      return std::unique_ptr<suite::Operator>(new Shout(configuration));
+    } },
+  { "convert", [](const ConfigurationMap &configuration) {
+      return std::unique_ptr<suite::Operator>(new Convert());
+    } },
+};
+
+State::State(const ConfigurationMap &configuration)
+  : suite(operators, configuration) {}
+
+Threading
+threading(const ConfigurationMap &configuration) {
+  return Threading::Send;
+}
+
+std::unique_ptr<State>
+initialize(const ConfigurationMap &configuration) {
+  //
+  // /!\ NOTE: `make_unique` requires "c++14"
+  //
+  return std::make_unique<State>(configuration);
+}
+
+bool
+input_rule(Context &context, std::unique_ptr<State> &state, rust::Vec<Token> &tokens) {
+  return state->suite.input_rule(context, tokens);
+}
+
+rust::Vec<Output>
+run(Context &context, std::unique_ptr<State> &state, rust::Vec<Input> inputs) {
+  return state->suite.run(context, std::move(inputs));
+}
+} // namespace flow
+} // namespace zenoh
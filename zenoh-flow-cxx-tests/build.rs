@@ -0,0 +1,9 @@
+fn main() {
+    zenoh_flow_cxx_build::bridge("src/codec.rs", &["cpp/codec.cpp"]).compile("golden");
+    if std::env::var_os("CARGO_FEATURE_JSON").is_some() {
+        zenoh_flow_cxx_build::bridge("src/json.rs", &["cpp/json.cpp"]).compile("roundtrip");
+    }
+    if std::env::var_os("CARGO_FEATURE_PROTOBUF").is_some() {
+        zenoh_flow_cxx_build::bridge("src/protobuf.rs", &["cpp/protobuf.cpp"]).compile("messages");
+    }
+}
@@ -0,0 +1,69 @@
+#include "zenoh-flow-cxx-tests/cpp/codec.hpp"
+#include "zenoh-flow-cxx-tests/../include/zenoh_flow_codec.hpp"
+#include <cstdint>
+#include <functional>
+#include <map>
+#include <stdexcept>
+#include <string>
+#include <tuple>
+#include <vector>
+
+namespace zenoh {
+namespace flow {
+namespace golden {
+
+namespace {
+
+//
+// The values of `tests/codec.rs`, by name.
+//
+struct Golden {
+  std::function<codec::Bytes()> encode;
+  std::function<bool(rust::Slice<const std::uint8_t>)> decodes;
+};
+
+template <typename T>
+Golden golden(const T &value) {
+  return Golden {
+    [value]() { return codec::encode(value); },
+    [value](rust::Slice<const std::uint8_t> bytes) {
+      return codec::decode<T>(bytes) == value;
+    },
+  };
+}
+
+const Golden &find(rust::Str name) {
+  using Tuple = std::tuple<std::uint8_t, std::string, std::vector<std::int16_t>>;
+  static const std::map<std::string, Golden> values {
+    { "u64", golden(std::uint64_t(0x0102030405060708)) },
+    { "i64", golden(std::int64_t(-2)) },
+    { "u16", golden(std::uint16_t(0x0102)) },
+    { "bool", golden(true) },
+    { "f64", golden(1.5) },
+    { "f32", golden(-0.25f) },
+    { "string", golden(std::string("fizz")) },
+    { "empty string", golden(std::string()) },
+    { "vector", golden(std::vector<std::uint32_t> { 1, 256 }) },
+    { "tuple", golden(Tuple { 7, "fizz", { -1, 2 } }) },
+  };
+
+  auto value = values.find(std::string(name));
+  if (value == values.end()) {
+    throw std::invalid_argument("Unknown value: " + std::string(name));
+  }
+  return value->second;
+}
+
+} // namespace
+
+rust::Vec<std::uint8_t> encode(rust::Str name) {
+  return find(name).encode();
+}
+
+bool decodes(rust::Str name, rust::Slice<const std::uint8_t> bytes) {
+  return find(name).decodes(bytes);
+}
+
+} // namespace golden
+} // namespace flow
+} // namespace zenoh
@@ -0,0 +1,14 @@
+#pragma once
+#include <cstdint>
+#include "rust/cxx.h"
+
+namespace zenoh {
+namespace flow {
+namespace golden {
+
+rust::Vec<std::uint8_t> encode(rust::Str name);
+bool decodes(rust::Str name, rust::Slice<const std::uint8_t> bytes);
+
+} // namespace golden
+} // namespace flow
+} // namespace zenoh
@@ -0,0 +1,27 @@
+#include "zenoh-flow-cxx-tests/cpp/json.hpp"
+#include "zenoh-flow-cxx-tests/../include/zenoh_flow_json.hpp"
+#include <cstdint>
+
+namespace zenoh {
+namespace flow {
+namespace roundtrip {
+
+rust::Vec<std::uint8_t> json(rust::Slice<const std::uint8_t> bytes) {
+  return flow::json::encode(flow::json::decode(bytes));
+}
+
+rust::Vec<std::uint8_t> cbor(rust::Slice<const std::uint8_t> bytes) {
+  return flow::cbor::encode(flow::cbor::decode(bytes));
+}
+
+rust::Vec<std::uint8_t> command(bool cbor) {
+  flow::json::Value command;
+  command["speed"] = 1.5;
+  command["frames"].push_back(42);
+  command["name"] = "fizz";
+  return cbor ? flow::cbor::encode(command) : flow::json::encode(command);
+}
+
+} // namespace roundtrip
+} // namespace flow
+} // namespace zenoh
@@ -0,0 +1,15 @@
+#pragma once
+#include <cstdint>
+#include "rust/cxx.h"
+
+namespace zenoh {
+namespace flow {
+namespace roundtrip {
+
+rust::Vec<std::uint8_t> json(rust::Slice<const std::uint8_t> bytes);
+rust::Vec<std::uint8_t> cbor(rust::Slice<const std::uint8_t> bytes);
+rust::Vec<std::uint8_t> command(bool cbor);
+
+} // namespace roundtrip
+} // namespace flow
+} // namespace zenoh
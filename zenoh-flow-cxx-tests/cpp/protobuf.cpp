@@ -0,0 +1,32 @@
+#include "zenoh-flow-cxx-tests/cpp/protobuf.hpp"
+#include "zenoh-flow-cxx-tests/../include/zenoh_flow_protobuf.hpp"
+#include "sensors.pb.h"
+#include <cstdint>
+#include <string>
+
+namespace zenoh {
+namespace flow {
+namespace roundtrip {
+
+rust::Vec<std::uint8_t> imu(rust::Slice<const std::uint8_t> bytes) {
+  return flow::protobuf::encode(flow::protobuf::decode<sensors::Imu>(bytes));
+}
+
+rust::Vec<std::uint8_t> reading() {
+  sensors::Imu imu;
+  imu.set_x(0.5);
+  imu.set_y(-9.81);
+  imu.set_z(1e-3);
+  imu.set_frame("base_link");
+  imu.add_samples(1);
+  imu.add_samples(UINT64_MAX);
+  return flow::protobuf::encode(imu);
+}
+
+bool is_imu(rust::Str port_type) {
+  return flow::protobuf::is_declared_as<sensors::Imu>(std::string(port_type));
+}
+
+} // namespace roundtrip
+} // namespace flow
+} // namespace zenoh
@@ -0,0 +1,15 @@
+#pragma once
+#include <cstdint>
+#include "rust/cxx.h"
+
+namespace zenoh {
+namespace flow {
+namespace roundtrip {
+
+rust::Vec<std::uint8_t> imu(rust::Slice<const std::uint8_t> bytes);
+rust::Vec<std::uint8_t> reading();
+bool is_imu(rust::Str port_type);
+
+} // namespace roundtrip
+} // namespace flow
+} // namespace zenoh
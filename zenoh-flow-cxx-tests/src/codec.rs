@@ -0,0 +1,15 @@
+//! The codec of `zenoh_flow_codec.hpp`, on values also encoded by `zenoh_flow_cxx_common::codec`
+//! in `tests/codec.rs`.
+
+#[cxx::bridge(namespace = "zenoh::flow::golden")]
+pub mod ffi {
+    unsafe extern "C++" {
+        include!("zenoh-flow-cxx-tests/cpp/codec.hpp");
+
+        /// Returns the encoding, by the C++ codec, of the value `name`.
+        fn encode(name: &str) -> Result<Vec<u8>>;
+
+        /// Whether the C++ codec decodes `bytes` into the value `name`.
+        fn decodes(name: &str, bytes: &[u8]) -> Result<bool>;
+    }
+}
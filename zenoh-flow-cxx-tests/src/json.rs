@@ -0,0 +1,19 @@
+//! The payloads of `zenoh_flow_json.hpp`, against `serde_json` and `ciborium` in
+//! `tests/json.rs`.
+
+#[cxx::bridge(namespace = "zenoh::flow::roundtrip")]
+pub mod ffi {
+    unsafe extern "C++" {
+        include!("zenoh-flow-cxx-tests/cpp/json.hpp");
+
+        /// Decodes JSON text in C++ and encodes it back.
+        fn json(bytes: &[u8]) -> Result<Vec<u8>>;
+
+        /// Decodes CBOR in C++ and encodes it back.
+        fn cbor(bytes: &[u8]) -> Result<Vec<u8>>;
+
+        /// The command of the example of `zenoh_flow_json.hpp`, encoded in C++ as JSON text or,
+        /// with `cbor`, as CBOR.
+        fn command(cbor: bool) -> Vec<u8>;
+    }
+}
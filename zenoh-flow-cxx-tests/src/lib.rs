@@ -0,0 +1,214 @@
+use serde_yaml::{Mapping, Value};
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    path::Path,
+    process::Command,
+    time::{Duration, Instant},
+};
+use zenoh_flow::{
+    model::dataflow::DataFlowDescriptor, runtime::graph::DataFlowGraph, ZFError, ZFResult,
+};
+use zenoh_flow_cxx_graphs::{generate, template_path, workspace_dir, Variables};
+
+pub mod codec;
+#[cfg(feature = "json")]
+pub mod json;
+#[cfg(feature = "protobuf")]
+pub mod protobuf;
+
+extern crate zenoh_flow;
+
+/// The components, as cargo packages, used by the graphs of `graphs/`: the C++ components and a
+/// Rust sink receiving their outputs.
+pub const COMPONENTS: [&str; 8] = [
+    "zenoh-flow-cxx-source",
+    "zenoh-flow-cxx-operator",
+    "zenoh-flow-cxx-sink",
+    "zenoh-flow-cxx-manual-source",
+    "zenoh-flow-cxx-fizz",
+    "zenoh-flow-cxx-fusion",
+    "zenoh-flow-cxx-suite",
+    "zenoh-flow-cxx-rust-sink",
+];
+
+/// Kinds of components that can appear in a graph descriptor.
+const KINDS: [&str; 3] = ["sources", "operators", "sinks"];
+
+/// Builds the cdylibs of the components.
+///
+/// Integration tests only build the crate they belong to, the libraries loaded by the runtime
+/// have to be built explicitly.
+pub fn build_components() -> ZFResult<()> {
+    let mut args = Vec::new();
+    for component in COMPONENTS.iter() {
+        args.extend(["-p", *component]);
+    }
+    cargo_build(&args)
+}
+
+/// Builds the cdylib of a component outside of `COMPONENTS`, with `features`: the components
+/// needing tools the others do not (e.g. `zenoh-flow-cxx-imu` and `flatc`).
+pub fn build_component(package: &str, features: &[&str]) -> ZFResult<()> {
+    let features = features.join(",");
+    cargo_build(&["-p", package, "--features", &features])
+}
+
+fn cargo_build(args: &[&str]) -> ZFResult<()> {
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let status = Command::new(cargo)
+        .current_dir(workspace_dir())
+        .arg("build")
+        .args(args)
+        .status()
+        .map_err(|e| ZFError::IOError(format!("{}", e)))?;
+    if !status.success() {
+        return Err(ZFError::IOError(format!(
+            "Could not build the C++ components: {}",
+            status
+        )));
+    }
+
+    Ok(())
+}
+
+/// A graph descriptor of `graphs/`, rendered to run on the local machine.
+pub struct Graph {
+    descriptor: Value,
+}
+
+impl Graph {
+    /// Generates the descriptor of `graphs/<name>.yaml` for the libraries of the local build.
+    pub fn load(name: &str) -> ZFResult<Self> {
+        let yaml = generate(&template_path(name), &Variables::default())
+            .map_err(|e| ZFError::IOError(format!("{}", e)))?;
+        let descriptor: Value =
+            serde_yaml::from_str(&yaml).map_err(|e| ZFError::ParsingError(format!("{}", e)))?;
+
+        Ok(Self { descriptor })
+    }
+
+    /// Adds `configuration` to the configuration of the component `id`.
+    pub fn configure(mut self, id: &str, configuration: HashMap<String, String>) -> Self {
+        let mut map = Mapping::new();
+        for (key, value) in configuration {
+            map.insert(Value::from(key), Value::from(value));
+        }
+
+        for kind in KINDS.iter() {
+            if let Some(components) = self
+                .descriptor
+                .get_mut(*kind)
+                .and_then(|components| components.as_sequence_mut())
+            {
+                for component in components.iter_mut() {
+                    if component.get("id").and_then(|c| c.as_str()) == Some(id) {
+                        let existing = &mut component["configuration"];
+                        if !existing.is_mapping() {
+                            *existing = Value::Mapping(Mapping::new());
+                        }
+                        if let Some(existing) = existing.as_mapping_mut() {
+                            for (key, value) in map.iter() {
+                                existing.insert(key.clone(), value.clone());
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        self
+    }
+
+    /// Maps every component of the graph on the same runtime, ignoring the `mapping` section.
+    pub fn map_all(mut self, runtime: &str) -> Self {
+        let mut mapping = Vec::new();
+        for kind in KINDS.iter() {
+            if let Some(components) = self
+                .descriptor
+                .get(*kind)
+                .and_then(|components| components.as_sequence())
+            {
+                for component in components {
+                    let mut entry = Mapping::new();
+                    entry.insert(Value::from("id"), component["id"].clone());
+                    entry.insert(Value::from("runtime"), Value::from(runtime));
+                    mapping.push(Value::Mapping(entry));
+                }
+            }
+        }
+
+        self.descriptor["mapping"] = Value::Sequence(mapping);
+        self
+    }
+
+    /// Returns the names of the runtimes the graph is mapped on.
+    pub fn runtimes(&self) -> Vec<String> {
+        let mut runtimes: Vec<String> = self
+            .descriptor
+            .get("mapping")
+            .and_then(|mapping| mapping.as_sequence())
+            .map(|mapping| {
+                mapping
+                    .iter()
+                    .filter_map(|entry| entry.get("runtime").and_then(|r| r.as_str()))
+                    .map(|runtime| runtime.to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        runtimes.sort();
+        runtimes.dedup();
+        runtimes
+    }
+
+    pub fn descriptor(&self) -> ZFResult<DataFlowDescriptor> {
+        let yaml = serde_yaml::to_string(&self.descriptor)
+            .map_err(|e| ZFError::ParsingError(format!("{}", e)))?;
+        DataFlowDescriptor::from_yaml(&yaml)
+    }
+}
+
+/// Instantiates, in this process, the part of `graph` mapped on `runtime` and starts its nodes.
+///
+/// The returned `DataFlowGraph` must be kept alive for as long as the nodes should run.
+pub async fn spawn_runtime(graph: &Graph, runtime: &str) -> ZFResult<DataFlowGraph> {
+    let mut dataflow_graph = DataFlowGraph::try_from(graph.descriptor()?)?;
+    dataflow_graph.load(runtime)?;
+    dataflow_graph.make_connections(runtime).await;
+
+    for runner in dataflow_graph.get_runners() {
+        async_std::task::spawn(async move {
+            let _ = runner.run().await;
+        });
+    }
+
+    Ok(dataflow_graph)
+}
+
+/// Waits until the file at `path` contains at least `count` lines and returns them.
+///
+/// Panics, with the lines read so far, if they are not written within `timeout`.
+pub async fn wait_for_lines(path: &Path, count: usize, timeout: Duration) -> Vec<String> {
+    let start = Instant::now();
+    loop {
+        let lines: Vec<String> = std::fs::read_to_string(path)
+            .map(|content| content.lines().map(|line| line.to_string()).collect())
+            .unwrap_or_default();
+
+        if lines.len() >= count {
+            return lines;
+        }
+        if start.elapsed() > timeout {
+            panic!(
+                "{}: expected {} lines within {:?}, got {}: {:?}",
+                path.display(),
+                count,
+                timeout,
+                lines.len(),
+                lines
+            );
+        }
+
+        async_std::task::sleep(Duration::from_millis(100)).await;
+    }
+}
@@ -0,0 +1,18 @@
+//! The payloads of `zenoh_flow_protobuf.hpp`, against a `prost` component in
+//! `tests/protobuf.rs`.
+
+#[cxx::bridge(namespace = "zenoh::flow::roundtrip")]
+pub mod ffi {
+    unsafe extern "C++" {
+        include!("zenoh-flow-cxx-tests/cpp/protobuf.hpp");
+
+        /// Decodes a `sensors.Imu` in C++ and encodes it back.
+        fn imu(bytes: &[u8]) -> Result<Vec<u8>>;
+
+        /// A `sensors.Imu` built and encoded in C++.
+        fn reading() -> Vec<u8>;
+
+        /// Whether `port_type` is the type of `sensors.Imu` for C++ components.
+        fn is_imu(port_type: &str) -> bool;
+    }
+}
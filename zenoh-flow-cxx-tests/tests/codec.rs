@@ -0,0 +1,35 @@
+//! The C++ codec against the Rust one, whose encodings are pinned by the tests of
+//! `zenoh_flow_cxx_common::codec`.
+
+use serde::Serialize;
+use zenoh_flow_cxx_common::codec::encode;
+use zenoh_flow_cxx_tests::codec::ffi;
+
+fn check<T: Serialize>(name: &str, value: T) {
+    let bytes = encode(&value).unwrap();
+    assert_eq!(ffi::encode(name).unwrap(), bytes, "{}", name);
+    assert!(ffi::decodes(name, &bytes).unwrap(), "{}", name);
+}
+
+#[test]
+fn cxx_encodes_like_rust() {
+    check("u64", 0x0102_0304_0506_0708_u64);
+    check("i64", -2_i64);
+    check("u16", 0x0102_u16);
+    check("bool", true);
+    check("f64", 1.5_f64);
+    check("f32", -0.25_f32);
+    check("string", "fizz".to_string());
+    check("empty string", String::new());
+    check("vector", vec![1_u32, 256]);
+    check("tuple", (7_u8, "fizz".to_string(), vec![-1_i16, 2]));
+}
+
+#[test]
+fn cxx_refuses_invalid_payloads() {
+    assert!(ffi::decodes("u64", &[8, 7, 6]).is_err());
+    assert!(ffi::decodes("bool", &[2]).is_err());
+    // Trailing bytes.
+    assert!(ffi::decodes("u16", &[2, 1, 0]).is_err());
+    assert!(!ffi::decodes("u64", &[0; 8]).unwrap());
+}
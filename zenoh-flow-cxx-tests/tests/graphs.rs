@@ -0,0 +1,548 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    time::Duration,
+};
+use zenoh_flow_cxx_tests::{
+    build_component, build_components, spawn_runtime, wait_for_lines, Graph,
+};
+
+const EXPECTED_MESSAGES: usize = 5;
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+fn counter_graph(output: &Path) -> Graph {
+    let mut source = HashMap::new();
+    source.insert("interactive".to_string(), "false".to_string());
+
+    let mut sink = HashMap::new();
+    sink.insert("output".to_string(), output.display().to_string());
+
+    Graph::load("cxx-multiple-runtimes")
+        .unwrap()
+        .configure("CxxSource", source)
+        .configure("CxxSink", sink)
+}
+
+/// Returns the values the sink wrote on `port`, one `<port>: <value>` line per message.
+fn values(lines: &[String], port: &str) -> Vec<String> {
+    let prefix = format!("{}: ", port);
+    lines
+        .iter()
+        .map(|line| {
+            line.strip_prefix(&prefix)
+                .unwrap_or_else(|| panic!("Unexpected line in the sink output: {}", line))
+                .to_string()
+        })
+        .collect()
+}
+
+fn counts(lines: &[String]) -> Vec<u8> {
+    values(lines, "count")
+        .iter()
+        .map(|count| count.parse().unwrap())
+        .collect()
+}
+
+/// The counter increases by one for every tick: whatever the first value received by the sink
+/// (zenoh may drop the very first messages while the runtimes connect), the next ones must
+/// follow it. It is an `unsigned char`, wrapping after 255.
+fn assert_consecutive(counts: &[u8]) {
+    assert!(
+        counts.len() >= EXPECTED_MESSAGES,
+        "Received only {:?}",
+        counts
+    );
+    for pair in counts.windows(2) {
+        assert_eq!(
+            pair[0].wrapping_add(1),
+            pair[1],
+            "Non consecutive counts: {:?}",
+            counts
+        );
+    }
+}
+
+fn output_file(name: &str) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("{}-{}.txt", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+#[async_std::test]
+async fn counter_single_runtime() {
+    build_components().unwrap();
+    let output = output_file("counter-single-runtime");
+
+    let graph = counter_graph(&output).map_all("local");
+    let _runtime = spawn_runtime(&graph, "local").await.unwrap();
+
+    let lines = wait_for_lines(&output, EXPECTED_MESSAGES, TIMEOUT).await;
+    let counts = counts(&lines);
+    assert_consecutive(&counts);
+    assert_eq!(counts[0], 1);
+}
+
+#[async_std::test]
+async fn counter_buffer_pool() {
+    build_components().unwrap();
+    let output = output_file("counter-buffer-pool");
+
+    // The counts are written to recycled buffers once the first messages are dropped.
+    let mut operator = HashMap::new();
+    operator.insert("buffer_pool".to_string(), "4".to_string());
+
+    let graph = counter_graph(&output)
+        .configure("CxxCounter", operator)
+        .map_all("local");
+    let _runtime = spawn_runtime(&graph, "local").await.unwrap();
+
+    let lines = wait_for_lines(&output, EXPECTED_MESSAGES, TIMEOUT).await;
+    let counts = counts(&lines);
+    assert_consecutive(&counts);
+    assert_eq!(counts[0], 1);
+}
+
+#[async_std::test]
+async fn counter_checkpoint() {
+    build_components().unwrap();
+    let output = output_file("counter-checkpoint");
+
+    // The counter resumes from the snapshot of its instance, named after the component.
+    let checkpoints =
+        std::env::temp_dir().join(format!("counter-checkpoint-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&checkpoints);
+    std::fs::create_dir_all(&checkpoints).unwrap();
+    let snapshot = checkpoints.join("CxxCounter.snapshot");
+    std::fs::write(&snapshot, [41u8]).unwrap();
+
+    let mut operator = HashMap::new();
+    operator.insert("checkpoint".to_string(), checkpoints.display().to_string());
+    operator.insert("checkpoint_period".to_string(), "1".to_string());
+
+    let graph = counter_graph(&output)
+        .configure("CxxCounter", operator)
+        .map_all("local");
+    let _runtime = spawn_runtime(&graph, "local").await.unwrap();
+
+    let lines = wait_for_lines(&output, EXPECTED_MESSAGES, TIMEOUT).await;
+    let counts = counts(&lines);
+    assert_consecutive(&counts);
+    assert_eq!(counts[0], 42);
+
+    // With a period of 1, a snapshot is taken after every call to `run`.
+    let saved = std::fs::read(&snapshot).unwrap();
+    assert_eq!(saved.len(), 1);
+    assert!(saved[0] >= counts[EXPECTED_MESSAGES - 1], "{:?}", saved);
+}
+
+#[async_std::test]
+async fn counter_multiple_runtimes() {
+    build_components().unwrap();
+    let output = output_file("counter-multiple-runtimes");
+
+    // Both links cross runtimes: their payloads are compressed, and decompressed before they
+    // reach the C++ components.
+    let mut source = HashMap::new();
+    source.insert("compression/tick".to_string(), "lz4".to_string());
+    let mut operator = HashMap::new();
+    operator.insert("compression/count".to_string(), "zstd".to_string());
+
+    let graph = counter_graph(&output)
+        .configure("CxxSource", source)
+        .configure("CxxCounter", operator);
+    assert_eq!(graph.runtimes(), vec!["operator", "sink", "source"]);
+
+    // The consumers are started first so that they are subscribed when the source produces.
+    let _sink = spawn_runtime(&graph, "sink").await.unwrap();
+    let _operator = spawn_runtime(&graph, "operator").await.unwrap();
+    let _source = spawn_runtime(&graph, "source").await.unwrap();
+
+    let lines = wait_for_lines(&output, EXPECTED_MESSAGES, TIMEOUT).await;
+    assert_consecutive(&counts(&lines));
+}
+
+#[async_std::test]
+async fn counter_shared_memory() {
+    build_components().unwrap();
+    let output = output_file("counter-shared-memory");
+
+    // Every payload crossing runtimes goes through a shared-memory segment.
+    let mut source = HashMap::new();
+    source.insert("shm_threshold".to_string(), "1".to_string());
+    let mut operator = HashMap::new();
+    operator.insert("shm_threshold".to_string(), "1".to_string());
+
+    let graph = counter_graph(&output)
+        .configure("CxxSource", source)
+        .configure("CxxCounter", operator);
+
+    let _sink = spawn_runtime(&graph, "sink").await.unwrap();
+    let _operator = spawn_runtime(&graph, "operator").await.unwrap();
+    let _source = spawn_runtime(&graph, "source").await.unwrap();
+
+    let lines = wait_for_lines(&output, EXPECTED_MESSAGES, TIMEOUT).await;
+    assert_consecutive(&counts(&lines));
+}
+
+#[async_std::test]
+async fn fizz_single_runtime() {
+    build_components().unwrap();
+    let output = output_file("fizz-single-runtime");
+
+    let mut source = HashMap::new();
+    source.insert("interactive".to_string(), "false".to_string());
+    let mut sink = HashMap::new();
+    sink.insert("output".to_string(), output.display().to_string());
+
+    let graph = Graph::load("cxx-fizz-single-runtime")
+        .unwrap()
+        .configure("CxxManualSource", source)
+        .configure("CxxSink", sink)
+        .map_all("local");
+    let _runtime = spawn_runtime(&graph, "local").await.unwrap();
+
+    let expected = [
+        "1", "2", "Fizz", "4", "Buzz", "Fizz", "7", "8", "Fizz", "Buzz", "11", "Fizz", "13", "14",
+        "FizzBuzz",
+    ];
+    let lines = wait_for_lines(&output, expected.len(), TIMEOUT).await;
+    let fizz = values(&lines, "fizz");
+    assert_eq!(&fizz[..expected.len()], &expected[..]);
+}
+
+#[async_std::test]
+async fn fizz_rust_sink() {
+    build_components().unwrap();
+    let output = output_file("fizz-rust-sink");
+
+    let mut source = HashMap::new();
+    source.insert("interactive".to_string(), "false".to_string());
+    let mut sink = HashMap::new();
+    sink.insert("output".to_string(), output.display().to_string());
+
+    let graph = Graph::load("cxx-rust-sink-single-runtime")
+        .unwrap()
+        .configure("CxxManualSource", source)
+        .configure("RustSink", sink)
+        .map_all("local");
+    let _runtime = spawn_runtime(&graph, "local").await.unwrap();
+
+    // The outputs of the operator carry metadata, yet the Rust sink decodes bare strings.
+    let expected = ["1", "2", "Fizz", "4", "Buzz"];
+    let lines = wait_for_lines(&output, expected.len(), TIMEOUT).await;
+    let fizz = values(&lines, "fizz");
+    assert_eq!(&fizz[..expected.len()], &expected[..]);
+}
+
+#[async_std::test]
+async fn codec_rust_sinks() {
+    build_components().unwrap();
+    // Decoded according to the types of the ports: bytes would be printed otherwise.
+    let sinks = [
+        ("RustNegated", "negated", ["-1", "-2", "-3"]),
+        ("RustHalf", "half", ["0.5", "1", "1.5"]),
+        ("RustText", "text", ["1", "2", "3"]),
+    ];
+
+    let mut source = HashMap::new();
+    source.insert("interactive".to_string(), "false".to_string());
+    let mut graph = Graph::load("cxx-codec-single-runtime")
+        .unwrap()
+        .configure("CxxManualSource", source);
+    let mut outputs = Vec::new();
+    for (sink, port, _) in sinks.iter() {
+        let output = output_file(&format!("codec-{}", port));
+        let mut configuration = HashMap::new();
+        configuration.insert("output".to_string(), output.display().to_string());
+        graph = graph.configure(sink, configuration);
+        outputs.push(output);
+    }
+    let graph = graph.map_all("local");
+    let _runtime = spawn_runtime(&graph, "local").await.unwrap();
+
+    for ((_, port, expected), output) in sinks.iter().zip(&outputs) {
+        let lines = wait_for_lines(output, expected.len(), TIMEOUT).await;
+        assert_eq!(&values(&lines, port)[..expected.len()], &expected[..]);
+    }
+}
+
+#[async_std::test]
+async fn imu_flatbuffers() {
+    build_components().unwrap();
+    build_component("zenoh-flow-cxx-imu", &["flatbuffers"]).unwrap();
+    let output = output_file("imu-flatbuffers");
+
+    let mut source = HashMap::new();
+    source.insert("interactive".to_string(), "false".to_string());
+    let mut sink = HashMap::new();
+    sink.insert("output".to_string(), output.display().to_string());
+    let graph = Graph::load("cxx-imu-single-runtime")
+        .unwrap()
+        .configure("CxxManualSource", source)
+        .configure("RustSink", sink)
+        .map_all("local");
+    let _runtime = spawn_runtime(&graph, "local").await.unwrap();
+
+    // The reading of `n` is (n, 2n, 2n): its magnitude is 3n.
+    let expected = ["3", "6", "9"];
+    let lines = wait_for_lines(&output, expected.len(), TIMEOUT).await;
+    assert_eq!(
+        &values(&lines, "magnitude")[..expected.len()],
+        &expected[..]
+    );
+}
+
+#[async_std::test]
+async fn fizz_batched() {
+    build_components().unwrap();
+    let output = output_file("fizz-batched");
+
+    let mut source = HashMap::new();
+    source.insert("interactive".to_string(), "false".to_string());
+    let mut sink = HashMap::new();
+    sink.insert("output".to_string(), output.display().to_string());
+    // The operator receives the numbers by 3 and outputs 3 messages on `fizz` at once.
+    let mut fizz = HashMap::new();
+    fizz.insert("batch_size".to_string(), "3".to_string());
+    fizz.insert("output_sequences".to_string(), "true".to_string());
+
+    let graph = Graph::load("cxx-fizz-single-runtime")
+        .unwrap()
+        .configure("CxxManualSource", source)
+        .configure("CxxFizz", fizz)
+        .configure("CxxSink", sink)
+        .map_all("local");
+    let _runtime = spawn_runtime(&graph, "local").await.unwrap();
+
+    let expected = ["1", "2", "Fizz", "4", "Buzz", "Fizz"];
+    let lines = wait_for_lines(&output, expected.len(), TIMEOUT).await;
+    let fizz = values(&lines, "fizz");
+    assert_eq!(&fizz[..expected.len()], &expected[..]);
+}
+
+#[async_std::test]
+async fn fizz_batch_flushed() {
+    build_components().unwrap();
+    let output = output_file("fizz-batch-flushed");
+
+    let mut source = HashMap::new();
+    source.insert("interactive".to_string(), "false".to_string());
+    let mut sink = HashMap::new();
+    sink.insert("output".to_string(), output.display().to_string());
+
+    let graph = Graph::load("cxx-fizz-batched-single-runtime")
+        .unwrap()
+        .configure("CxxManualSource", source.clone())
+        .configure("CxxSource", source)
+        .configure("CxxSink", sink)
+        .map_all("local");
+    let _runtime = spawn_runtime(&graph, "local").await.unwrap();
+
+    // The ticks flush the first number alone: without them, it would only be delivered with
+    // the second one, 2 s later.
+    let lines = wait_for_lines(&output, 1, TIMEOUT).await;
+    assert_eq!(values(&lines, "fizz"), ["1"]);
+}
+
+#[async_std::test]
+async fn fusion_colliding_payloads() {
+    build_components().unwrap();
+    let output = output_file("fusion-colliding-payloads");
+
+    // The first number, 0x58465A00, starts with the bytes of an envelope: the links of the graph
+    // do not carry envelopes, so it reaches the operator as is.
+    let mut source = HashMap::new();
+    source.insert("interactive".to_string(), "false".to_string());
+    source.insert("start".to_string(), "1481005567".to_string());
+    let mut sink = HashMap::new();
+    sink.insert("output".to_string(), output.display().to_string());
+
+    let graph = Graph::load("cxx-fusion-single-runtime")
+        .unwrap()
+        .configure("CxxManualSource", source)
+        .configure("CxxSink", sink)
+        .map_all("local");
+    let _runtime = spawn_runtime(&graph, "local").await.unwrap();
+
+    let expected = ["1481005568!", "FIZZ!", "BUZZ!"];
+    let lines = wait_for_lines(&output, expected.len(), TIMEOUT).await;
+    let fizz = values(&lines, "fizz");
+    assert_eq!(&fizz[..expected.len()], &expected[..]);
+}
+
+#[async_std::test]
+async fn fusion_single_runtime() {
+    build_components().unwrap();
+    let output = output_file("fusion-single-runtime");
+
+    let mut source = HashMap::new();
+    source.insert("interactive".to_string(), "false".to_string());
+    let mut sink = HashMap::new();
+    sink.insert("output".to_string(), output.display().to_string());
+
+    let graph = Graph::load("cxx-fusion-single-runtime")
+        .unwrap()
+        .configure("CxxManualSource", source)
+        .configure("CxxSink", sink)
+        .map_all("local");
+    let _runtime = spawn_runtime(&graph, "local").await.unwrap();
+
+    // `fizzbuzz` then `shout`, in the same call to `run`.
+    let expected = ["1!", "2!", "FIZZ!", "4!", "BUZZ!", "FIZZ!"];
+    let lines = wait_for_lines(&output, expected.len(), TIMEOUT).await;
+    let fizz = values(&lines, "fizz");
+    assert_eq!(&fizz[..expected.len()], &expected[..]);
+}
+
+#[async_std::test]
+async fn relay_opaque_inputs() {
+    build_components().unwrap();
+    let output = output_file("relay-opaque-inputs");
+
+    let mut source = HashMap::new();
+    source.insert("interactive".to_string(), "false".to_string());
+    let mut sink = HashMap::new();
+    sink.insert("output".to_string(), output.display().to_string());
+
+    let graph = Graph::load("cxx-relay-single-runtime")
+        .unwrap()
+        .configure("CxxManualSource", source)
+        .configure("CxxSink", sink)
+        .map_all("local");
+    let _runtime = spawn_runtime(&graph, "local").await.unwrap();
+
+    // The sink gets the very messages of the source.
+    let expected = ["1", "2", "3", "4", "5"];
+    let lines = wait_for_lines(&output, expected.len(), TIMEOUT).await;
+    let relayed = values(&lines, "relayed");
+    assert_eq!(&relayed[..expected.len()], &expected[..]);
+}
+
+#[async_std::test]
+async fn instances_single_runtime() {
+    build_components().unwrap();
+    let loud_output = output_file("instances-loud");
+    let quiet_output = output_file("instances-quiet");
+
+    let mut source = HashMap::new();
+    source.insert("interactive".to_string(), "false".to_string());
+    let mut loud_sink = HashMap::new();
+    loud_sink.insert("output".to_string(), loud_output.display().to_string());
+    let mut quiet_sink = HashMap::new();
+    quiet_sink.insert("output".to_string(), quiet_output.display().to_string());
+
+    let graph = Graph::load("cxx-instances-single-runtime")
+        .unwrap()
+        .configure("CxxManualSource", source)
+        .configure("CxxLoudSink", loud_sink)
+        .configure("CxxQuietSink", quiet_sink)
+        .map_all("local");
+    let _runtime = spawn_runtime(&graph, "local").await.unwrap();
+
+    // Each instance of the libraries runs with its own configuration.
+    let loud = ["1!", "2!", "FIZZ!", "4!", "BUZZ!"];
+    let lines = wait_for_lines(&loud_output, loud.len(), TIMEOUT).await;
+    assert_eq!(&values(&lines, "fizz")[..loud.len()], &loud[..]);
+
+    let quiet = ["1", "2", "Fizz", "4", "Buzz"];
+    let lines = wait_for_lines(&quiet_output, quiet.len(), TIMEOUT).await;
+    assert_eq!(&values(&lines, "fizz")[..quiet.len()], &quiet[..]);
+}
+
+#[async_std::test]
+async fn suite_single_runtime() {
+    build_components().unwrap();
+    let output = output_file("suite-single-runtime");
+
+    let mut source = HashMap::new();
+    source.insert("interactive".to_string(), "false".to_string());
+    let mut sink = HashMap::new();
+    sink.insert("output".to_string(), output.display().to_string());
+
+    let graph = Graph::load("cxx-suite-single-runtime")
+        .unwrap()
+        .configure("CxxManualSource", source)
+        .configure("CxxSink", sink)
+        .map_all("local");
+    let _runtime = spawn_runtime(&graph, "local").await.unwrap();
+
+    // `fizzbuzz` then `shout`, two components of the same library.
+    let expected = ["1?", "2?", "FIZZ?", "4?", "BUZZ?", "FIZZ?"];
+    let lines = wait_for_lines(&output, expected.len(), TIMEOUT).await;
+    let fizz = values(&lines, "fizz");
+    assert_eq!(&fizz[..expected.len()], &expected[..]);
+}
+
+/// Returns the spans exported in `path`, as `(service, span)`.
+fn spans(path: &Path) -> Vec<(String, serde_json::Value)> {
+    let mut spans = Vec::new();
+    for line in std::fs::read_to_string(path).unwrap().lines() {
+        let request: serde_json::Value = serde_json::from_str(line).unwrap();
+        for resource in request["resourceSpans"].as_array().unwrap() {
+            let service = resource["resource"]["attributes"][0]["value"]["stringValue"]
+                .as_str()
+                .unwrap()
+                .to_string();
+            for scope in resource["scopeSpans"].as_array().unwrap() {
+                for span in scope["spans"].as_array().unwrap() {
+                    spans.push((service.clone(), span.clone()));
+                }
+            }
+        }
+    }
+    spans
+}
+
+#[async_std::test]
+async fn fizz_traces() {
+    build_components().unwrap();
+    let output = output_file("fizz-traces");
+    let trace = output_file("fizz-traces-spans");
+
+    let mut source = HashMap::new();
+    source.insert("interactive".to_string(), "false".to_string());
+    let mut sink = HashMap::new();
+    sink.insert("output".to_string(), output.display().to_string());
+    let mut traced = HashMap::new();
+    traced.insert("trace".to_string(), trace.display().to_string());
+
+    let graph = Graph::load("cxx-fizz-single-runtime")
+        .unwrap()
+        .configure("CxxManualSource", source)
+        .configure("CxxSink", sink)
+        .configure("CxxManualSource", traced.clone())
+        .configure("CxxFizz", traced.clone())
+        .configure("CxxSink", traced)
+        .map_all("local");
+    let _runtime = spawn_runtime(&graph, "local").await.unwrap();
+    wait_for_lines(&output, EXPECTED_MESSAGES, TIMEOUT).await;
+
+    let spans = spans(&trace);
+    let trace_ids = |service: &str| -> HashSet<String> {
+        spans
+            .iter()
+            .filter(|(s, span)| s == service && span["name"] == "run")
+            .map(|(_, span)| span["traceId"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    // A sample is followed, in a single trace, from the source to the sink.
+    let source = trace_ids("zenoh-flow-cxx-manual-source");
+    let fizz = trace_ids("zenoh-flow-cxx-fizz");
+    let sink = trace_ids("zenoh-flow-cxx-sink");
+    assert!(
+        sink.iter()
+            .any(|id| source.contains(id) && fizz.contains(id)),
+        "No trace through the whole graph: {:?}",
+        spans
+    );
+
+    // The span added by the C++ operator is a child of the span of its `run`.
+    let runs: HashSet<&str> = spans
+        .iter()
+        .filter(|(s, span)| s == "zenoh-flow-cxx-fizz" && span["name"] == "run")
+        .map(|(_, span)| span["spanId"].as_str().unwrap())
+        .collect();
+    assert!(spans.iter().any(|(_, span)| span["name"] == "fizzbuzz"
+        && runs.contains(span["parentSpanId"].as_str().unwrap_or_default())));
+}
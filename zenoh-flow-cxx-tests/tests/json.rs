@@ -0,0 +1,69 @@
+//! The JSON and CBOR payloads of C++ components against the ones of Rust components.
+#![cfg(feature = "json")]
+
+use serde_json::{json, Value};
+use zenoh_flow_cxx_tests::json::ffi;
+
+fn values() -> Vec<Value> {
+    vec![
+        json!(null),
+        json!(true),
+        json!(0),
+        json!(-1),
+        json!(u64::MAX),
+        json!(i64::MIN),
+        json!(1.5),
+        json!(-0.1),
+        json!(1e300),
+        json!(""),
+        json!("fizz \"buzz\"\n\u{e9}\u{1f600}"),
+        json!([]),
+        json!({}),
+        json!([1, "two", [3.5, null], {"four": false}]),
+        json!({"speed": 1.5, "frames": [42], "nested": {"a": {"b": [true]}}}),
+    ]
+}
+
+fn cbor(value: &Value) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    ciborium::ser::into_writer(value, &mut bytes).unwrap();
+    bytes
+}
+
+fn from_cbor(bytes: &[u8]) -> Value {
+    ciborium::de::from_reader(bytes).unwrap()
+}
+
+#[test]
+fn json_round_trips() {
+    for value in values() {
+        let bytes = ffi::json(&serde_json::to_vec(&value).unwrap()).unwrap();
+        assert_eq!(serde_json::from_slice::<Value>(&bytes).unwrap(), value);
+    }
+}
+
+#[test]
+fn cbor_round_trips() {
+    for value in values() {
+        let bytes = ffi::cbor(&cbor(&value)).unwrap();
+        assert_eq!(from_cbor(&bytes), value);
+    }
+}
+
+#[test]
+fn rust_reads_cxx_payloads() {
+    let command = json!({"speed": 1.5, "frames": [42], "name": "fizz"});
+    let text = ffi::command(false);
+    assert_eq!(serde_json::from_slice::<Value>(&text).unwrap(), command);
+    assert_eq!(from_cbor(&ffi::command(true)), command);
+}
+
+#[test]
+fn cxx_refuses_invalid_payloads() {
+    assert!(ffi::json(b"{\"speed\": ").is_err());
+    assert!(ffi::json(b"[1] 2").is_err());
+    assert!(ffi::json(b"\xff").is_err());
+    // A map of one entry, truncated.
+    assert!(ffi::cbor(&[0xa1, 0x61]).is_err());
+    assert!(ffi::cbor(&[]).is_err());
+}
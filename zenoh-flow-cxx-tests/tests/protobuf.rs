@@ -0,0 +1,75 @@
+//! The Protocol Buffers payloads of C++ components against the ones of a `prost` component.
+#![cfg(feature = "protobuf")]
+
+use zenoh_flow::Data;
+use zenoh_flow_cxx_common::protobuf::{self, Protobuf};
+use zenoh_flow_cxx_tests::protobuf::ffi;
+
+/// `sensors.Imu` of `proto/sensors.proto`, as `prost-build` would generate it.
+#[derive(Clone, PartialEq, prost::Message)]
+struct Imu {
+    #[prost(double, tag = "1")]
+    x: f64,
+    #[prost(double, tag = "2")]
+    y: f64,
+    #[prost(double, tag = "3")]
+    z: f64,
+    #[prost(string, tag = "4")]
+    frame: String,
+    #[prost(uint64, repeated, tag = "5")]
+    samples: Vec<u64>,
+}
+
+fn readings() -> Vec<Imu> {
+    vec![
+        Imu::default(),
+        Imu {
+            x: 0.5,
+            y: -9.81,
+            z: 1e-3,
+            frame: "base_link".to_string(),
+            samples: vec![1, u64::MAX],
+        },
+        Imu {
+            x: f64::MAX,
+            y: f64::MIN_POSITIVE,
+            z: -0.0,
+            frame: "fizz \"buzz\"\n\u{e9}\u{1f600}".to_string(),
+            samples: (0..1000).collect(),
+        },
+    ]
+}
+
+#[test]
+fn messages_round_trip() {
+    for imu in readings() {
+        let bytes = Protobuf(imu.clone()).try_serialize().unwrap();
+        let bytes = ffi::imu(&bytes).unwrap();
+        assert_eq!(protobuf::decode::<Imu>(&bytes).unwrap(), imu);
+    }
+}
+
+#[test]
+fn rust_reads_cxx_messages() {
+    let imu = protobuf::decode::<Imu>(&ffi::reading()).unwrap();
+    assert_eq!(imu, readings()[1]);
+}
+
+#[test]
+fn cxx_refuses_invalid_messages() {
+    // The field 1 (a double), truncated.
+    assert!(ffi::imu(&[0x09, 0x00, 0x00]).is_err());
+    // The field 4 (a string) of 16 bytes, missing.
+    assert!(ffi::imu(&[0x22, 0x10]).is_err());
+}
+
+#[test]
+fn port_types_name_the_messages() {
+    assert!(ffi::is_imu("protobuf:sensors.Imu"));
+    assert!(!ffi::is_imu("protobuf:sensors.Gps"));
+    assert!(!ffi::is_imu("sensors.Imu"));
+    assert_eq!(
+        protobuf::message_type("protobuf:sensors.Imu"),
+        Some("sensors.Imu")
+    );
+}